@@ -0,0 +1,151 @@
+//! Measures the overhead ringbahn adds to each event on top of io-uring.
+//!
+//! Each loop submits batches of no-op events and waits for all of them to complete. The first
+//! uses io-uring directly; the second prepares each event through a `Ring` and completes it
+//! through ringbahn's completions, so the difference between them is ringbahn's per-event cost.
+//! The third is a baseline for those completions: it tracks each event with the heap allocated,
+//! mutex protected state ringbahn used before completions were allocated from a slab.
+//!
+//! Run with `cargo bench --bench completion`.
+
+use std::io;
+use std::mem;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use futures::task::noop_waker;
+use iou::{IoUring, SQEs};
+
+use ringbahn::drive::{self, Completion, Drive};
+use ringbahn::ring::Ring;
+
+const BATCH: usize = 64;
+const ROUNDS: usize = 5000;
+
+/// A driver which leaves submitting and completing events to the benchmark loop.
+#[derive(Clone)]
+struct Manual(Arc<Mutex<IoUring>>);
+
+impl Drive for Manual {
+    fn poll_prepare<'cx>(
+        self: Pin<&mut Self>,
+        ctx: &mut Context<'cx>,
+        count: u32,
+        prepare: impl FnOnce(SQEs<'_>, &mut Context<'cx>) -> Completion<'cx>,
+    ) -> Poll<Completion<'cx>> {
+        let mut ring = self.0.lock().unwrap();
+        Poll::Ready(prepare(ring.prepare_sqes(count).unwrap(), ctx))
+    }
+
+    fn poll_submit(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<u32>> {
+        Poll::Ready(Ok(0))
+    }
+}
+
+fn raw(ring: &mut IoUring) {
+    for _ in 0..BATCH {
+        unsafe { ring.prepare_sqe().unwrap().prep_nop(); }
+    }
+    ring.submit_sqes_and_wait(BATCH as u32).unwrap();
+    assert_eq!(ring.cqes().count(), BATCH);
+}
+
+fn ringbahn(driver: &Manual, rings: &mut [Ring<Manual>], ctx: &mut Context<'_>) {
+    for ring in rings.iter_mut() {
+        let poll = Pin::new(ring).poll(ctx, 1, |sqs| {
+            let mut sqe = sqs.single().unwrap();
+            unsafe { sqe.prep_nop(); }
+            sqe
+        });
+        assert!(poll.is_pending());
+    }
+
+    let mut ring = driver.0.lock().unwrap();
+    ring.submit_sqes_and_wait(BATCH as u32).unwrap();
+    for cqe in ring.cqes() {
+        drive::complete(cqe);
+    }
+    drop(ring);
+
+    for ring in rings.iter_mut() {
+        assert!(Pin::new(ring).poll(ctx, 1, |_| unreachable!()).is_ready());
+    }
+}
+
+// The state of an event in the mutex based completions.
+enum State {
+    Submitted(Waker),
+    Completed(io::Result<u32>),
+    Empty,
+}
+
+// Events are prepared through the driver one at a time, as they are by a `Ring`.
+fn mutex(driver: &Manual, states: &mut Vec<*mut parking_lot::Mutex<State>>, ctx: &mut Context<'_>) {
+    for _ in 0..BATCH {
+        let state = Box::new(parking_lot::Mutex::new(State::Submitted(ctx.waker().clone())));
+        let state = Box::into_raw(state);
+        unsafe {
+            let mut ring = driver.0.lock().unwrap();
+            let mut sqe = ring.prepare_sqe().unwrap();
+            sqe.prep_nop();
+            sqe.set_user_data(state as u64);
+        }
+        states.push(state);
+    }
+
+    let mut ring = driver.0.lock().unwrap();
+    ring.submit_sqes_and_wait(BATCH as u32).unwrap();
+    for cqe in ring.cqes() {
+        let state = unsafe { &*(cqe.user_data() as *const parking_lot::Mutex<State>) };
+        let mut state = state.lock();
+        if let State::Submitted(waker) = mem::replace(&mut *state, State::Empty) {
+            *state = State::Completed(cqe.result());
+            waker.wake();
+        }
+    }
+
+    drop(ring);
+
+    for state in states.drain(..) {
+        let state = unsafe { Box::from_raw(state) };
+        let result = mem::replace(&mut *state.lock(), State::Empty);
+        assert!(matches!(result, State::Completed(Ok(_))));
+    }
+}
+
+fn measure(mut f: impl FnMut()) -> Duration {
+    // warm up, so that the slab has allocated all of the slots the loop needs
+    for _ in 0..ROUNDS / 10 {
+        f();
+    }
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        f();
+    }
+    start.elapsed()
+}
+
+fn per_event(elapsed: Duration) -> f64 {
+    elapsed.as_nanos() as f64 / (ROUNDS * BATCH) as f64
+}
+
+fn main() {
+    let mut uring = IoUring::new(BATCH as u32).unwrap();
+    let raw = per_event(measure(|| raw(&mut uring)));
+
+    let driver = Manual(Arc::new(Mutex::new(IoUring::new(BATCH as u32).unwrap())));
+    let mut rings: Vec<_> = (0..BATCH).map(|_| Ring::new(driver.clone())).collect();
+    let waker = noop_waker();
+    let mut ctx = Context::from_waker(&waker);
+    let ringbahn = per_event(measure(|| ringbahn(&driver, &mut rings, &mut ctx)));
+
+    let mut states = Vec::with_capacity(BATCH);
+    let mutex = per_event(measure(|| mutex(&driver, &mut states, &mut ctx)));
+
+    println!("io-uring:  {:>8.1} ns/event", raw);
+    println!("ringbahn:  {:>8.1} ns/event", ringbahn);
+    println!("mutex:     {:>8.1} ns/event", mutex);
+    println!("overhead:  {:>8.1} ns/event (mutex: {:.1})", ringbahn - raw, mutex - raw);
+}
@@ -0,0 +1,114 @@
+//! A small thread pool for syscalls which cannot be submitted to io-uring on every kernel.
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use parking_lot::{Condvar, Mutex};
+
+use crate::signal;
+
+const MAX_THREADS: usize = 16;
+const IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+type Job = Box<dyn FnOnce() + Send>;
+
+struct Pool {
+    state: Mutex<PoolState>,
+    condvar: Condvar,
+}
+
+struct PoolState {
+    jobs: VecDeque<Job>,
+    threads: usize,
+    idle: usize,
+}
+
+static POOL: Lazy<Pool> = Lazy::new(|| Pool {
+    state: Mutex::new(PoolState { jobs: VecDeque::new(), threads: 0, idle: 0 }),
+    condvar: Condvar::new(),
+});
+
+/// Run a blocking function on the thread pool, returning a future of its result.
+///
+/// Threads are started as they are needed, up to a limit, and exit after they have been idle for
+/// a while.
+pub(crate) fn spawn<T, F>(f: F) -> Blocking<T> where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let shared = Arc::new(Mutex::new(Shared { result: None, waker: None }));
+    let job = {
+        let shared = shared.clone();
+        Box::new(move || {
+            let result = f();
+            let mut shared = shared.lock();
+            shared.result = Some(result);
+            if let Some(waker) = shared.waker.take() {
+                waker.wake();
+            }
+        })
+    };
+
+    let mut state = POOL.state.lock();
+    state.jobs.push_back(job);
+    if state.idle == 0 && state.threads < MAX_THREADS {
+        state.threads += 1;
+        signal::spawn_thread(work);
+    } else {
+        POOL.condvar.notify_one();
+    }
+
+    Blocking { shared }
+}
+
+fn work() {
+    let mut state = POOL.state.lock();
+    loop {
+        if let Some(job) = state.jobs.pop_front() {
+            drop(state);
+            job();
+            state = POOL.state.lock();
+            continue;
+        }
+
+        state.idle += 1;
+        let timed_out = POOL.condvar.wait_for(&mut state, IDLE_TIMEOUT).timed_out();
+        state.idle -= 1;
+        if timed_out && state.jobs.is_empty() {
+            state.threads -= 1;
+            return;
+        }
+    }
+}
+
+struct Shared<T> {
+    result: Option<T>,
+    waker: Option<Waker>,
+}
+
+/// A future of the result of a function run on the thread pool.
+///
+/// Dropping this future does not stop the function from running.
+pub(crate) struct Blocking<T> {
+    shared: Arc<Mutex<Shared<T>>>,
+}
+
+impl<T> Future for Blocking<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<T> {
+        let mut shared = self.shared.lock();
+        match shared.result.take() {
+            Some(result)    => Poll::Ready(result),
+            None            => {
+                shared.waker = Some(ctx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use std::alloc::{self, Layout};
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+use std::slice;
+
+use crate::ring::{Cancel, CancelNarrow};
+
+/// A zeroed buffer whose address is aligned to a power of two, such as the buffers needed for
+/// IO on files opened with `O_DIRECT`
+pub struct AlignedBuf {
+    ptr: NonNull<u8>,
+    len: usize,
+    align: usize,
+}
+
+unsafe impl Send for AlignedBuf { }
+unsafe impl Sync for AlignedBuf { }
+
+impl AlignedBuf {
+    /// Allocate a zeroed buffer of `len` bytes, aligned to `align` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `align` is not a power of two, or if `len` rounded up to `align` overflows.
+    pub fn new(len: usize, align: usize) -> AlignedBuf {
+        let layout = Layout::from_size_align(len, align).expect("invalid buffer alignment");
+        let ptr = match len {
+            // The allocator does not allow zero sized allocations.
+            0   => NonNull::new(align as *mut u8).unwrap(),
+            _   => {
+                let ptr = unsafe { alloc::alloc_zeroed(layout) };
+                NonNull::new(ptr).unwrap_or_else(|| alloc::handle_alloc_error(layout))
+            }
+        };
+        AlignedBuf { ptr, len, align }
+    }
+
+    /// The alignment of the buffer's address
+    pub fn align(&self) -> usize {
+        self.align
+    }
+}
+
+impl Deref for AlignedBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl DerefMut for AlignedBuf {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl fmt::Debug for AlignedBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AlignedBuf").field("len", &self.len).field("align", &self.align).finish()
+    }
+}
+
+impl Drop for AlignedBuf {
+    fn drop(&mut self) {
+        if self.len != 0 {
+            unsafe {
+                let layout = Layout::from_size_align_unchecked(self.len, self.align);
+                alloc::dealloc(self.ptr.as_ptr(), layout);
+            }
+        }
+    }
+}
+
+// The layout is needed to deallocate the buffer, so it is boxed along with the pointer.
+unsafe impl Cancel for AlignedBuf {
+    fn into_raw(self) -> (*mut (), usize) {
+        Box::new(self).into_raw()
+    }
+
+    unsafe fn drop_raw(data: *mut (), metadata: usize) {
+        Box::<AlignedBuf>::drop_raw(data, metadata)
+    }
+}
+
+unsafe impl CancelNarrow for AlignedBuf { }
@@ -0,0 +1,313 @@
+use std::alloc::{self, Layout};
+use std::io;
+use std::mem::{self, ManuallyDrop};
+use std::os::unix::io::RawFd;
+use std::pin::Pin;
+use std::ptr::{self, NonNull};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::task::{Context, Poll};
+
+use futures_core::{ready, Stream};
+use iou::SQE;
+use iou::sqe::{BufferGroupId, MsgFlags, SubmissionFlags};
+use parking_lot::Mutex;
+
+use crate::drive::Drive;
+use crate::event::{Event, RecvMulti};
+use crate::ring::{Cancel, CancelNarrow, Cancellation, Ring};
+
+use super::{LeasedBuffer, Pool};
+
+const IORING_REGISTER_PBUF_RING: libc::c_uint = 22;
+const IORING_UNREGISTER_PBUF_RING: libc::c_uint = 23;
+
+const PAGE_SIZE: usize = 4096;
+
+#[repr(C)]
+#[allow(non_camel_case_types)]
+struct io_uring_buf {
+    addr: u64,
+    len: u32,
+    bid: u16,
+    resv: u16,
+}
+
+#[repr(C)]
+#[allow(non_camel_case_types)]
+struct io_uring_buf_reg {
+    ring_addr: u64,
+    ring_entries: u32,
+    bgid: u16,
+    flags: u16,
+    resv: [u64; 3],
+}
+
+/// A ring of buffers which is shared with the kernel for buffer selection.
+///
+/// Like a [`BufferGroup`](super::BufferGroup), a buffer ring is a pool of buffers which the kernel
+/// selects from when completing events like [`RecvMulti`](crate::event::RecvMulti). But instead
+/// of providing buffers to the kernel by submitting events, buffers are recycled by writing them
+/// to a ring mapped into the kernel and bumping its tail, which requires no syscalls at all.
+///
+/// The ring is registered with a single io-uring instance when it is constructed, and unregistered
+/// when the last handle to it is dropped. As with buffer groups, if an event is cancelled after the
+/// kernel selected a buffer for it, that buffer will not be returned to the ring.
+#[derive(Clone)]
+pub struct BufRing {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    ring_fd: RawFd,
+    id: u16,
+    entries: u16,
+    len: u32,
+    ring: NonNull<io_uring_buf>,
+    data: NonNull<u8>,
+    tail: Mutex<u16>,
+}
+
+unsafe impl Send for Inner { }
+unsafe impl Sync for Inner { }
+
+impl BufRing {
+    /// Register a ring of `entries` buffers, each `len` bytes long, with an io-uring instance.
+    ///
+    /// `entries` must be a power of two no greater than 32768. Drivers expose this through
+    /// [`Drive::register_buf_ring`], which should be preferred to calling it directly.
+    ///
+    /// # Safety
+    ///
+    /// `ring_fd` must be the file descriptor of an io-uring instance which outlives the returned
+    /// `BufRing` and all of its clones.
+    pub unsafe fn register(ring_fd: RawFd, entries: u16, len: u32) -> io::Result<BufRing> {
+        assert!(entries.is_power_of_two() && entries <= 1 << 15, "invalid buffer ring size");
+        assert!(len > 0, "buffer rings cannot have empty buffers");
+
+        let ring = alloc::alloc_zeroed(ring_layout(entries)) as *mut io_uring_buf;
+        let data = alloc::alloc_zeroed(data_layout(entries, len));
+        let inner = Inner {
+            ring: NonNull::new(ring).expect("failed to allocate buffer ring"),
+            data: NonNull::new(data).expect("failed to allocate buffer ring"),
+            tail: Mutex::new(0),
+            id: super::next_group_id(),
+            ring_fd, entries, len,
+        };
+
+        let reg = io_uring_buf_reg {
+            ring_addr: ring as u64,
+            ring_entries: entries as u32,
+            bgid: inner.id,
+            flags: 0,
+            resv: [0; 3],
+        };
+        let arg = &reg as *const io_uring_buf_reg as *const libc::c_void;
+        if uring_sys::syscalls::io_uring_register(ring_fd, IORING_REGISTER_PBUF_RING, arg, 1) < 0 {
+            let err = io::Error::last_os_error();
+            // Don't try to unregister a ring that was never registered.
+            let inner = mem::ManuallyDrop::new(inner);
+            inner.free();
+            return Err(err);
+        }
+
+        let ring = BufRing { inner: Arc::new(inner) };
+        {
+            let mut tail = ring.inner.tail.lock();
+            for index in 0..entries {
+                ring.push(&mut tail, index);
+            }
+            ring.publish(*tail);
+        }
+        Ok(ring)
+    }
+
+    /// The id of the buffer group this ring is registered as.
+    pub fn id(&self) -> BufferGroupId {
+        BufferGroupId { id: self.inner.id as u32 }
+    }
+
+    /// The length of each buffer in this ring.
+    pub fn buffer_len(&self) -> u32 {
+        self.inner.len
+    }
+
+    /// The number of buffers in this ring.
+    pub fn entries(&self) -> u16 {
+        self.inner.entries
+    }
+
+    /// Receive from a socket into buffers selected from this ring, as a stream.
+    ///
+    /// A single multishot recv is submitted, and each buffer the kernel fills is yielded as it
+    /// completes. The stream ends when the peer shuts down the connection. If the kernel stops
+    /// receiving because of an error (for example, because the ring has run out of buffers), the
+    /// error is yielded and the recv is submitted again the next time the stream is polled.
+    pub fn recv_multishot<D: Drive>(&self, fd: RawFd, flags: MsgFlags, driver: D)
+        -> RecvMultishot<D>
+    {
+        RecvMultishot {
+            ring: Ring::new(driver),
+            event: ManuallyDrop::new(RecvMulti::new(fd, self.clone(), flags)),
+            done: false,
+        }
+    }
+
+    /// Configure an SQE which has already been prepared to select a buffer from this ring.
+    pub(crate) unsafe fn select(&self, sqe: &mut SQE<'_>) {
+        let raw = sqe.raw_mut();
+        raw.addr = 0;
+        raw.buf_index.buf_index.index_or_group = self.inner.id;
+        sqe.set_flags(SubmissionFlags::BUFFER_SELECT);
+    }
+
+    /// Lease the buffer the kernel selected, as reported by the flags of a completion.
+    pub(crate) fn lease(&self, flags: u32) -> Option<LeasedBuffer> {
+        let index = super::selected_buffer(flags)?;
+        debug_assert!(index < self.inner.entries);
+        Some(LeasedBuffer::new(Pool::Ring(self.clone()), index, self.inner.len as usize))
+    }
+
+    pub(super) fn recycle(&self, index: u16) {
+        let mut tail = self.inner.tail.lock();
+        self.push(&mut tail, index);
+        self.publish(*tail);
+    }
+
+    pub(super) fn buffer(&self, index: u16) -> *mut u8 {
+        unsafe { self.inner.data.as_ptr().add(index as usize * self.inner.len as usize) }
+    }
+
+    // Write a buffer into the ring at the tail, without making it visible to the kernel.
+    fn push(&self, tail: &mut u16, index: u16) {
+        let mask = self.inner.entries - 1;
+        unsafe {
+            // Fields are written individually because the kernel reads the tail of the ring
+            // from the reserved field of the first entry.
+            let entry = self.inner.ring.as_ptr().add((*tail & mask) as usize);
+            ptr::addr_of_mut!((*entry).addr).write(self.buffer(index) as u64);
+            ptr::addr_of_mut!((*entry).len).write(self.inner.len);
+            ptr::addr_of_mut!((*entry).bid).write(index);
+        }
+        *tail = tail.wrapping_add(1);
+    }
+
+    // Make all of the buffers written before the tail visible to the kernel.
+    fn publish(&self, tail: u16) {
+        unsafe {
+            let tail_ptr = ptr::addr_of_mut!((*self.inner.ring.as_ptr()).resv);
+            (*(tail_ptr as *const AtomicU16)).store(tail, Ordering::Release);
+        }
+    }
+}
+
+impl Inner {
+    unsafe fn free(&self) {
+        alloc::dealloc(self.ring.as_ptr() as *mut u8, ring_layout(self.entries));
+        alloc::dealloc(self.data.as_ptr(), data_layout(self.entries, self.len));
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        let reg = io_uring_buf_reg {
+            ring_addr: 0,
+            ring_entries: 0,
+            bgid: self.id,
+            flags: 0,
+            resv: [0; 3],
+        };
+        let arg = &reg as *const io_uring_buf_reg as *const libc::c_void;
+        unsafe {
+            let result = uring_sys::syscalls::io_uring_register(
+                self.ring_fd,
+                IORING_UNREGISTER_PBUF_RING,
+                arg,
+                1,
+            );
+            // If the ring could not be unregistered, the kernel may still write into the buffers,
+            // so they must be leaked.
+            if result >= 0 {
+                self.free();
+            }
+        }
+    }
+}
+
+/// A stream of buffers received by a multishot recv.
+///
+/// Returned by [`BufRing::recv_multishot`]. Dropping the stream before it ends submits an async
+/// cancel for the recv, so that the kernel stops consuming data and buffers for it.
+pub struct RecvMultishot<D: Drive> {
+    ring: Ring<D>,
+    event: ManuallyDrop<RecvMulti>,
+    done: bool,
+}
+
+impl<D: Drive> RecvMultishot<D> {
+    fn split(self: Pin<&mut Self>) -> (Pin<&mut Ring<D>>, &mut RecvMulti, &mut bool) {
+        unsafe {
+            let this = Pin::get_unchecked_mut(self);
+            (Pin::new_unchecked(&mut this.ring), &mut this.event, &mut this.done)
+        }
+    }
+}
+
+impl<D: Drive> Stream for RecvMultishot<D> {
+    type Item = io::Result<LeasedBuffer>;
+
+    fn poll_next(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let (ring, event, done) = self.split();
+        if *done {
+            return Poll::Ready(None);
+        }
+
+        let (result, flags) = ready!(ring.poll_with_flags(ctx, 1, |sqs| unsafe {
+            event.prepare(sqs)
+        }));
+        event.complete(&result, flags);
+        match result {
+            Ok(n)   => match event.buf.take() {
+                Some(mut buf)   => {
+                    buf.truncate(n as usize);
+                    Poll::Ready(Some(Ok(buf)))
+                }
+                None            => {
+                    *done = true;
+                    Poll::Ready(None)
+                }
+            }
+            Err(e)  => Poll::Ready(Some(Err(e))),
+        }
+    }
+}
+
+impl<D: Drive> Drop for RecvMultishot<D> {
+    fn drop(&mut self) {
+        // The ring is never moved out of the stream, which was pinned to be polled.
+        let ring = unsafe { Pin::new_unchecked(&mut self.ring) };
+        ring.cancel_now(Cancellation::from(self.event.ring.clone()));
+        unsafe { ManuallyDrop::drop(&mut self.event); }
+    }
+}
+
+fn ring_layout(entries: u16) -> Layout {
+    let size = entries as usize * mem::size_of::<io_uring_buf>();
+    Layout::from_size_align(size, PAGE_SIZE).unwrap()
+}
+
+fn data_layout(entries: u16, len: u32) -> Layout {
+    Layout::from_size_align(entries as usize * len as usize, 1).unwrap()
+}
+
+unsafe impl Cancel for BufRing {
+    fn into_raw(self) -> (*mut (), usize) {
+        (Arc::into_raw(self.inner) as *mut (), 0)
+    }
+
+    unsafe fn drop_raw(data: *mut (), _: usize) {
+        drop(Arc::from_raw(data as *const Inner));
+    }
+}
+
+unsafe impl CancelNarrow for BufRing { }
@@ -0,0 +1,206 @@
+use std::fmt;
+use std::io;
+use std::mem::ManuallyDrop;
+use std::ops::{Deref, DerefMut};
+use std::os::unix::io::RawFd;
+use std::ptr::NonNull;
+use std::slice;
+use std::sync::Arc;
+
+use iou::SQE;
+use parking_lot::Mutex;
+use uring_sys::IoRingOp;
+
+use crate::ring::Cancel;
+
+const IORING_REGISTER_BUFFERS: libc::c_uint = 0;
+const IORING_UNREGISTER_BUFFERS: libc::c_uint = 1;
+
+const IORING_OP_READ_FIXED: u8 = 4;
+const IORING_OP_WRITE_FIXED: u8 = 5;
+
+/// A pool of buffers registered with an io-uring instance, for reads and writes with
+/// `READ_FIXED` and `WRITE_FIXED`.
+///
+/// Registered buffers are mapped into the kernel once, rather than on every event. A driver can
+/// offer a pool through [`Drive::fixed_pool`](crate::Drive::fixed_pool), in which case IO
+/// handles like [`File`](crate::fs::File) lease their internal buffers from it while they hold
+/// data or have IO in flight, and fall back to ordinary buffers while every buffer in the pool is
+/// leased.
+///
+/// An io-uring instance can only have one set of registered buffers, so the pool is registered
+/// when it is constructed and unregistered when the last handle to it is dropped.
+#[derive(Clone)]
+pub struct FixedPool {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    ring_fd: RawFd,
+    len: u32,
+    count: u16,
+    data: NonNull<u8>,
+    free: Mutex<Vec<u16>>,
+}
+
+unsafe impl Send for Inner { }
+unsafe impl Sync for Inner { }
+
+impl FixedPool {
+    /// Register a pool of `count` buffers, each `len` bytes long, with an io-uring instance.
+    ///
+    /// # Safety
+    ///
+    /// `ring_fd` must be the file descriptor of an io-uring instance which outlives the returned
+    /// `FixedPool` and all of its clones, and its buffers must only be used with that instance.
+    pub unsafe fn register(ring_fd: RawFd, count: u16, len: u32) -> io::Result<FixedPool> {
+        assert!(count > 0 && len > 0, "fixed buffer pools cannot be empty");
+
+        let data = vec![0u8; count as usize * len as usize].into_boxed_slice();
+        let data = NonNull::new_unchecked(Box::into_raw(data) as *mut u8);
+        let free = Mutex::new((0..count).rev().collect());
+        let inner = Inner { ring_fd, len, count, data, free };
+
+        let iovecs: Vec<libc::iovec> = (0..count).map(|index| libc::iovec {
+            iov_base: inner.buffer(index) as *mut libc::c_void,
+            iov_len: len as usize,
+        }).collect();
+        let arg = iovecs.as_ptr() as *const libc::c_void;
+        let result = uring_sys::syscalls::io_uring_register(
+            ring_fd,
+            IORING_REGISTER_BUFFERS,
+            arg,
+            count as libc::c_uint,
+        );
+        if result < 0 {
+            let err = io::Error::last_os_error();
+            // Don't try to unregister buffers that were never registered.
+            ManuallyDrop::new(inner).free();
+            return Err(err);
+        }
+
+        Ok(FixedPool { inner: Arc::new(inner) })
+    }
+
+    /// The length of each buffer in this pool.
+    pub fn buffer_len(&self) -> u32 {
+        self.inner.len
+    }
+
+    /// The number of buffers in this pool.
+    pub fn count(&self) -> u16 {
+        self.inner.count
+    }
+
+    /// Lease a buffer from the pool, if any are free.
+    pub fn lease(&self) -> Option<FixedBuf> {
+        let index = self.inner.free.lock().pop()?;
+        Some(FixedBuf { pool: self.clone(), index })
+    }
+}
+
+impl fmt::Debug for FixedPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FixedPool")
+            .field("buffer_len", &self.inner.len)
+            .field("count", &self.inner.count)
+            .finish()
+    }
+}
+
+impl Inner {
+    fn buffer(&self, index: u16) -> *mut u8 {
+        unsafe { self.data.as_ptr().add(index as usize * self.len as usize) }
+    }
+
+    unsafe fn free(&self) {
+        let len = self.count as usize * self.len as usize;
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(self.data.as_ptr(), len)));
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        unsafe {
+            let result = uring_sys::syscalls::io_uring_register(
+                self.ring_fd,
+                IORING_UNREGISTER_BUFFERS,
+                std::ptr::null(),
+                0,
+            );
+            // If the buffers could not be unregistered, the kernel may still write into them, so
+            // they must be leaked.
+            if result >= 0 {
+                self.free();
+            }
+        }
+    }
+}
+
+/// Turn an SQE prepared as a read or write of a range within the registered buffer `index` into a
+/// `READ_FIXED` or `WRITE_FIXED`.
+pub(crate) unsafe fn fix(sqe: &mut SQE<'_>, index: u16) {
+    let raw = sqe.raw_mut();
+    raw.opcode = match raw.opcode {
+        op if op == IoRingOp::IORING_OP_READ as u8  => IORING_OP_READ_FIXED,
+        op if op == IoRingOp::IORING_OP_WRITE as u8 => IORING_OP_WRITE_FIXED,
+        _                                           => unreachable!(),
+    };
+    raw.buf_index.buf_index.index_or_group = index;
+}
+
+/// A buffer leased from a [`FixedPool`].
+///
+/// The buffer is returned to the pool when it is dropped.
+pub struct FixedBuf {
+    pool: FixedPool,
+    index: u16,
+}
+
+impl FixedBuf {
+    /// The index of this buffer among the buffers registered with the kernel.
+    pub fn index(&self) -> u16 {
+        self.index
+    }
+}
+
+impl Deref for FixedBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        let len = self.pool.inner.len as usize;
+        unsafe { slice::from_raw_parts(self.pool.inner.buffer(self.index), len) }
+    }
+}
+
+impl DerefMut for FixedBuf {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        let len = self.pool.inner.len as usize;
+        unsafe { slice::from_raw_parts_mut(self.pool.inner.buffer(self.index), len) }
+    }
+}
+
+impl fmt::Debug for FixedBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FixedBuf").field("index", &self.index).finish()
+    }
+}
+
+impl Drop for FixedBuf {
+    fn drop(&mut self) {
+        self.pool.inner.free.lock().push(self.index);
+    }
+}
+
+unsafe impl Cancel for FixedBuf {
+    fn into_raw(self) -> (*mut (), usize) {
+        let this = ManuallyDrop::new(self);
+        let inner = unsafe { std::ptr::read(&this.pool.inner) };
+        (Arc::into_raw(inner) as *mut (), this.index as usize)
+    }
+
+    unsafe fn drop_raw(data: *mut (), index: usize) {
+        let pool = FixedPool { inner: Arc::from_raw(data as *const Inner) };
+        drop(FixedBuf { pool, index: index as u16 });
+    }
+}
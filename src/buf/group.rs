@@ -0,0 +1,173 @@
+use std::mem::ManuallyDrop;
+use std::ptr::{self, NonNull};
+use std::slice;
+use std::sync::Arc;
+
+use iou::{SQE, SQEs};
+use iou::sqe::{BufferGroupId, SubmissionFlags};
+use parking_lot::Mutex;
+
+use crate::ring::{Cancel, CancelComplete, CancelNarrow};
+
+use super::{LeasedBuffer, Pool};
+
+/// A pool of buffers which the kernel selects from when completing reads.
+///
+/// Instead of passing a buffer with each read, events like
+/// [`ReadSelect`](crate::event::ReadSelect) and [`RecvSelect`](crate::event::RecvSelect) name a
+/// buffer group, and the kernel picks a buffer from that group only once data is ready. This
+/// allows many idle IO objects to share a small amount of memory.
+///
+/// Buffers the kernel has selected are handed out as [`LeasedBuffer`]s. When a leased buffer is
+/// dropped, it is returned to the group, and provided back to the kernel alongside the next read
+/// submitted against the group. The buffers are provided to the kernel lazily in the same way, so
+/// constructing a group performs no IO.
+///
+/// A group must only be used with a single io-uring instance. Group ids are never reused, so a
+/// process can construct at most 65536 buffer groups and buffer rings. If an event is cancelled
+/// after the kernel selected a buffer for it, that buffer is returned to the group once the event
+/// completes.
+#[derive(Clone)]
+pub struct BufferGroup {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    id: u16,
+    len: u32,
+    count: u16,
+    data: NonNull<u8>,
+    returned: Mutex<Vec<u16>>,
+}
+
+unsafe impl Send for Inner { }
+unsafe impl Sync for Inner { }
+
+impl BufferGroup {
+    /// Construct a group of `count` buffers, each `len` bytes long.
+    pub fn new(count: u16, len: u32) -> BufferGroup {
+        assert!(count > 0 && len > 0, "buffer groups cannot be empty");
+        let id = super::next_group_id();
+
+        let data = vec![0u8; count as usize * len as usize].into_boxed_slice();
+        let data = unsafe { NonNull::new_unchecked(Box::into_raw(data) as *mut u8) };
+
+        BufferGroup {
+            inner: Arc::new(Inner {
+                id,
+                returned: Mutex::new((0..count).collect()),
+                len, count, data,
+            })
+        }
+    }
+
+    /// The id of this group, to be passed to the kernel.
+    pub fn id(&self) -> BufferGroupId {
+        BufferGroupId { id: self.inner.id as u32 }
+    }
+
+    /// The length of each buffer in this group.
+    pub fn buffer_len(&self) -> u32 {
+        self.inner.len
+    }
+
+    /// The number of buffers in this group.
+    pub fn count(&self) -> u16 {
+        self.inner.count
+    }
+
+    /// The number of SQEs needed to prepare an event selecting a buffer from this group.
+    pub(crate) fn sqes_needed(&self) -> u32 {
+        if self.inner.returned.lock().is_empty() { 1 } else { 2 }
+    }
+
+    /// Prepare an event which selects a buffer from this group.
+    ///
+    /// If there are buffers which need to be provided to the kernel and there is room in `sqs`,
+    /// a `ProvideBuffers` event is hard linked ahead of the event prepared by `prepare`.
+    pub(crate) unsafe fn prepare<'sq>(
+        &self,
+        sqs: &mut SQEs<'sq>,
+        prepare: impl FnOnce(&mut SQE<'sq>),
+    ) -> SQE<'sq> {
+        if sqs.remaining() > 1 {
+            if let Some((index, count)) = self.take_returned() {
+                let mut sqe = sqs.hard_linked().next().unwrap();
+                let len = count as usize * self.inner.len as usize;
+                let bufs = slice::from_raw_parts_mut(self.buffer(index), len);
+                sqe.prep_provide_buffers(bufs, count as u32, self.id(), index as u32);
+            }
+        }
+
+        let mut sqe = sqs.single().unwrap();
+        prepare(&mut sqe);
+        let raw = sqe.raw_mut();
+        raw.addr = 0;
+        raw.len = self.inner.len;
+        raw.buf_index.buf_index.index_or_group = self.inner.id;
+        sqe.set_flags(SubmissionFlags::BUFFER_SELECT);
+        sqe
+    }
+
+    /// Lease the buffer the kernel selected, as reported by the flags of a completion.
+    pub(crate) fn lease(&self, flags: u32) -> Option<LeasedBuffer> {
+        let index = super::selected_buffer(flags)?;
+        debug_assert!(index < self.inner.count);
+        Some(LeasedBuffer::new(Pool::Group(self.clone()), index, self.inner.len as usize))
+    }
+
+    pub(super) fn recycle(&self, index: u16) {
+        self.inner.returned.lock().push(index);
+    }
+
+    // Take the lowest contiguous run of buffers which have been returned to the group.
+    fn take_returned(&self) -> Option<(u16, u16)> {
+        let mut returned = self.inner.returned.lock();
+        if returned.is_empty() {
+            return None;
+        }
+
+        returned.sort_unstable();
+        let first = returned[0];
+        let count = returned.iter().enumerate()
+                            .take_while(|&(n, &index)| index as usize == first as usize + n)
+                            .count();
+        returned.drain(..count);
+        Some((first, count as u16))
+    }
+
+    pub(super) fn buffer(&self, index: u16) -> *mut u8 {
+        unsafe { self.inner.data.as_ptr().add(index as usize * self.inner.len as usize) }
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        unsafe {
+            let len = self.count as usize * self.len as usize;
+            drop(Box::from_raw(ptr::slice_from_raw_parts_mut(self.data.as_ptr(), len)));
+        }
+    }
+}
+
+unsafe impl Cancel for BufferGroup {
+    fn into_raw(self) -> (*mut (), usize) {
+        (Arc::into_raw(self.inner) as *mut (), 0)
+    }
+
+    unsafe fn drop_raw(data: *mut (), _: usize) {
+        drop(Arc::from_raw(data as *const Inner));
+    }
+}
+
+unsafe impl CancelComplete for BufferGroup {
+    unsafe fn complete_raw(data: *mut (), _: usize, flags: u32) {
+        // The cancellation keeps its reference to the group until it is dropped.
+        let inner = ManuallyDrop::new(Arc::from_raw(data as *const Inner));
+        if let Some(index) = super::selected_buffer(flags) {
+            inner.returned.lock().push(index);
+        }
+    }
+}
+
+unsafe impl CancelNarrow for BufferGroup { }
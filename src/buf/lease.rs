@@ -0,0 +1,66 @@
+use std::ops::{Deref, DerefMut};
+use std::slice;
+
+use super::{BufferGroup, BufRing};
+
+pub(super) enum Pool {
+    Group(BufferGroup),
+    Ring(BufRing),
+}
+
+/// A buffer which the kernel selected from a [`BufferGroup`] or [`BufRing`] and filled.
+///
+/// The buffer is returned to the pool it was selected from when it is dropped.
+pub struct LeasedBuffer {
+    pool: Pool,
+    index: u16,
+    len: usize,
+}
+
+impl LeasedBuffer {
+    pub(super) fn new(pool: Pool, index: u16, len: usize) -> LeasedBuffer {
+        LeasedBuffer { pool, index, len }
+    }
+
+    /// The id of this buffer within its pool.
+    pub fn index(&self) -> u16 {
+        self.index
+    }
+
+    /// Shorten the buffer to `len` bytes, such as the number of bytes the kernel wrote to it.
+    ///
+    /// If `len` is greater than the current length of the buffer, this has no effect.
+    pub fn truncate(&mut self, len: usize) {
+        self.len = std::cmp::min(self.len, len);
+    }
+
+    fn ptr(&self) -> *mut u8 {
+        match &self.pool {
+            Pool::Group(group)  => group.buffer(self.index),
+            Pool::Ring(ring)    => ring.buffer(self.index),
+        }
+    }
+}
+
+impl Deref for LeasedBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr(), self.len) }
+    }
+}
+
+impl DerefMut for LeasedBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.ptr(), self.len) }
+    }
+}
+
+impl Drop for LeasedBuffer {
+    fn drop(&mut self) {
+        match &self.pool {
+            Pool::Group(group)  => group.recycle(self.index),
+            Pool::Ring(ring)    => ring.recycle(self.index),
+        }
+    }
+}
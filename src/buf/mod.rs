@@ -0,0 +1,212 @@
+//! Buffers for IO on io-uring
+
+mod aligned;
+mod buf_ring;
+mod fixed;
+mod group;
+mod lease;
+
+use std::cmp;
+use std::io;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::task::Poll;
+
+use either::Either;
+use futures_core::ready;
+
+use crate::ring::Cancellation;
+
+pub use aligned::AlignedBuf;
+pub use buf_ring::{BufRing, RecvMultishot};
+pub use fixed::{FixedBuf, FixedPool};
+pub(crate) use fixed::fix;
+pub use group::BufferGroup;
+pub use lease::LeasedBuffer;
+
+use lease::Pool;
+
+const IORING_CQE_F_BUFFER: u32 = 1 << 0;
+const IORING_CQE_BUFFER_SHIFT: u32 = 16;
+
+// Either an ordinary buffer, or one leased from a pool of registered buffers.
+type Data = Either<Box<[u8]>, FixedBuf>;
+
+static NEXT_GROUP_ID: AtomicU32 = AtomicU32::new(0);
+
+/// The capacity of the buffers IO handles use unless they are constructed with another.
+pub(crate) const DEFAULT_CAPACITY: usize = 4096 * 2;
+
+/// How many times larger than its capacity a buffer's single large read can be.
+pub(crate) const LARGE_READ_FACTOR: usize = 64;
+
+#[derive(Debug)]
+pub(crate) struct Buffer {
+    data: Option<Data>,
+    // The buffer of the usual capacity, while a larger one has been swapped in for a large read.
+    spare: Option<Data>,
+    capacity: usize,
+    // Buffers of the usual capacity are leased from this pool while it has any free, and returned
+    // to it once they hold no data and no IO is using them.
+    pool: Option<FixedPool>,
+    pos: u32,
+    cap: u32,
+    // A fill has started but not completed, so the kernel may be writing into the buffer.
+    filling: bool,
+}
+
+impl Default for Buffer {
+    fn default() -> Buffer {
+        Buffer::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+impl Buffer {
+    pub fn with_capacity(capacity: usize) -> Buffer {
+        Buffer::with_pool(capacity, None)
+    }
+
+    /// Construct a buffer which leases registered buffers from `pool` when they are the length
+    /// of its capacity, falling back to ordinary buffers when every one of them is leased. A
+    /// registered buffer is only kept while it holds data or is being filled.
+    pub fn with_pool(capacity: usize, pool: Option<FixedPool>) -> Buffer {
+        assert!(capacity > 0, "buffer capacity must not be 0");
+        assert!(capacity <= u32::MAX as usize, "buffer capacity must be smaller than 4 GiB");
+        Buffer { data: None, spare: None, capacity, pool, pos: 0, cap: 0, filling: false }
+    }
+
+    /// The index of the registered buffer in use, if the buffer is leased from a pool.
+    pub fn fixed_index(&self) -> Option<u16> {
+        self.data.as_ref().and_then(|data| data.as_ref().right()).map(FixedBuf::index)
+    }
+
+    pub fn buffered_from_read(&self) -> &[u8] {
+        self.data.as_deref().map_or(&[], |data| &data[self.pos as usize..self.cap as usize])
+    }
+
+    pub fn fill_buf(&mut self, fill: impl FnOnce(&mut [u8], Option<u16>) -> Poll<io::Result<u32>>)
+        -> Poll<io::Result<&[u8]>>
+    {
+        self.fill_buf_sized(0, fill)
+    }
+
+    /// Fill the buffer like `fill_buf`, but if it is empty and `len` is larger than its capacity,
+    /// fill an owned buffer of `len` bytes instead, up to `LARGE_READ_FACTOR` times the capacity,
+    /// which is swapped in for the buffer until its data has been consumed. This lets a large read
+    /// be made with one event, rather than one for each time the buffer is refilled. The larger
+    /// buffer is dropped once it has been read, rather than kept around.
+    ///
+    /// The data is still read into a buffer owned by this one rather than the caller's: the
+    /// kernel could keep writing into the caller's slice after the read has been dropped.
+    ///
+    /// `fill` is passed the index of the registered buffer it is filling, if there is one.
+    pub fn fill_buf_sized(
+        &mut self,
+        len: usize,
+        fill: impl FnOnce(&mut [u8], Option<u16>) -> Poll<io::Result<u32>>,
+    ) -> Poll<io::Result<&[u8]>>
+    {
+        if self.pos >= self.cap {
+            if !self.filling {
+                self.clear();
+                let max = self.capacity.saturating_mul(LARGE_READ_FACTOR);
+                let max = cmp::min(max, u32::MAX as usize);
+                self.resize(cmp::min(cmp::max(len, self.capacity), max));
+            }
+
+            self.filling = true;
+            let index = self.fixed_index();
+            let result = ready!(fill(self.data.as_deref_mut().unwrap(), index));
+            self.filling = false;
+            self.cap = result?;
+            self.pos = 0;
+            self.release();
+        }
+        Poll::Ready(Ok(self.buffered_from_read()))
+    }
+
+    /// Copy as much of `slice` as fits into the buffer after the data it holds, returning how
+    /// much was copied.
+    pub fn append(&mut self, slice: &[u8]) -> usize {
+        if self.data.is_none() {
+            self.resize(self.capacity);
+        }
+        let data = self.data.as_deref_mut().unwrap();
+        let n = cmp::min(slice.len(), data.len() - self.cap as usize);
+        data[self.cap as usize..][..n].copy_from_slice(&slice[..n]);
+        self.cap += n as u32;
+        n
+    }
+
+    /// Returns true if there is no room in the buffer after the data it holds.
+    pub fn is_full(&self) -> bool {
+        self.data.iter().any(|data| self.cap as usize == data.len())
+    }
+
+    pub fn consume(&mut self, amt: usize) {
+        self.pos = cmp::min(self.pos + amt as u32, self.cap);
+        self.release();
+    }
+
+    pub fn clear(&mut self) {
+        self.pos = 0;
+        self.cap = 0;
+        self.filling = false;
+        self.release();
+    }
+
+    pub fn into_data(self) -> Option<Either<Box<[u8]>, FixedBuf>> {
+        self.data
+    }
+
+    pub fn cancellation(&mut self) -> Cancellation {
+        Cancellation::from(self.data.take())
+    }
+
+    // Make the buffer `len` bytes long, swapping the buffer of the usual capacity in or out.
+    fn resize(&mut self, len: usize) {
+        if self.data.iter().any(|data| data.len() == len) {
+            return;
+        }
+        if len == self.capacity && self.spare.is_some() {
+            self.data = self.spare.take();
+            return;
+        }
+        // Only an ordinary buffer of the usual capacity is kept: larger buffers and registered
+        // buffers are released once they are empty.
+        let capacity = self.capacity;
+        let old = self.data.take();
+        if old.iter().any(|data| data.is_left() && data.len() == capacity) {
+            self.spare = old;
+        }
+        let pool = self.pool.as_ref().filter(|pool| pool.buffer_len() as usize == len);
+        self.data = match pool.and_then(FixedPool::lease) {
+            Some(fixed) => Some(Either::Right(fixed)),
+            None        => Some(Either::Left(vec![0; len].into_boxed_slice())),
+        };
+    }
+
+    // Return a registered buffer to its pool, or drop a buffer larger than the usual capacity,
+    // once it holds no data and no IO is using it.
+    fn release(&mut self) {
+        let capacity = self.capacity;
+        let release = |data: &Data| data.is_right() || data.len() != capacity;
+        if !self.filling && self.pos >= self.cap && self.data.iter().any(release) {
+            self.data = None;
+        }
+    }
+}
+
+fn next_group_id() -> u16 {
+    let id = NEXT_GROUP_ID.fetch_add(1, Ordering::Relaxed);
+    assert!(id <= u16::MAX as u32, "exhausted io-uring buffer group ids");
+    id as u16
+}
+
+// The id of the buffer the kernel selected for an event, as reported by the flags of its CQE.
+fn selected_buffer(flags: u32) -> Option<u16> {
+    if flags & IORING_CQE_F_BUFFER != 0 {
+        Some((flags >> IORING_CQE_BUFFER_SHIFT) as u16)
+    } else {
+        None
+    }
+}
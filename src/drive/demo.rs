@@ -3,18 +3,21 @@
 use std::future::Future;
 use std::io;
 use std::pin::Pin;
+use std::ptr;
 use std::sync::Once;
 use std::task::{Poll, Context};
-use std::thread;
 
 use event_listener::*;
 use futures_core::ready;
-use once_cell::sync::Lazy;
+use once_cell::sync::{Lazy, OnceCell};
 use parking_lot::Mutex;
 
 const ENTRIES: u32   = 32;
+const FIXED_BUFFERS: u16 = 32;
 
 use super::{Drive, Completion};
+use crate::buf::{BufRing, FixedPool, DEFAULT_CAPACITY};
+use crate::signal;
 
 use iou::*;
 
@@ -23,10 +26,19 @@ type Queues = (
     Mutex<CompletionQueue<'static>>,
     Registrar<'static>,
     Event,
+    RawRing,
 );
 
+struct RawRing(*mut uring_sys::io_uring);
+
+unsafe impl Send for RawRing { }
+unsafe impl Sync for RawRing { }
+
 static QUEUES: Lazy<Queues> = Lazy::new(init);
 
+// Only registered once it is asked for with `register_fixed_pool`.
+static FIXED_POOL: OnceCell<FixedPool> = OnceCell::new();
+
 /// The driver handle
 pub struct DemoDriver {
     listener: Option<EventListener>,
@@ -45,7 +57,7 @@ impl DemoDriver {
         match sq.submit() {
             Ok(n)       => Poll::Ready(Ok(n)),
             Err(err)    => {
-                if err.raw_os_error().map_or(false, |code| code == libc::EBUSY) {
+                if err.raw_os_error() == Some(libc::EBUSY) {
                     self.listener = Some(QUEUES.3.listen());
                     Poll::Pending
                 } else {
@@ -80,7 +92,7 @@ impl Drive for DemoDriver {
             match sq.prepare_sqes(count) {
                 Some(sqs)   => return Poll::Ready(prepare(sqs, ctx)),
                 None        => {
-                    let _ = ready!(self.poll_submit_inner(ctx, &mut *sq));
+                    let _ = ready!(self.poll_submit_inner(ctx, &mut sq));
                 }
             }
         }
@@ -90,7 +102,18 @@ impl Drive for DemoDriver {
         mut self: Pin<&mut Self>,
         ctx: &mut Context<'_>,
     ) -> Poll<io::Result<u32>> {
-        self.poll_submit_inner(ctx, &mut *QUEUES.0.lock())
+        self.poll_submit_inner(ctx, &mut QUEUES.0.lock())
+    }
+
+    fn fixed_pool(&self) -> Option<FixedPool> {
+        FIXED_POOL.get().cloned()
+    }
+
+    /// Unlike the registrar, buffer rings can be registered after IO has been submitted to the
+    /// demo driver.
+    fn register_buf_ring(&self, entries: u16, len: u32) -> io::Result<BufRing> {
+        // The io-uring instance is leaked, so it outlives every buffer ring.
+        unsafe { BufRing::register((*(QUEUES.4).0).ring_fd, entries, len) }
     }
 }
 
@@ -105,6 +128,9 @@ pub fn driver() -> DemoDriver {
 ///
 /// This will return `None` if events have already been submitted to the driver. The Demo Driver
 /// currently only allows registering IO objects prior to submitting IO.
+///
+/// Buffers cannot be registered through the registrar if the pool of
+/// [`register_fixed_pool`] has been registered.
 pub fn registrar() -> Option<&'static Registrar<'static>> {
     if !STARTED_COMPLETION_THREAD.is_completed() {
         Some(&QUEUES.2)
@@ -114,34 +140,48 @@ pub fn registrar() -> Option<&'static Registrar<'static>> {
 
 }
 
+/// Register a pool of 32 buffers of 8 KiB for IO handles on the demo driver to lease from
+///
+/// Until this is called, the demo driver has no [`fixed_pool`](Drive::fixed_pool), and IO
+/// handles use ordinary buffers. Handles constructed after the pool is registered lease a
+/// registered buffer while they hold data in it or have IO in flight with it. Once registered,
+/// the pool is returned by every later call, and buffers can no longer be registered through the
+/// [`registrar()`].
+pub fn register_fixed_pool() -> io::Result<FixedPool> {
+    FIXED_POOL.get_or_try_init(|| {
+        // The io-uring instance is leaked, so it outlives the pool.
+        unsafe {
+            FixedPool::register((*(QUEUES.4).0).ring_fd, FIXED_BUFFERS, DEFAULT_CAPACITY as u32)
+        }
+    }).cloned()
+}
+
 fn init() -> Queues {
     let flags = SetupFlags::empty();
     let features = SetupFeatures::NODROP;
     let ring = Box::new(IoUring::new_with_flags(ENTRIES, flags, features).unwrap());
     let ring = Box::leak(ring);
+    let raw = RawRing(unsafe { ring.raw_mut() });
     let (sq, cq, reg) = ring.queues();
-    (Mutex::new(sq), Mutex::new(cq), reg, Event::new())
+    (Mutex::new(sq), Mutex::new(cq), reg, Event::new(), raw)
 }
 
 static STARTED_COMPLETION_THREAD: Once = Once::new();
 
 fn start_completion_thread() {
-    STARTED_COMPLETION_THREAD.call_once(|| { thread::spawn(move || {
+    STARTED_COMPLETION_THREAD.call_once(|| { signal::spawn_thread(move || {
         let mut cq = QUEUES.1.lock();
-        while let Ok(cqe) = cq.wait_for_cqe() {
-            let mut ready = cq.ready() as usize + 1;
+        while cq.wait(1).is_ok() {
+            let mut ready = cq.ready() as usize;
             QUEUES.3.notify_additional(ready);
 
-            super::complete(cqe);
-            ready -= 1;
-
-            while let Some(cqe) = cq.peek_for_cqe() {
+            while let Some((user_data, res, flags)) = peek_for_cqe(&QUEUES.4) {
                 if ready == 0 {
                     ready = cq.ready() as usize + 1;
                     QUEUES.3.notify_additional(ready);
                 }
 
-                super::complete(cqe);
+                super::complete_raw(user_data, res, flags);
                 ready -= 1;
             }
 
@@ -149,3 +189,19 @@ fn start_completion_thread() {
         }
     }); });
 }
+
+// iou's CQE truncates the flags the kernel sets on completions, so the completion thread reads
+// the completion queue directly in order to preserve them. Must only be called while holding the
+// completion queue lock.
+fn peek_for_cqe(ring: &RawRing) -> Option<(u64, i32, u32)> {
+    unsafe {
+        let mut cqe = ptr::null_mut();
+        uring_sys::io_uring_peek_cqe(ring.0, &mut cqe);
+        if cqe.is_null() {
+            return None;
+        }
+        let fields = ((*cqe).user_data, (*cqe).res, (*cqe).flags);
+        uring_sys::io_uring_cqe_seen(ring.0, cqe);
+        Some(fields)
+    }
+}
@@ -5,13 +5,14 @@ pub mod demo;
 use std::io;
 use std::marker::PhantomData;
 use std::pin::Pin;
-use std::task::{Context, Poll};
+use std::task::{Context, Poll, Waker};
 
+use crate::buf::{BufRing, FixedPool};
 use crate::ring;
-use crate::{Submission, Event};
+use crate::{Submission, SubmissionSet, Event};
 use iou::{SQE, SQEs};
 
-pub use crate::ring::completion::complete;
+pub use crate::ring::completion::{complete, complete_raw};
 
 /// A completion which will be used to wake the task waiting on this event.
 ///
@@ -23,8 +24,13 @@ pub struct Completion<'cx> {
 }
 
 impl<'cx> Completion<'cx> {
-    pub(crate) fn new(mut sqe: SQE<'_>, _sqes: SQEs<'_>, cx: &mut Context<'cx>) -> Completion<'cx> {
-        let real = ring::Completion::new(cx.waker().clone());
+    pub(crate) fn new(sqe: SQE<'_>, sqes: SQEs<'_>, cx: &mut Context<'cx>) -> Completion<'cx> {
+        Completion::with_waker(sqe, sqes, cx.waker())
+    }
+
+    // Construct a completion which wakes `waker`, rather than the task preparing the event.
+    pub(crate) fn with_waker(mut sqe: SQE<'_>, _sqes: SQEs<'_>, waker: &Waker) -> Completion<'cx> {
+        let real = ring::Completion::new(waker);
         unsafe {
             sqe.set_user_data(real.addr());
         }
@@ -78,7 +84,34 @@ pub trait Drive {
         ctx: &mut Context<'_>,
     ) -> Poll<io::Result<u32>>;
 
+    /// The pool of registered buffers IO handles on this driver should lease their buffers from,
+    /// if it has one.
+    ///
+    /// Handles which lease a registered buffer read and write with `READ_FIXED` and
+    /// `WRITE_FIXED`, and use ordinary buffers while every buffer in the pool is leased. By
+    /// default, drivers have no pool.
+    fn fixed_pool(&self) -> Option<FixedPool> {
+        None
+    }
+
+    /// Register a ring of `entries` buffers, each `len` bytes long, with this driver's io-uring
+    /// instance, for events which select their buffers from it.
+    ///
+    /// Drivers which own their io-uring instance can implement this with
+    /// [`BufRing::register`]. By default, drivers do not support buffer rings.
+    fn register_buf_ring(&self, entries: u16, len: u32) -> io::Result<BufRing> {
+        let _ = (entries, len);
+        Err(io::Error::new(io::ErrorKind::Unsupported, "driver does not support buffer rings"))
+    }
+
     fn submit<E: Event>(self, event: E) -> Submission<E, Self> where Self: Sized {
         Submission::new(event, self)
     }
+
+    /// Submit a collection of events together, so that they can be submitted with one syscall.
+    fn submit_all<E: Event>(self, events: impl IntoIterator<Item = E>) -> SubmissionSet<E, Self>
+        where Self: Sized
+    {
+        SubmissionSet::new(events, self)
+    }
 }
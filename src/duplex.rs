@@ -0,0 +1,179 @@
+use std::io;
+use std::mem::ManuallyDrop;
+use std::os::unix::io::RawFd;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures_core::ready;
+
+use crate::buf::{self, Buffer};
+use crate::drive::Drive;
+use crate::ring::Ring;
+
+/// One direction of a full-duplex IO object.
+///
+/// Each half has its own ring and buffer, so that an IO object can have a read and a write in
+/// flight at the same time.
+pub(crate) struct Half<D: Drive> {
+    ring: Ring<D>,
+    buf: Buffer,
+}
+
+impl<D: Drive> Half<D> {
+    pub fn with_capacity(driver: D, capacity: usize) -> Half<D> {
+        let pool = driver.fixed_pool();
+        Half {
+            ring: Ring::new(driver),
+            buf: Buffer::with_pool(capacity, pool),
+        }
+    }
+
+    pub fn buffered_from_read(&self) -> &[u8] {
+        self.buf.buffered_from_read()
+    }
+
+    /// Fill the buffer by reading from `fd` at `offset`, unless it still holds unconsumed data.
+    pub fn poll_fill_buf(self: Pin<&mut Self>, ctx: &mut Context<'_>, fd: RawFd, offset: u64)
+        -> Poll<io::Result<&[u8]>>
+    {
+        self.poll_fill_buf_sized(ctx, fd, offset, 0)
+    }
+
+    /// Fill the buffer like `poll_fill_buf`, reading up to `len` bytes at once if that is more
+    /// than the buffer holds.
+    pub fn poll_fill_buf_sized(
+        self: Pin<&mut Self>,
+        ctx: &mut Context<'_>,
+        fd: RawFd,
+        offset: u64,
+        len: usize,
+    ) -> Poll<io::Result<&[u8]>> {
+        let (ring, buf) = self.split();
+        buf.fill_buf_sized(len, |buf, index| {
+            let n = ready!(ring.poll(ctx, 1, |sqs| {
+                let mut sqe = sqs.single().unwrap();
+                unsafe {
+                    sqe.prep_read(fd, buf, offset);
+                    if let Some(index) = index {
+                        buf::fix(&mut sqe, index);
+                    }
+                }
+                sqe
+            }))?;
+            Poll::Ready(Ok(n))
+        })
+    }
+
+    /// Read into `slice` from `fd` at `offset`, through the buffer.
+    ///
+    /// If the buffer is empty and `slice` is larger than it, as much of `slice` as the buffer's
+    /// limit on large reads allows is read with a single event, and copied once out of the owned
+    /// buffer the kernel filled.
+    pub fn poll_read(
+        mut self: Pin<&mut Self>,
+        ctx: &mut Context<'_>,
+        fd: RawFd,
+        offset: u64,
+        slice: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut inner = ready!(self.as_mut().poll_fill_buf_sized(ctx, fd, offset, slice.len()))?;
+        let len = io::Read::read(&mut inner, slice)?;
+        self.consume(len);
+        Poll::Ready(Ok(len))
+    }
+
+    pub fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.split().1.consume(amt);
+    }
+
+    /// Write as much of `slice` as fits in the buffer to `fd` at `offset`.
+    pub fn poll_write(
+        self: Pin<&mut Self>,
+        ctx: &mut Context<'_>,
+        fd: RawFd,
+        offset: u64,
+        slice: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let (ring, buf) = self.split();
+        ready!(buf.fill_buf(|mut buf, _| {
+            Poll::Ready(Ok(io::Write::write(&mut buf, slice)? as u32))
+        }))?;
+        let (data, index) = (buf.buffered_from_read(), buf.fixed_index());
+        let n = ready!(ring.poll(ctx, 1, |sqs| {
+            let mut sqe = sqs.single().unwrap();
+            unsafe {
+                sqe.prep_write(fd, data, offset);
+                if let Some(index) = index {
+                    buf::fix(&mut sqe, index);
+                }
+            }
+            sqe
+        }))?;
+        buf.clear();
+        Poll::Ready(Ok(n as usize))
+    }
+
+    /// Returns true if this half has no IO in flight.
+    pub fn is_idle(&self) -> bool {
+        self.ring.is_idle()
+    }
+
+    /// Cancel any IO in flight, discarding the buffer.
+    pub fn cancel(&mut self) {
+        self.ring.cancel(self.buf.cancellation());
+        self.buf.clear();
+    }
+
+    /// Discard the buffer, cancelling any IO in flight.
+    ///
+    /// Unlike `cancel_pinned`, the buffer is kept to be reused if there is no IO in flight.
+    pub fn discard(self: Pin<&mut Self>) {
+        match self.is_idle() {
+            true    => self.split().1.clear(),
+            false   => self.cancel_pinned(),
+        }
+    }
+
+    pub fn cancel_pinned(self: Pin<&mut Self>) {
+        let (ring, buf) = self.split();
+        ring.cancel_pinned(buf.cancellation());
+        buf.clear();
+    }
+
+    pub fn driver(&self) -> &D {
+        self.ring.driver()
+    }
+
+    pub fn ring(self: Pin<&mut Self>) -> Pin<&mut Ring<D>> {
+        self.split().0
+    }
+
+    #[inline(always)]
+    fn split(self: Pin<&mut Self>) -> (Pin<&mut Ring<D>>, &mut Buffer) {
+        unsafe {
+            let this = Pin::get_unchecked_mut(self);
+            (Pin::new_unchecked(&mut this.ring), &mut this.buf)
+        }
+    }
+}
+
+/// A file descriptor shared by the owned halves of an IO object, which closes it when dropped.
+pub(crate) struct Fd(pub(crate) RawFd);
+
+impl Drop for Fd {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.0); }
+    }
+}
+
+/// Drop an owned half's reference to its file descriptor, unless it has IO in flight.
+pub(crate) fn drop_half<D: Drive>(half: &mut Half<D>, fd: &mut ManuallyDrop<Arc<Fd>>) {
+    if half.is_idle() {
+        unsafe { ManuallyDrop::drop(fd); }
+    } else {
+        // The kernel may still be using the file descriptor, so this half's reference to it is
+        // leaked, and it is never closed.
+        half.cancel();
+    }
+}
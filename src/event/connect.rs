@@ -16,7 +16,7 @@ impl<FD: UringFd + Copy> Event for Connect<FD> {
 
     unsafe fn prepare<'sq>(&mut self, sqs: &mut SQEs<'sq>) -> SQE<'sq> {
         let mut sqe = sqs.single().unwrap();
-        sqe.prep_connect(self.fd, &mut *self.addr);
+        sqe.prep_connect(self.fd, &self.addr);
         sqe
     }
 
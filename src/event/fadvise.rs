@@ -1,10 +1,13 @@
 use std::os::unix::io::RawFd;
 
-use iou::sqe::PosixFadviseAdvice;
+use iou::sqe::{FsyncFlags, PosixFadviseAdvice};
 use iou::registrar::UringFd;
 
 use super::{Event, SQE, SQEs};
 
+/// Advise the kernel how a range of a file will be accessed.
+///
+/// Older kernels only read a 32 bit length for this event, so `size` is truncated to 32 bits.
 pub struct Fadvise<FD = RawFd> {
     pub fd: FD,
     pub offset: u64,
@@ -17,7 +20,15 @@ impl<FD: UringFd + Copy> Event for Fadvise<FD> {
 
     unsafe fn prepare<'sq>(&mut self, sqs: &mut SQEs<'sq>) -> SQE<'sq> {
         let mut sqe = sqs.single().unwrap();
-        sqe.prep_fadvise(self.fd, self.offset, self.size, self.flags);
+        // uring-sys misnames the symbol of its fadvise helper, so iou's prep_fadvise fails to
+        // link. The fd is set up by preparing an fsync, and the rest of the SQE is filled in
+        // directly.
+        sqe.prep_fsync(self.fd, FsyncFlags::empty());
+        let raw = sqe.raw_mut();
+        raw.opcode = uring_sys::IoRingOp::IORING_OP_FADVISE as _;
+        raw.off_addr2.off = self.offset;
+        raw.len = self.size as u32;
+        raw.cmd_flags.fadvise_advice = self.flags as u32;
         sqe
     }
 }
@@ -0,0 +1,30 @@
+use std::os::unix::io::RawFd;
+
+use iou::registrar::UringFd;
+use iou::sqe::FsyncFlags;
+
+use super::{Event, SQE, SQEs};
+
+const IORING_OP_FTRUNCATE: u8 = 55;
+
+/// Truncate or extend a file to a length.
+///
+/// This opcode was added in Linux 6.9; older kernels fail it with `EINVAL`.
+pub struct Ftruncate<FD = RawFd> {
+    pub fd: FD,
+    pub len: u64,
+}
+
+impl<FD: UringFd + Copy> Event for Ftruncate<FD> {
+    fn sqes_needed(&self) -> u32 { 1 }
+
+    unsafe fn prepare<'sq>(&mut self, sqs: &mut SQEs<'sq>) -> SQE<'sq> {
+        let mut sqe = sqs.single().unwrap();
+        // iou cannot prepare ftruncate, so the fd is set up by preparing an fsync.
+        sqe.prep_fsync(self.fd, FsyncFlags::empty());
+        let raw = sqe.raw_mut();
+        raw.opcode = IORING_OP_FTRUNCATE;
+        raw.off_addr2.off = self.len;
+        sqe
+    }
+}
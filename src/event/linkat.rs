@@ -0,0 +1,60 @@
+use std::ffi::CString;
+use std::mem::ManuallyDrop;
+use std::os::unix::io::RawFd;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use nix::fcntl::AtFlags;
+
+use super::{Event, SQE, SQEs, Cancellation};
+
+const IORING_OP_LINKAT: u8 = 39;
+
+/// Create a hard link at `new_path` to the file at `old_path`. If `old_path` is a symbolic link,
+/// it is only dereferenced if `flags` contains `AT_SYMLINK_FOLLOW`.
+pub struct LinkAt {
+    pub old_dir_fd: RawFd,
+    pub old_path: CString,
+    pub new_dir_fd: RawFd,
+    pub new_path: CString,
+    pub flags: AtFlags,
+}
+
+impl LinkAt {
+    pub fn without_dir(old_path: impl AsRef<Path>, new_path: impl AsRef<Path>, flags: AtFlags)
+        -> LinkAt
+    {
+        let old_path = CString::new(old_path.as_ref().as_os_str().as_bytes()).unwrap();
+        let new_path = CString::new(new_path.as_ref().as_os_str().as_bytes()).unwrap();
+        LinkAt {
+            old_dir_fd: libc::AT_FDCWD,
+            old_path,
+            new_dir_fd: libc::AT_FDCWD,
+            new_path,
+            flags,
+        }
+    }
+}
+
+impl Event for LinkAt {
+    fn sqes_needed(&self) -> u32 { 1 }
+
+    unsafe fn prepare<'sq>(&mut self, sqs: &mut SQEs<'sq>) -> SQE<'sq> {
+        let mut sqe = sqs.single().unwrap();
+        super::prep_path_op(
+            &mut sqe,
+            IORING_OP_LINKAT,
+            self.old_dir_fd,
+            &self.old_path,
+            self.new_dir_fd as u32,
+            Some(&self.new_path),
+            self.flags.bits() as u32,
+        );
+        sqe
+    }
+
+    fn cancel(this: ManuallyDrop<Self>) -> Cancellation {
+        let this = ManuallyDrop::into_inner(this);
+        Cancellation::from((this.old_path, this.new_path))
+    }
+}
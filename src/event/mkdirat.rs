@@ -0,0 +1,39 @@
+use std::ffi::CString;
+use std::mem::ManuallyDrop;
+use std::os::unix::io::RawFd;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use iou::sqe::Mode;
+
+use super::{Event, SQE, SQEs, Cancellation};
+
+const IORING_OP_MKDIRAT: u8 = 37;
+
+pub struct MkdirAt {
+    pub path: CString,
+    pub dir_fd: RawFd,
+    pub mode: Mode,
+}
+
+impl MkdirAt {
+    pub fn without_dir(path: impl AsRef<Path>, mode: Mode) -> MkdirAt {
+        let path = CString::new(path.as_ref().as_os_str().as_bytes()).unwrap();
+        MkdirAt { path, dir_fd: libc::AT_FDCWD, mode }
+    }
+}
+
+impl Event for MkdirAt {
+    fn sqes_needed(&self) -> u32 { 1 }
+
+    unsafe fn prepare<'sq>(&mut self, sqs: &mut SQEs<'sq>) -> SQE<'sq> {
+        let mut sqe = sqs.single().unwrap();
+        let mode = self.mode.bits();
+        super::prep_path_op(&mut sqe, IORING_OP_MKDIRAT, self.dir_fd, &self.path, mode, None, 0);
+        sqe
+    }
+
+    fn cancel(this: ManuallyDrop<Self>) -> Cancellation {
+        Cancellation::from(ManuallyDrop::into_inner(this).path)
+    }
+}
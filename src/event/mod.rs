@@ -8,19 +8,33 @@ mod fadvise;
 mod fallocate;
 mod files_update;
 mod fsync;
+mod ftruncate;
+mod linkat;
+mod mkdirat;
 mod openat;
+mod openat2;
+mod poll_add;
 mod provide_buffers;
 mod read;
 mod readv;
 mod recv;
+mod renameat;
 mod send;
 mod splice;
 mod statx;
+mod symlinkat;
+mod sync_file_range;
+mod tee;
 mod timeout;
+mod unlinkat;
+mod waitid;
 mod write;
 mod writev;
 
+use std::ffi::CStr;
+use std::io;
 use std::mem::ManuallyDrop;
+use std::os::unix::io::RawFd;
 
 use iou::{SQE, SQEs};
 
@@ -34,18 +48,32 @@ pub use fadvise::Fadvise;
 pub use fallocate::Fallocate;
 pub use files_update::FilesUpdate;
 pub use fsync::Fsync;
+pub use ftruncate::Ftruncate;
+pub use linkat::LinkAt;
+pub use mkdirat::MkdirAt;
 pub use openat::OpenAt;
+pub use openat2::{OpenAt2, ResolveFlags};
+pub use poll_add::PollAdd;
 pub use provide_buffers::{ProvideBuffers, RemoveBuffers};
-pub use read::{Read, ReadFixed};
+pub use read::{Read, ReadAligned, ReadFixed, ReadSelect};
 pub use readv::ReadVectored;
-pub use recv::Recv;
+pub use recv::{Recv, RecvMulti, RecvSelect};
+pub use renameat::{RenameAt, RenameFlags};
 pub use send::Send;
 pub use splice::Splice;
 pub use statx::Statx;
+pub use symlinkat::SymlinkAt;
+pub use sync_file_range::{SyncFileRange, SyncFileRangeFlags};
+pub use tee::Tee;
 pub use timeout::{Timeout, StaticTimeout};
-pub use write::{Write, WriteFixed};
+pub use unlinkat::UnlinkAt;
+pub use waitid::WaitId;
+pub use write::{Write, WriteAligned, WriteFixed};
 pub use writev::WriteVectored;
 
+pub(crate) use splice::prep_splice;
+pub(crate) use sync_file_range::prep_sync_file_range;
+
 /// An IO event that can be scheduled on an io-uring driver.
 ///
 /// ## Safety
@@ -86,4 +114,34 @@ pub trait Event {
     fn cancel(_: ManuallyDrop<Self>) -> Cancellation where Self: Sized {
         Cancellation::from(())
     }
+
+    /// Observe the result and flags the kernel set on the completion of this event.
+    ///
+    /// This is called once the event has completed, before ownership of it is passed back to the
+    /// user. Most events ignore these, but events which let the kernel select a buffer use the
+    /// flags to find out which buffer was selected, and the result to find out how much of it
+    /// was filled.
+    fn complete(&mut self, _result: &io::Result<u32>, _flags: u32) { }
+}
+
+// iou 0.3 cannot prepare the events which operate on the filesystem namespace, so they are
+// prepared by filling in the SQE directly.
+unsafe fn prep_path_op(
+    sqe: &mut SQE<'_>,
+    opcode: u8,
+    fd: RawFd,
+    path: &CStr,
+    len: u32,
+    path2: Option<&CStr>,
+    flags: u32,
+) {
+    sqe.prep_nop();
+    let raw = sqe.raw_mut();
+    raw.opcode = opcode;
+    raw.fd = fd;
+    raw.addr = path.as_ptr() as u64;
+    raw.len = len;
+    raw.off_addr2.addr2 = path2.map_or(0, |path| path.as_ptr() as u64);
+    // The unlink, rename and link flags all share this field with the open flags.
+    raw.cmd_flags.open_flags = flags;
 }
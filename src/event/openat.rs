@@ -27,7 +27,7 @@ impl Event for OpenAt {
 
     unsafe fn prepare<'sq>(&mut self, sqs: &mut SQEs<'sq>) -> SQE<'sq> {
         let mut sqe = sqs.single().unwrap();
-        sqe.prep_openat(self.dir_fd, &*self.path, self.flags, self.mode);
+        sqe.prep_openat(self.dir_fd, &self.path, self.flags, self.mode);
         sqe
     }
 
@@ -0,0 +1,96 @@
+use std::ffi::CString;
+use std::mem::{self, ManuallyDrop};
+use std::os::unix::io::RawFd;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use iou::sqe::{Mode, OFlag};
+
+use super::{Event, SQE, SQEs, Cancellation};
+
+const IORING_OP_OPENAT2: u8 = 28;
+
+bitflags::bitflags! {
+    /// Flags which restrict how [`OpenAt2`] resolves its path, as with `openat2(2)`.
+    pub struct ResolveFlags: u64 {
+        /// Fail if resolution crosses a mount point.
+        const RESOLVE_NO_XDEV       = 0x01;
+        /// Fail if resolution follows a magic link, such as those in `/proc/[pid]/fd`.
+        const RESOLVE_NO_MAGICLINKS = 0x02;
+        /// Fail if resolution follows any symbolic link.
+        const RESOLVE_NO_SYMLINKS   = 0x04;
+        /// Fail if resolution escapes the directory, whether through `..`, an absolute path or
+        /// a symbolic link.
+        const RESOLVE_BENEATH       = 0x08;
+        /// Resolve the path as if the directory were the root of the filesystem, so that `..`
+        /// and absolute symbolic links stay inside it.
+        const RESOLVE_IN_ROOT       = 0x10;
+        /// Fail with `EAGAIN` unless the path can be resolved from the dentry cache.
+        const RESOLVE_CACHED        = 0x20;
+    }
+}
+
+// The argument to openat2, from linux/openat2.h
+#[allow(non_camel_case_types)]
+#[repr(C)]
+struct open_how {
+    flags: u64,
+    mode: u64,
+    resolve: u64,
+}
+
+/// Open a file relative to a directory, with control over how its path is resolved.
+pub struct OpenAt2 {
+    pub path: CString,
+    pub dir_fd: RawFd,
+    pub flags: OFlag,
+    pub mode: Mode,
+    pub resolve: ResolveFlags,
+    how: Box<open_how>,
+}
+
+impl OpenAt2 {
+    pub fn new(dir_fd: RawFd, path: impl AsRef<Path>, flags: OFlag, mode: Mode, resolve: ResolveFlags)
+        -> OpenAt2
+    {
+        let path = CString::new(path.as_ref().as_os_str().as_bytes()).unwrap();
+        let how = Box::new(open_how { flags: 0, mode: 0, resolve: 0 });
+        OpenAt2 { path, dir_fd, flags, mode, resolve, how }
+    }
+
+    pub fn without_dir(path: impl AsRef<Path>, flags: OFlag, mode: Mode, resolve: ResolveFlags)
+        -> OpenAt2
+    {
+        OpenAt2::new(libc::AT_FDCWD, path, flags, mode, resolve)
+    }
+}
+
+impl Event for OpenAt2 {
+    fn sqes_needed(&self) -> u32 { 1 }
+
+    unsafe fn prepare<'sq>(&mut self, sqs: &mut SQEs<'sq>) -> SQE<'sq> {
+        *self.how = open_how {
+            flags: self.flags.bits() as u64,
+            // openat2 rejects a mode unless the file may be created.
+            mode: match self.flags.intersects(OFlag::O_CREAT | OFlag::O_TMPFILE) {
+                true    => self.mode.bits() as u64,
+                false   => 0,
+            },
+            resolve: self.resolve.bits(),
+        };
+        let mut sqe = sqs.single().unwrap();
+        sqe.prep_nop();
+        let raw = sqe.raw_mut();
+        raw.opcode = IORING_OP_OPENAT2;
+        raw.fd = self.dir_fd;
+        raw.addr = self.path.as_ptr() as u64;
+        raw.len = mem::size_of::<open_how>() as u32;
+        raw.off_addr2.addr2 = &*self.how as *const open_how as u64;
+        sqe
+    }
+
+    fn cancel(this: ManuallyDrop<Self>) -> Cancellation {
+        let this = ManuallyDrop::into_inner(this);
+        Cancellation::from((this.path, this.how))
+    }
+}
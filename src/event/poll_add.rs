@@ -0,0 +1,22 @@
+use std::os::unix::io::RawFd;
+
+use iou::registrar::UringFd;
+use iou::sqe::PollFlags;
+
+use super::{Event, SQE, SQEs};
+
+/// Wait until a file descriptor is ready for any of the events in `flags`.
+pub struct PollAdd<FD = RawFd> {
+    pub fd: FD,
+    pub flags: PollFlags,
+}
+
+impl<FD: UringFd + Copy> Event for PollAdd<FD> {
+    fn sqes_needed(&self) -> u32 { 1 }
+
+    unsafe fn prepare<'sq>(&mut self, sqs: &mut SQEs<'sq>) -> SQE<'sq> {
+        let mut sqe = sqs.single().unwrap();
+        sqe.prep_poll_add(self.fd, self.flags);
+        sqe
+    }
+}
@@ -1,8 +1,11 @@
+use std::io;
 use std::mem::ManuallyDrop;
 use std::os::unix::io::RawFd;
 
 use iou::registrar::{UringFd, RegisteredBuf};
 
+use crate::buf::{AlignedBuf, BufferGroup, LeasedBuffer};
+
 use super::{Event, SQE, SQEs, Cancellation};
 
 /// A basic read event.
@@ -45,3 +48,65 @@ impl<FD: UringFd + Copy> Event for ReadFixed<FD> {
         Cancellation::from(ManuallyDrop::into_inner(this).buf)
     }
 }
+
+/// A read event which lets the kernel select a buffer from a [`BufferGroup`].
+///
+/// Once the event has completed, the selected buffer is stored in `buf`, truncated to the number
+/// of bytes the kernel wrote to it.
+pub struct ReadSelect<FD = RawFd> {
+    pub fd: FD,
+    pub group: BufferGroup,
+    pub offset: u64,
+    pub buf: Option<LeasedBuffer>,
+}
+
+impl<FD> ReadSelect<FD> {
+    pub fn new(fd: FD, group: BufferGroup, offset: u64) -> ReadSelect<FD> {
+        ReadSelect { fd, group, offset, buf: None }
+    }
+}
+
+impl<FD: UringFd + Copy> Event for ReadSelect<FD> {
+    fn sqes_needed(&self) -> u32 {
+        self.group.sqes_needed()
+    }
+
+    unsafe fn prepare<'sq>(&mut self, sqs: &mut SQEs<'sq>) -> SQE<'sq> {
+        let (fd, offset) = (self.fd, self.offset);
+        self.group.prepare(sqs, |sqe| sqe.prep_read(fd, &mut [0u8; 0][..], offset))
+    }
+
+    fn cancel(this: ManuallyDrop<Self>) -> Cancellation {
+        // A buffer the kernel selects after the event was cancelled is returned to the group.
+        Cancellation::with_completions(ManuallyDrop::into_inner(this).group)
+    }
+
+    fn complete(&mut self, result: &io::Result<u32>, flags: u32) {
+        self.buf = self.group.lease(flags);
+        if let (Some(buf), Ok(n)) = (&mut self.buf, result) {
+            buf.truncate(*n as usize);
+        }
+    }
+}
+
+/// A read event using a buffer with an aligned address, as needed by files opened with
+/// `O_DIRECT`.
+pub struct ReadAligned<FD = RawFd> {
+    pub fd: FD,
+    pub buf: AlignedBuf,
+    pub offset: u64,
+}
+
+impl<FD: UringFd + Copy> Event for ReadAligned<FD> {
+    fn sqes_needed(&self) -> u32 { 1 }
+
+    unsafe fn prepare<'sq>(&mut self, sqs: &mut SQEs<'sq>) -> SQE<'sq> {
+        let mut sqe = sqs.single().unwrap();
+        sqe.prep_read(self.fd, &mut self.buf[..], self.offset);
+        sqe
+    }
+
+    fn cancel(this: ManuallyDrop<Self>) -> Cancellation {
+        Cancellation::from(ManuallyDrop::into_inner(this).buf)
+    }
+}
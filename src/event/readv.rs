@@ -14,7 +14,7 @@ pub struct ReadVectored<FD = RawFd> {
 }
 
 impl<FD> ReadVectored<FD> {
-    fn as_iovecs(buffers: &mut [Box<[u8]>]) -> &mut [IoSliceMut] {
+    fn as_iovecs(buffers: &mut [Box<[u8]>]) -> &mut [IoSliceMut<'_>] {
         // Unsafe contract:
         // This pointer cast is defined behaviour because Box<[u8]> (wide pointer)
         // is currently ABI compatible with libc::iovec.
@@ -1,9 +1,12 @@
+use std::io;
 use std::mem::ManuallyDrop;
 use std::os::unix::io::RawFd;
 
 use iou::sqe::MsgFlags;
 use iou::registrar::UringFd;
 
+use crate::buf::{BufferGroup, BufRing, LeasedBuffer};
+
 use super::{Event, SQE, SQEs, Cancellation};
 
 pub struct Recv<FD = RawFd> {
@@ -17,7 +20,7 @@ impl<FD: UringFd + Copy> Event for Recv<FD> {
 
     unsafe fn prepare<'sq>(&mut self, sqs: &mut SQEs<'sq>) -> SQE<'sq> {
         let mut sqe = sqs.single().unwrap();
-        sqe.prep_recv(self.fd, &mut self.buf[..], self.flags);
+        prep_recv(&mut sqe, self.fd, &mut self.buf[..], self.flags);
         sqe
     }
 
@@ -25,3 +28,89 @@ impl<FD: UringFd + Copy> Event for Recv<FD> {
         Cancellation::from(ManuallyDrop::into_inner(this).buf)
     }
 }
+
+/// A recv event which lets the kernel select a buffer from a [`BufferGroup`].
+///
+/// Once the event has completed, the selected buffer is stored in `buf`, truncated to the number
+/// of bytes the kernel wrote to it.
+pub struct RecvSelect<FD = RawFd> {
+    pub fd: FD,
+    pub group: BufferGroup,
+    pub flags: MsgFlags,
+    pub buf: Option<LeasedBuffer>,
+}
+
+impl<FD> RecvSelect<FD> {
+    pub fn new(fd: FD, group: BufferGroup, flags: MsgFlags) -> RecvSelect<FD> {
+        RecvSelect { fd, group, flags, buf: None }
+    }
+}
+
+impl<FD: UringFd + Copy> Event for RecvSelect<FD> {
+    fn sqes_needed(&self) -> u32 {
+        self.group.sqes_needed()
+    }
+
+    unsafe fn prepare<'sq>(&mut self, sqs: &mut SQEs<'sq>) -> SQE<'sq> {
+        let (fd, flags) = (self.fd, self.flags);
+        self.group.prepare(sqs, |sqe| prep_recv(sqe, fd, &mut [], flags))
+    }
+
+    fn cancel(this: ManuallyDrop<Self>) -> Cancellation {
+        // A buffer the kernel selects after the event was cancelled is returned to the group.
+        Cancellation::with_completions(ManuallyDrop::into_inner(this).group)
+    }
+
+    fn complete(&mut self, result: &io::Result<u32>, flags: u32) {
+        self.buf = self.group.lease(flags);
+        if let (Some(buf), Ok(n)) = (&mut self.buf, result) {
+            buf.truncate(*n as usize);
+        }
+    }
+}
+
+/// A multishot recv event, which completes each time data is received into a buffer selected
+/// from a [`BufRing`].
+///
+/// Submitted as a [`Submission`](crate::Submission), only the first completion is observed. Use
+/// [`BufRing::recv_multishot`] to receive every completion as a stream.
+pub struct RecvMulti<FD = RawFd> {
+    pub fd: FD,
+    pub ring: BufRing,
+    pub flags: MsgFlags,
+    pub buf: Option<LeasedBuffer>,
+}
+
+const IORING_RECV_MULTISHOT: u16 = 1 << 1;
+
+impl<FD> RecvMulti<FD> {
+    pub fn new(fd: FD, ring: BufRing, flags: MsgFlags) -> RecvMulti<FD> {
+        RecvMulti { fd, ring, flags, buf: None }
+    }
+}
+
+impl<FD: UringFd + Copy> Event for RecvMulti<FD> {
+    fn sqes_needed(&self) -> u32 { 1 }
+
+    unsafe fn prepare<'sq>(&mut self, sqs: &mut SQEs<'sq>) -> SQE<'sq> {
+        let mut sqe = sqs.single().unwrap();
+        prep_recv(&mut sqe, self.fd, &mut [], self.flags);
+        sqe.raw_mut().ioprio |= IORING_RECV_MULTISHOT;
+        self.ring.select(&mut sqe);
+        sqe
+    }
+
+    fn cancel(this: ManuallyDrop<Self>) -> Cancellation {
+        Cancellation::from(ManuallyDrop::into_inner(this).ring.clone())
+    }
+
+    fn complete(&mut self, _: &io::Result<u32>, flags: u32) {
+        self.buf = self.ring.lease(flags);
+    }
+}
+
+// iou prepares recv events with the send opcode, so the opcode is corrected here.
+unsafe fn prep_recv(sqe: &mut SQE<'_>, fd: impl UringFd, buf: &mut [u8], flags: MsgFlags) {
+    sqe.prep_recv(fd, buf, flags);
+    sqe.raw_mut().opcode = uring_sys::IoRingOp::IORING_OP_RECV as _;
+}
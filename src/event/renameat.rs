@@ -0,0 +1,69 @@
+use std::ffi::CString;
+use std::mem::ManuallyDrop;
+use std::os::unix::io::RawFd;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use super::{Event, SQE, SQEs, Cancellation};
+
+const IORING_OP_RENAMEAT: u8 = 35;
+
+bitflags::bitflags! {
+    /// Flags which change the behavior of [`RenameAt`], as with `renameat2(2)`.
+    pub struct RenameFlags: u32 {
+        /// Fail with `EEXIST` instead of replacing an existing file at the new path.
+        const RENAME_NOREPLACE  = libc::RENAME_NOREPLACE;
+        /// Atomically exchange the old and new paths, both of which must exist.
+        const RENAME_EXCHANGE   = libc::RENAME_EXCHANGE;
+        /// Leave a whiteout object at the old path. This is only meaningful for overlay
+        /// filesystems.
+        const RENAME_WHITEOUT   = libc::RENAME_WHITEOUT;
+    }
+}
+
+pub struct RenameAt {
+    pub old_dir_fd: RawFd,
+    pub old_path: CString,
+    pub new_dir_fd: RawFd,
+    pub new_path: CString,
+    pub flags: RenameFlags,
+}
+
+impl RenameAt {
+    pub fn without_dir(old_path: impl AsRef<Path>, new_path: impl AsRef<Path>, flags: RenameFlags)
+        -> RenameAt
+    {
+        let old_path = CString::new(old_path.as_ref().as_os_str().as_bytes()).unwrap();
+        let new_path = CString::new(new_path.as_ref().as_os_str().as_bytes()).unwrap();
+        RenameAt {
+            old_dir_fd: libc::AT_FDCWD,
+            old_path,
+            new_dir_fd: libc::AT_FDCWD,
+            new_path,
+            flags,
+        }
+    }
+}
+
+impl Event for RenameAt {
+    fn sqes_needed(&self) -> u32 { 1 }
+
+    unsafe fn prepare<'sq>(&mut self, sqs: &mut SQEs<'sq>) -> SQE<'sq> {
+        let mut sqe = sqs.single().unwrap();
+        super::prep_path_op(
+            &mut sqe,
+            IORING_OP_RENAMEAT,
+            self.old_dir_fd,
+            &self.old_path,
+            self.new_dir_fd as u32,
+            Some(&self.new_path),
+            self.flags.bits(),
+        );
+        sqe
+    }
+
+    fn cancel(this: ManuallyDrop<Self>) -> Cancellation {
+        let this = ManuallyDrop::into_inner(this);
+        Cancellation::from((this.old_path, this.new_path))
+    }
+}
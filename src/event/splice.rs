@@ -18,7 +18,30 @@ impl Event for Splice {
 
     unsafe fn prepare<'sq>(&mut self, sqs: &mut SQEs<'sq>) -> SQE<'sq> {
         let mut sqe = sqs.single().unwrap();
-        sqe.prep_splice(self.fd_in, self.off_in, self.fd_out, self.off_out, self.bytes, self.flags);
+        let (fd_in, fd_out) = (self.fd_in, self.fd_out);
+        prep_splice(&mut sqe, fd_in, self.off_in, fd_out, self.off_out, self.bytes, self.flags);
         sqe
     }
 }
+
+// The wrapper uring-sys uses to prepare splices passes its arguments to liburing in the wrong
+// order, so the SQE is filled in directly.
+pub(crate) unsafe fn prep_splice(
+    sqe: &mut SQE<'_>,
+    fd_in: RawFd,
+    off_in: i64,
+    fd_out: RawFd,
+    off_out: i64,
+    bytes: u32,
+    flags: SpliceFlags,
+) {
+    sqe.prep_nop();
+    let raw = sqe.raw_mut();
+    raw.opcode = uring_sys::IoRingOp::IORING_OP_SPLICE as _;
+    raw.fd = fd_out;
+    raw.off_addr2.off = off_out as u64;
+    raw.addr = off_in as u64;
+    raw.len = bytes;
+    raw.cmd_flags.splice_flags = flags.bits();
+    raw.buf_index.buf_index.splice_fd_in = fd_in;
+}
@@ -42,7 +42,7 @@ impl<FD: UringFd + Copy> Event for Statx<FD> {
 
     unsafe fn prepare<'sq>(&mut self, sqs: &mut SQEs<'sq>) -> SQE<'sq> {
         let mut sqe = sqs.single().unwrap();
-        sqe.prep_statx(self.dir_fd, self.path.as_c_str(), self.flags, self.mask, &mut *self.statx);
+        sqe.prep_statx(self.dir_fd, self.path.as_c_str(), self.flags, self.mask, &mut self.statx);
         sqe
     }
 
@@ -0,0 +1,42 @@
+use std::ffi::CString;
+use std::mem::ManuallyDrop;
+use std::os::unix::io::RawFd;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use super::{Event, SQE, SQEs, Cancellation};
+
+const IORING_OP_SYMLINKAT: u8 = 38;
+
+/// Create a symbolic link at `link_path`, which points to `target`.
+///
+/// The target is stored in the link as is; it is not resolved relative to `dir_fd`.
+pub struct SymlinkAt {
+    pub target: CString,
+    pub dir_fd: RawFd,
+    pub link_path: CString,
+}
+
+impl SymlinkAt {
+    pub fn without_dir(target: impl AsRef<Path>, link_path: impl AsRef<Path>) -> SymlinkAt {
+        let target = CString::new(target.as_ref().as_os_str().as_bytes()).unwrap();
+        let link_path = CString::new(link_path.as_ref().as_os_str().as_bytes()).unwrap();
+        SymlinkAt { target, dir_fd: libc::AT_FDCWD, link_path }
+    }
+}
+
+impl Event for SymlinkAt {
+    fn sqes_needed(&self) -> u32 { 1 }
+
+    unsafe fn prepare<'sq>(&mut self, sqs: &mut SQEs<'sq>) -> SQE<'sq> {
+        let mut sqe = sqs.single().unwrap();
+        let link_path = Some(self.link_path.as_c_str());
+        super::prep_path_op(&mut sqe, IORING_OP_SYMLINKAT, self.dir_fd, &self.target, 0, link_path, 0);
+        sqe
+    }
+
+    fn cancel(this: ManuallyDrop<Self>) -> Cancellation {
+        let this = ManuallyDrop::into_inner(this);
+        Cancellation::from((this.target, this.link_path))
+    }
+}
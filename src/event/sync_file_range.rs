@@ -0,0 +1,56 @@
+use std::os::unix::io::RawFd;
+
+use iou::registrar::UringFd;
+use iou::sqe::FsyncFlags;
+
+use super::{Event, SQE, SQEs};
+
+bitflags::bitflags! {
+    /// Flags which control what [`SyncFileRange`] waits for, as with `sync_file_range(2)`.
+    pub struct SyncFileRangeFlags: u32 {
+        /// Wait for writeback of any pages in the range which is already in progress.
+        const WAIT_BEFORE   = 1;
+        /// Start writeback of any dirty pages in the range.
+        const WRITE         = 2;
+        /// Wait for writeback of the pages in the range to complete.
+        const WAIT_AFTER    = 4;
+    }
+}
+
+/// Write back dirty pages of a file in a byte range.
+///
+/// This does not write back the file's metadata, and offers no durability guarantee on its own;
+/// see `sync_file_range(2)`. A `len` of zero syncs to the end of the file.
+pub struct SyncFileRange<FD = RawFd> {
+    pub fd: FD,
+    pub offset: u64,
+    pub len: u32,
+    pub flags: SyncFileRangeFlags,
+}
+
+impl<FD: UringFd + Copy> Event for SyncFileRange<FD> {
+    fn sqes_needed(&self) -> u32 { 1 }
+
+    unsafe fn prepare<'sq>(&mut self, sqs: &mut SQEs<'sq>) -> SQE<'sq> {
+        let mut sqe = sqs.single().unwrap();
+        prep_sync_file_range(&mut sqe, self.fd, self.offset, self.len, self.flags);
+        sqe
+    }
+}
+
+// iou cannot prepare sync_file_range, so the fd is set up by preparing an fsync, and the rest of
+// the SQE is filled in directly.
+pub(crate) unsafe fn prep_sync_file_range(
+    sqe: &mut SQE<'_>,
+    fd: impl UringFd,
+    offset: u64,
+    len: u32,
+    flags: SyncFileRangeFlags,
+) {
+    sqe.prep_fsync(fd, FsyncFlags::empty());
+    let raw = sqe.raw_mut();
+    raw.opcode = uring_sys::IoRingOp::IORING_OP_SYNC_FILE_RANGE as _;
+    raw.off_addr2.off = offset;
+    raw.len = len;
+    raw.cmd_flags.sync_range_flags = flags.bits();
+}
@@ -0,0 +1,26 @@
+use std::os::unix::io::RawFd;
+
+use iou::sqe::SpliceFlags;
+
+use super::{Event, SQE, SQEs};
+
+/// Duplicate up to `bytes` bytes from one pipe into another, without consuming them from the
+/// first pipe.
+pub struct Tee {
+    pub fd_in: RawFd,
+    pub fd_out: RawFd,
+    pub bytes: u32,
+    pub flags: SpliceFlags,
+}
+
+impl Event for Tee {
+    fn sqes_needed(&self) -> u32 { 1 }
+
+    unsafe fn prepare<'sq>(&mut self, sqs: &mut SQEs<'sq>) -> SQE<'sq> {
+        let mut sqe = sqs.single().unwrap();
+        // A tee is prepared like a splice between two pipes, which have no offsets.
+        super::prep_splice(&mut sqe, self.fd_in, 0, self.fd_out, 0, self.bytes, self.flags);
+        sqe.raw_mut().opcode = uring_sys::IoRingOp::IORING_OP_TEE as _;
+        sqe
+    }
+}
@@ -50,7 +50,7 @@ impl Event for Timeout {
 
     unsafe fn prepare<'sq>(&mut self, sqs: &mut SQEs<'sq>) -> SQE<'sq> {
         let mut sqe = sqs.single().unwrap();
-        sqe.prep_timeout(&*self.ts, self.events, self.flags);
+        sqe.prep_timeout(&self.ts, self.events, self.flags);
         sqe
     }
 
@@ -0,0 +1,40 @@
+use std::ffi::CString;
+use std::mem::ManuallyDrop;
+use std::os::unix::io::RawFd;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use nix::fcntl::AtFlags;
+
+use super::{Event, SQE, SQEs, Cancellation};
+
+const IORING_OP_UNLINKAT: u8 = 36;
+
+/// Remove a directory entry. Directories are only removed if `flags` contains `AT_REMOVEDIR`.
+pub struct UnlinkAt {
+    pub path: CString,
+    pub dir_fd: RawFd,
+    pub flags: AtFlags,
+}
+
+impl UnlinkAt {
+    pub fn without_dir(path: impl AsRef<Path>, flags: AtFlags) -> UnlinkAt {
+        let path = CString::new(path.as_ref().as_os_str().as_bytes()).unwrap();
+        UnlinkAt { path, dir_fd: libc::AT_FDCWD, flags }
+    }
+}
+
+impl Event for UnlinkAt {
+    fn sqes_needed(&self) -> u32 { 1 }
+
+    unsafe fn prepare<'sq>(&mut self, sqs: &mut SQEs<'sq>) -> SQE<'sq> {
+        let mut sqe = sqs.single().unwrap();
+        let flags = self.flags.bits() as u32;
+        super::prep_path_op(&mut sqe, IORING_OP_UNLINKAT, self.dir_fd, &self.path, 0, None, flags);
+        sqe
+    }
+
+    fn cancel(this: ManuallyDrop<Self>) -> Cancellation {
+        Cancellation::from(ManuallyDrop::into_inner(this).path)
+    }
+}
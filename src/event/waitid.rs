@@ -0,0 +1,47 @@
+use std::mem::{self, ManuallyDrop};
+
+use super::{Event, SQE, SQEs, Cancellation};
+
+const IORING_OP_WAITID: u8 = 50;
+
+/// Wait for a child process to change state, like `waitid(2)`.
+///
+/// `id_type` and `id` select the children to wait for, such as `libc::P_PIDFD` and a pidfd, and
+/// `options` must include at least one of `WEXITED`, `WSTOPPED` and `WCONTINUED`. The event
+/// completes with 0 once a child has changed state, and `info` describes what happened to it.
+///
+/// This opcode was added in Linux 6.7; older kernels fail it with `EINVAL`.
+pub struct WaitId {
+    pub id_type: libc::idtype_t,
+    pub id: libc::id_t,
+    pub options: libc::c_int,
+    pub info: Box<libc::siginfo_t>,
+}
+
+impl WaitId {
+    pub fn new(id_type: libc::idtype_t, id: libc::id_t, options: libc::c_int) -> WaitId {
+        let info = unsafe { Box::new(mem::zeroed()) };
+        WaitId { id_type, id, options, info }
+    }
+}
+
+impl Event for WaitId {
+    fn sqes_needed(&self) -> u32 { 1 }
+
+    unsafe fn prepare<'sq>(&mut self, sqs: &mut SQEs<'sq>) -> SQE<'sq> {
+        let mut sqe = sqs.single().unwrap();
+        // iou cannot prepare waitid, so the SQE is filled in directly.
+        sqe.prep_nop();
+        let raw = sqe.raw_mut();
+        raw.opcode = IORING_OP_WAITID;
+        raw.fd = self.id as _;
+        raw.len = self.id_type as _;
+        raw.off_addr2.off = &mut *self.info as *mut libc::siginfo_t as u64;
+        raw.buf_index.buf_index.splice_fd_in = self.options;
+        sqe
+    }
+
+    fn cancel(this: ManuallyDrop<Self>) -> Cancellation {
+        Cancellation::from(ManuallyDrop::into_inner(this).info)
+    }
+}
@@ -3,6 +3,8 @@ use std::os::unix::io::RawFd;
 
 use iou::registrar::{UringFd, RegisteredBuf};
 
+use crate::buf::AlignedBuf;
+
 use super::{Event, SQE, SQEs, Cancellation};
 
 /// A basic write event.
@@ -45,3 +47,25 @@ impl<FD: UringFd + Copy> Event for WriteFixed<FD> {
         Cancellation::from(ManuallyDrop::into_inner(this).buf)
     }
 }
+
+/// A write event using a buffer with an aligned address, as needed by files opened with
+/// `O_DIRECT`.
+pub struct WriteAligned<FD = RawFd> {
+    pub fd: FD,
+    pub buf: AlignedBuf,
+    pub offset: u64,
+}
+
+impl<FD: UringFd + Copy> Event for WriteAligned<FD> {
+    fn sqes_needed(&self) -> u32 { 1 }
+
+    unsafe fn prepare<'sq>(&mut self, sqs: &mut SQEs<'sq>) -> SQE<'sq> {
+        let mut sqe = sqs.single().unwrap();
+        sqe.prep_write(self.fd, &self.buf[..], self.offset);
+        sqe
+    }
+
+    fn cancel(this: ManuallyDrop<Self>) -> Cancellation {
+        Cancellation::from(ManuallyDrop::into_inner(this).buf)
+    }
+}
@@ -14,7 +14,7 @@ pub struct WriteVectored<FD = RawFd> {
 }
 
 impl<FD> WriteVectored<FD> {
-    fn iovecs(&self) -> &[IoSlice] {
+    fn iovecs(&self) -> &[IoSlice<'_>] {
         unsafe { & *(&self.bufs[..] as *const [Box<[u8]>] as *const [IoSlice]) }
     }
 }
@@ -0,0 +1,393 @@
+use std::cmp;
+use std::ffi::CString;
+use std::io;
+use std::mem::{self, ManuallyDrop};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::io::RawFd;
+use std::path::Path;
+
+use iou::sqe::{Mode, OFlag, StatxFlags};
+use iou::{SQE, SQEs};
+
+use crate::blocking;
+use crate::buf::DEFAULT_CAPACITY;
+use crate::drive::Drive;
+use crate::drive::demo::DemoDriver;
+use crate::event::{Close, Event, OpenAt, Read, Write};
+use crate::ring::Cancellation;
+
+use super::Metadata;
+
+// The most the kernel reads or writes with one event.
+const MAX_RW_COUNT: u64 = 0x7fff_f000;
+
+// The size of each read and write made when copying a file.
+const COPY_CHUNK: u64 = 1024 * 1024;
+
+/// Read the entire contents of a file into a vector of bytes using the default driver
+pub async fn read(path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+    read_on_driver(path, DemoDriver::default()).await
+}
+
+/// Read the entire contents of a file into a vector of bytes
+///
+/// The file is opened and stated together, and then read with a buffer of the size it had when
+/// it was opened. The file is closed after that read without waiting for another round trip,
+/// unless the read was short, in which case the rest is read until the end of the file.
+pub async fn read_on_driver<D: Drive + Clone>(path: impl AsRef<Path>, driver: D)
+    -> io::Result<Vec<u8>>
+{
+    let (fd, metadata) = open_stat(path.as_ref(), &driver).await?;
+    let size = metadata.filter(Metadata::is_file).map_or(0, |metadata| metadata.len());
+
+    if size > 0 && size <= MAX_RW_COUNT {
+        let buf = vec![0; size as usize].into_boxed_slice();
+        let raw = fd.into_raw();
+        let (read, result) = driver.clone().submit(ReadClose { fd: raw, buf }).await;
+        match result {
+            Ok(_)                   => return Ok(read.buf.into_vec()),
+            // The read was short or failed, so the close was never run.
+            Err(err) if cancelled(&err) => {
+                let fd = Fd(raw);
+                let mut contents = read.buf.into_vec();
+                contents.clear();
+                read_to_end(&fd, &mut contents, &driver).await?;
+                close(fd, &driver).await?;
+                return Ok(contents);
+            }
+            // The close itself failed, but the file descriptor is released all the same.
+            Err(err)                => return Err(err),
+        }
+    }
+
+    let mut contents = Vec::with_capacity(size as usize);
+    read_to_end(&fd, &mut contents, &driver).await?;
+    close(fd, &driver).await?;
+    Ok(contents)
+}
+
+/// Read the entire contents of a file into a string using the default driver
+pub async fn read_to_string(path: impl AsRef<Path>) -> io::Result<String> {
+    read_to_string_on_driver(path, DemoDriver::default()).await
+}
+
+/// Read the entire contents of a file into a string
+///
+/// Returns an `InvalidData` error if the file is not valid UTF-8.
+pub async fn read_to_string_on_driver<D: Drive + Clone>(path: impl AsRef<Path>, driver: D)
+    -> io::Result<String>
+{
+    let contents = read_on_driver(path, driver).await?;
+    String::from_utf8(contents).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Write `contents` to a file using the default driver, creating it if it does not exist and
+/// truncating it if it does
+pub async fn write(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    write_on_driver(path, contents, DemoDriver::default()).await
+}
+
+/// Write `contents` to a file, creating it if it does not exist and truncating it if it does
+///
+/// The contents are written and the file closed with one round trip, unless the write is short,
+/// in which case the rest is written before closing it.
+pub async fn write_on_driver<D: Drive + Clone>(
+    path: impl AsRef<Path>,
+    contents: impl AsRef<[u8]>,
+    driver: D,
+) -> io::Result<()> {
+    let flags = OFlag::O_WRONLY | OFlag::O_CREAT | OFlag::O_TRUNC | OFlag::O_CLOEXEC;
+    let fd = open(path.as_ref(), flags, Mode::from_bits_truncate(0o666), &driver).await?;
+
+    let contents = contents.as_ref();
+    if contents.is_empty() || contents.len() as u64 > MAX_RW_COUNT {
+        write_all(&fd, contents, 0, &driver).await?;
+        return close(fd, &driver).await;
+    }
+
+    let raw = fd.into_raw();
+    let (_, result) = driver.clone().submit(WriteClose { fd: raw, buf: contents.into() }).await;
+    match result {
+        Ok(_)                       => Ok(()),
+        // The write was short or failed, so the close was never run. Writing at an offset can be
+        // repeated, so everything is written again.
+        Err(err) if cancelled(&err) => {
+            let fd = Fd(raw);
+            write_all(&fd, contents, 0, &driver).await?;
+            close(fd, &driver).await
+        }
+        Err(err)                    => Err(err),
+    }
+}
+
+/// Copy the contents of a file to another file using the default driver, returning the number
+/// of bytes copied
+pub async fn copy(from: impl AsRef<Path>, to: impl AsRef<Path>) -> io::Result<u64> {
+    copy_on_driver(from, to, DemoDriver::default()).await
+}
+
+/// Copy the contents of a file to another file, returning the number of bytes copied
+///
+/// Like `std::fs::copy`, the destination is created if it does not exist and truncated if it
+/// does, and it is given the permissions of the source. Each chunk of the file is read and
+/// written in one round trip, and both files are closed together.
+pub async fn copy_on_driver<D: Drive + Clone>(
+    from: impl AsRef<Path>,
+    to: impl AsRef<Path>,
+    driver: D,
+) -> io::Result<u64> {
+    let (src, metadata) = open_stat(from.as_ref(), &driver).await?;
+    let metadata = match metadata {
+        Some(metadata) if !metadata.is_file()   => {
+            let msg = "the source path is neither a regular file nor a symlink to a regular file";
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+        }
+        metadata                                => metadata,
+    };
+    let mode = metadata.as_ref().map(|metadata| metadata.permissions().mode() & 0o7777);
+    let size = metadata.map_or(0, |metadata| metadata.len());
+
+    let flags = OFlag::O_WRONLY | OFlag::O_CREAT | OFlag::O_TRUNC | OFlag::O_CLOEXEC;
+    let create_mode = Mode::from_bits_truncate(mode.unwrap_or(0o666));
+    let dst = open(to.as_ref(), flags, create_mode, &driver).await?;
+
+    // Chunks are read and written with one round trip each, until the size the file had when it
+    // was opened has been copied. If it is shorter than that, the rest is copied to its end.
+    let mut offset = 0;
+    let mut buf: Option<Box<[u8]>> = None;
+    let mut to_end = size == 0;
+    while offset < size {
+        let len = cmp::min(size - offset, COPY_CHUNK) as usize;
+        let chunk = buf.take().filter(|buf| buf.len() == len);
+        let chunk = chunk.unwrap_or_else(|| vec![0; len].into_boxed_slice());
+        let copy = ReadWrite { from: src.0, to: dst.0, buf: chunk, offset };
+        let (copy, result) = driver.clone().submit(copy).await;
+        match result {
+            Ok(n)                       => {
+                let n = n as usize;
+                write_all(&dst, &copy.buf[n..], offset + n as u64, &driver).await?;
+                offset += len as u64;
+                buf = Some(copy.buf);
+            }
+            // The read was short or failed, so the write was never run.
+            Err(err) if cancelled(&err) => {
+                to_end = true;
+                break;
+            }
+            Err(err)                    => return Err(err),
+        }
+    }
+
+    if to_end {
+        let mut buf = vec![0; DEFAULT_CAPACITY].into_boxed_slice();
+        loop {
+            let (read, result) = driver.clone().submit(Read { fd: src.0, buf, offset }).await;
+            let n = result? as usize;
+            if n == 0 {
+                break;
+            }
+            write_all(&dst, &read.buf[..n], offset, &driver).await?;
+            offset += n as u64;
+            buf = read.buf;
+        }
+    }
+
+    if let Some(mode) = mode {
+        // New files are created with the umask applied, and existing files keep their
+        // permissions, so the permissions are set again.
+        let fd = dst.0;
+        blocking::spawn(move || match unsafe { libc::fchmod(fd, mode) } {
+            0   => Ok(()),
+            _   => Err(io::Error::last_os_error()),
+        }).await?;
+    }
+
+    let closes = [Close { fd: src.into_raw() }, Close { fd: dst.into_raw() }];
+    for (_, result) in driver.submit_all(closes).join().await? {
+        result?;
+    }
+    Ok(offset)
+}
+
+// A file descriptor which is closed if it is dropped before it is closed through io-uring.
+pub(super) struct Fd(pub(super) RawFd);
+
+impl Fd {
+    // Take the file descriptor for an event which closes it. If that event is cancelled, the file
+    // descriptor is leaked rather than closed while the kernel may still be closing it.
+    pub(super) fn into_raw(self) -> RawFd {
+        let fd = self.0;
+        mem::forget(self);
+        fd
+    }
+}
+
+impl Drop for Fd {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.0); }
+    }
+}
+
+async fn open<D: Drive + Clone>(path: &Path, flags: OFlag, mode: Mode, driver: &D)
+    -> io::Result<Fd>
+{
+    let (_, result) = driver.clone().submit(OpenAt::without_dir(path, flags, mode)).await;
+    Ok(Fd(result? as RawFd))
+}
+
+// Open a file for reading and query its metadata with one round trip. The metadata is `None` if
+// it could not be queried.
+async fn open_stat<D: Drive + Clone>(path: &Path, driver: &D)
+    -> io::Result<(Fd, Option<Metadata>)>
+{
+    let path = CString::new(path.as_os_str().as_bytes()).unwrap();
+    let statx = Box::new(unsafe { mem::zeroed() });
+    let (open, result) = driver.clone().submit(StatOpen { path, statx }).await;
+    let fd = Fd(result? as RawFd);
+    let metadata = match open.statx.stx_mask & Metadata::mask().bits() as u32 {
+        0   => None,
+        _   => Some(Metadata::from_statx(*open.statx)),
+    };
+    Ok((fd, metadata))
+}
+
+async fn read_to_end<D: Drive + Clone>(fd: &Fd, contents: &mut Vec<u8>, driver: &D)
+    -> io::Result<()>
+{
+    let mut buf = vec![0; DEFAULT_CAPACITY].into_boxed_slice();
+    loop {
+        let read = Read { fd: fd.0, buf, offset: contents.len() as u64 };
+        let (read, result) = driver.clone().submit(read).await;
+        match result? as usize {
+            0   => return Ok(()),
+            n   => contents.extend_from_slice(&read.buf[..n]),
+        }
+        buf = read.buf;
+    }
+}
+
+async fn write_all<D: Drive + Clone>(fd: &Fd, mut buf: &[u8], mut offset: u64, driver: &D)
+    -> io::Result<()>
+{
+    while !buf.is_empty() {
+        let len = cmp::min(buf.len() as u64, MAX_RW_COUNT) as usize;
+        let write = Write { fd: fd.0, buf: buf[..len].into(), offset };
+        let (_, result) = driver.clone().submit(write).await;
+        match result? as usize {
+            0   => return Err(io::ErrorKind::WriteZero.into()),
+            n   => {
+                buf = &buf[n..];
+                offset += n as u64;
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn close<D: Drive + Clone>(fd: Fd, driver: &D) -> io::Result<()> {
+    let (_, result) = driver.clone().submit(Close { fd: fd.into_raw() }).await;
+    result.map(drop)
+}
+
+// Linked events complete with the result of the last event in the chain. A read or write which
+// is short breaks the chain, so the events linked after it complete with `ECANCELED`.
+fn cancelled(err: &io::Error) -> bool {
+    err.raw_os_error() == Some(libc::ECANCELED)
+}
+
+// Query the metadata of a path, then open it for reading. The open is hard linked, so it runs
+// even if the path could not be stated.
+struct StatOpen {
+    path: CString,
+    statx: Box<libc::statx>,
+}
+
+impl Event for StatOpen {
+    fn sqes_needed(&self) -> u32 { 2 }
+
+    unsafe fn prepare<'sq>(&mut self, sqs: &mut SQEs<'sq>) -> SQE<'sq> {
+        let mut linked = sqs.hard_linked();
+        let mut statx = linked.next().unwrap();
+        let flags = StatxFlags::empty();
+        statx.prep_statx(libc::AT_FDCWD, &self.path, flags, Metadata::mask(), &mut self.statx);
+        drop(statx);
+        let mut open = linked.terminate().unwrap();
+        let flags = OFlag::O_RDONLY | OFlag::O_CLOEXEC;
+        open.prep_openat(libc::AT_FDCWD, &self.path, flags, Mode::empty());
+        open
+    }
+
+    fn cancel(this: ManuallyDrop<Self>) -> Cancellation {
+        let this = ManuallyDrop::into_inner(this);
+        Cancellation::from((this.statx, this.path))
+    }
+}
+
+// Read all of `buf` from the start of a file, then close it.
+struct ReadClose {
+    fd: RawFd,
+    buf: Box<[u8]>,
+}
+
+impl Event for ReadClose {
+    fn sqes_needed(&self) -> u32 { 2 }
+
+    unsafe fn prepare<'sq>(&mut self, sqs: &mut SQEs<'sq>) -> SQE<'sq> {
+        let mut linked = sqs.soft_linked();
+        linked.next().unwrap().prep_read(self.fd, &mut self.buf[..], 0);
+        let mut close = linked.terminate().unwrap();
+        close.prep_close(self.fd);
+        close
+    }
+
+    fn cancel(this: ManuallyDrop<Self>) -> Cancellation {
+        Cancellation::from(ManuallyDrop::into_inner(this).buf)
+    }
+}
+
+// Write all of `buf` to the start of a file, then close it.
+struct WriteClose {
+    fd: RawFd,
+    buf: Box<[u8]>,
+}
+
+impl Event for WriteClose {
+    fn sqes_needed(&self) -> u32 { 2 }
+
+    unsafe fn prepare<'sq>(&mut self, sqs: &mut SQEs<'sq>) -> SQE<'sq> {
+        let mut linked = sqs.soft_linked();
+        linked.next().unwrap().prep_write(self.fd, &self.buf[..], 0);
+        let mut close = linked.terminate().unwrap();
+        close.prep_close(self.fd);
+        close
+    }
+
+    fn cancel(this: ManuallyDrop<Self>) -> Cancellation {
+        Cancellation::from(ManuallyDrop::into_inner(this).buf)
+    }
+}
+
+// Read all of `buf` from one file at `offset`, then write it to another at the same offset.
+struct ReadWrite {
+    from: RawFd,
+    to: RawFd,
+    buf: Box<[u8]>,
+    offset: u64,
+}
+
+impl Event for ReadWrite {
+    fn sqes_needed(&self) -> u32 { 2 }
+
+    unsafe fn prepare<'sq>(&mut self, sqs: &mut SQEs<'sq>) -> SQE<'sq> {
+        let mut linked = sqs.soft_linked();
+        linked.next().unwrap().prep_read(self.from, &mut self.buf[..], self.offset);
+        let mut write = linked.terminate().unwrap();
+        write.prep_write(self.to, &self.buf[..], self.offset);
+        write
+    }
+
+    fn cancel(this: ManuallyDrop<Self>) -> Cancellation {
+        Cancellation::from(ManuallyDrop::into_inner(this).buf)
+    }
+}
@@ -0,0 +1,152 @@
+use std::fs;
+use std::io;
+use std::mem::ManuallyDrop;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::{Component, Path};
+
+use iou::sqe::{OFlag, Mode, StatxFlags};
+
+use crate::drive::Drive;
+use crate::drive::demo::DemoDriver;
+use crate::event::{Close, MkdirAt, OpenAt, OpenAt2, ResolveFlags, Statx};
+
+use super::{File, Metadata};
+use super::contents::Fd;
+
+/// A handle to an open directory, which opens and inspects paths relative to itself
+///
+/// Every path is resolved with `openat2`, using the directory's resolve flags. By default these
+/// are `RESOLVE_BENEATH`, so that a path which would escape the directory (through `..`, an
+/// absolute path or a symbolic link) fails with `EXDEV` instead. Directories opened through a
+/// `Dir` inherit its resolve flags.
+pub struct Dir<D: Drive = DemoDriver> {
+    fd: RawFd,
+    resolve: ResolveFlags,
+    driver: D,
+}
+
+impl Dir {
+    /// Open a directory using the default driver
+    pub async fn open(path: impl AsRef<Path>) -> io::Result<Dir> {
+        Dir::open_on_driver(path, DemoDriver::default()).await
+    }
+}
+
+impl<D: Drive + Clone> Dir<D> {
+    /// Open a directory
+    pub async fn open_on_driver(path: impl AsRef<Path>, driver: D) -> io::Result<Dir<D>> {
+        let flags = OFlag::O_CLOEXEC | OFlag::O_RDONLY | OFlag::O_DIRECTORY;
+        let open = OpenAt::without_dir(path, flags, Mode::empty());
+        let (_, result) = driver.clone().submit(open).await;
+        Ok(Dir::from_fd(result? as RawFd, driver))
+    }
+
+    /// Take an existing directory and run its IO on an io-uring driver
+    pub fn run_on_driver(dir: fs::File, driver: D) -> Dir<D> {
+        let dir = ManuallyDrop::new(dir);
+        Dir::from_fd(dir.as_raw_fd(), driver)
+    }
+
+    fn from_fd(fd: RawFd, driver: D) -> Dir<D> {
+        Dir { fd, resolve: ResolveFlags::RESOLVE_BENEATH, driver }
+    }
+
+    /// Set the flags used to resolve paths relative to this directory
+    ///
+    /// `RESOLVE_IN_ROOT` treats the directory as the root of the filesystem instead of rejecting
+    /// paths which leave it. Resolving with no flags at all allows any path, like `openat`.
+    pub fn with_resolve_flags(mut self, resolve: ResolveFlags) -> Dir<D> {
+        self.resolve = resolve;
+        self
+    }
+
+    /// The flags used to resolve paths relative to this directory
+    pub fn resolve_flags(&self) -> ResolveFlags {
+        self.resolve
+    }
+
+    /// Open a file in this directory for reading
+    pub async fn open_file(&self, path: impl AsRef<Path>) -> io::Result<File<D>> {
+        let flags = OFlag::O_CLOEXEC | OFlag::O_RDONLY;
+        let fd = self.openat2(path, flags, Mode::empty()).await?;
+        Ok(File::from_fd(fd, self.driver.clone()))
+    }
+
+    /// Create a file in this directory, truncating it if it exists
+    pub async fn create_file(&self, path: impl AsRef<Path>) -> io::Result<File<D>> {
+        let flags = OFlag::O_CLOEXEC | OFlag::O_WRONLY | OFlag::O_CREAT | OFlag::O_TRUNC;
+        let fd = self.openat2(path, flags, Mode::from_bits(0o666).unwrap()).await?;
+        Ok(File::from_fd(fd, self.driver.clone()))
+    }
+
+    /// Open a subdirectory of this directory, with the same resolve flags
+    pub async fn open_dir(&self, path: impl AsRef<Path>) -> io::Result<Dir<D>> {
+        let flags = OFlag::O_CLOEXEC | OFlag::O_RDONLY | OFlag::O_DIRECTORY;
+        let fd = self.openat2(path, flags, Mode::empty()).await?;
+        Ok(Dir { fd, resolve: self.resolve, driver: self.driver.clone() })
+    }
+
+    /// Query the metadata of an entry in this directory, following symbolic links
+    ///
+    /// The entry is opened with `O_PATH` so that it is resolved under the directory's resolve
+    /// flags, and then stat'd through that file descriptor.
+    pub async fn metadata(&self, path: impl AsRef<Path>) -> io::Result<Metadata> {
+        let fd = self.openat2(path, OFlag::O_CLOEXEC | OFlag::O_PATH, Mode::empty()).await?;
+        let fd = Fd(fd);
+        let statx = Statx::without_path(fd.0, StatxFlags::empty(), Metadata::mask());
+        let (statx, result) = self.driver.clone().submit(statx).await;
+        let (_, closed) = self.driver.clone().submit(Close { fd: fd.into_raw() }).await;
+        result?;
+        closed?;
+        Ok(Metadata::from_statx(*statx.statx))
+    }
+
+    /// Create a subdirectory in this directory
+    ///
+    /// The parent of the new directory is resolved under the directory's resolve flags, so the
+    /// final component of the path must be a plain name.
+    pub async fn create_dir(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let name = match path.components().next_back() {
+            Some(Component::Normal(name))   => name,
+            _                               => return Err(io::Error::from_raw_os_error(libc::EINVAL)),
+        };
+        let parent = path.parent().filter(|parent| !parent.as_os_str().is_empty());
+
+        let parent_fd = match parent {
+            Some(parent)    => {
+                let flags = OFlag::O_CLOEXEC | OFlag::O_PATH | OFlag::O_DIRECTORY;
+                Some(Fd(self.openat2(parent, flags, Mode::empty()).await?))
+            }
+            None            => None,
+        };
+
+        let mut mkdir = MkdirAt::without_dir(name, Mode::from_bits(0o777).unwrap());
+        mkdir.dir_fd = parent_fd.as_ref().map_or(self.fd, |fd| fd.0);
+        let (_, result) = self.driver.clone().submit(mkdir).await;
+        let closed = match parent_fd {
+            Some(fd)    => self.driver.clone().submit(Close { fd: fd.into_raw() }).await.1,
+            None        => Ok(0),
+        };
+        result?;
+        closed.map(drop)
+    }
+
+    async fn openat2(&self, path: impl AsRef<Path>, flags: OFlag, mode: Mode) -> io::Result<RawFd> {
+        let open = OpenAt2::new(self.fd, path, flags, mode, self.resolve);
+        let (_, result) = self.driver.clone().submit(open).await;
+        Ok(result? as RawFd)
+    }
+}
+
+impl<D: Drive> AsRawFd for Dir<D> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl<D: Drive> Drop for Dir<D> {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.fd); }
+    }
+}
@@ -0,0 +1,170 @@
+use std::fs;
+use std::io;
+use std::os::unix::io::{AsRawFd, IntoRawFd, RawFd};
+use std::path::Path;
+
+use iou::sqe::{StatxFlags, StatxMode};
+
+use crate::buf::AlignedBuf;
+use crate::drive::Drive;
+use crate::drive::demo::DemoDriver;
+use crate::event::{ReadAligned, Statx, WriteAligned};
+
+use super::OpenOptions;
+
+// Not yet known to iou: the alignment restrictions on direct IO, reported since Linux 6.1.
+const STATX_DIOALIGN: u32 = 0x2000;
+
+/// A file opened with `O_DIRECT`, which reads and writes without going through the page cache
+///
+/// Direct IO has to be aligned: the offset and length of every read and write must be multiples
+/// of the file's offset alignment (usually the logical block size of the device), and the buffer
+/// must be at an address which is a multiple of its memory alignment. Both are queried with
+/// `statx` when the file is opened, and every read and write is checked against them, returning
+/// an `InvalidInput` error which says what is misaligned rather than submitting it. Buffers
+/// allocated with [`alloc`](DirectFile::alloc) are always suitably aligned.
+///
+/// Reads and writes are positional, and take ownership of their buffer for as long as the kernel
+/// is using it, returning it alongside their result.
+pub struct DirectFile<D: Drive = DemoDriver> {
+    fd: RawFd,
+    offset_align: usize,
+    mem_align: usize,
+    driver: D,
+}
+
+impl DirectFile {
+    /// Open a file for direct reads using the default driver
+    pub async fn open(path: impl AsRef<Path>) -> io::Result<DirectFile> {
+        DirectFile::open_with(OpenOptions::new().read(true), path).await
+    }
+
+    /// Create a file for direct writes using the default driver, truncating it if it exists
+    pub async fn create(path: impl AsRef<Path>) -> io::Result<DirectFile> {
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        DirectFile::open_with(&options, path).await
+    }
+
+    /// Open a file for direct IO with `options` using the default driver
+    ///
+    /// `O_DIRECT` is added to the options.
+    pub async fn open_with(options: &OpenOptions, path: impl AsRef<Path>)
+        -> io::Result<DirectFile>
+    {
+        DirectFile::open_with_on_driver(options, path, DemoDriver::default()).await
+    }
+}
+
+impl<D: Drive + Clone> DirectFile<D> {
+    /// Open a file for direct IO with `options`
+    ///
+    /// `O_DIRECT` is added to the options.
+    pub async fn open_with_on_driver(options: &OpenOptions, path: impl AsRef<Path>, driver: D)
+        -> io::Result<DirectFile<D>>
+    {
+        let file = options.clone().direct(true).open_on_driver(path, driver.clone()).await?;
+        let fd = fs::File::from(file).into_raw_fd();
+        // The alignment is filled in once it is known; until then, dropping the file closes it.
+        let mut file = DirectFile { fd, offset_align: 1, mem_align: 1, driver };
+
+        // The block size is always reported, whatever the mask.
+        let mask = unsafe { StatxMode::from_bits_unchecked(STATX_DIOALIGN as i32) };
+        let statx = Statx::without_path(fd, StatxFlags::empty(), mask);
+        let (statx, result) = file.driver.clone().submit(statx).await;
+        result?;
+
+        let statx = &statx.statx;
+        if statx.stx_mask & STATX_DIOALIGN != 0 {
+            if statx.stx_dio_offset_align == 0 {
+                let msg = "the file does not support direct IO";
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+            }
+            file.offset_align = statx.stx_dio_offset_align as usize;
+            file.mem_align = statx.stx_dio_mem_align as usize;
+        } else {
+            // Older kernels do not report the alignment, but the block size is always a multiple
+            // of the logical block size of the device.
+            file.offset_align = statx.stx_blksize as usize;
+            file.mem_align = statx.stx_blksize as usize;
+        }
+        Ok(file)
+    }
+
+    /// Read from the file at `offset` into all of `buf`, returning the buffer and the number of
+    /// bytes read
+    ///
+    /// Reads past the end of the file are short, even if the end of the file is not aligned.
+    pub async fn read_at(&self, buf: AlignedBuf, offset: u64) -> (AlignedBuf, io::Result<usize>) {
+        if let Err(err) = self.check_alignment(&buf, offset) {
+            return (buf, Err(err));
+        }
+        let read = ReadAligned { fd: self.fd, buf, offset };
+        let (read, result) = self.driver.clone().submit(read).await;
+        (read.buf, result.map(|n| n as usize))
+    }
+
+    /// Write all of `buf` to the file at `offset`, returning the buffer and the number of bytes
+    /// written
+    pub async fn write_at(&self, buf: AlignedBuf, offset: u64) -> (AlignedBuf, io::Result<usize>) {
+        if let Err(err) = self.check_alignment(&buf, offset) {
+            return (buf, Err(err));
+        }
+        let write = WriteAligned { fd: self.fd, buf, offset };
+        let (write, result) = self.driver.clone().submit(write).await;
+        (write.buf, result.map(|n| n as usize))
+    }
+}
+
+impl<D: Drive> DirectFile<D> {
+    /// The alignment required of the offset and length of every read and write
+    pub fn offset_align(&self) -> usize {
+        self.offset_align
+    }
+
+    /// The alignment required of the address of every buffer
+    pub fn mem_align(&self) -> usize {
+        self.mem_align
+    }
+
+    /// Allocate a zeroed buffer for reads and writes on this file
+    ///
+    /// The buffer is aligned to both the memory and offset alignment of the file. Its length
+    /// still has to be a multiple of the offset alignment to be read or written.
+    pub fn alloc(&self, len: usize) -> AlignedBuf {
+        AlignedBuf::new(len, self.mem_align.max(self.offset_align))
+    }
+
+    fn check_alignment(&self, buf: &AlignedBuf, offset: u64) -> io::Result<()> {
+        let misaligned = |what: String, align: usize| {
+            let msg = format!("direct IO {} is not a multiple of {}", what, align);
+            Err(io::Error::new(io::ErrorKind::InvalidInput, msg))
+        };
+        if !is_aligned(offset, self.offset_align) {
+            misaligned(format!("offset {}", offset), self.offset_align)
+        } else if !is_aligned(buf.len() as u64, self.offset_align) {
+            misaligned(format!("length {}", buf.len()), self.offset_align)
+        } else if !is_aligned(buf.as_ptr() as u64, self.mem_align) {
+            misaligned(format!("buffer address {:p}", buf.as_ptr()), self.mem_align)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+// The alignments the kernel reports are powers of two, like those of `AlignedBuf`.
+fn is_aligned(value: u64, align: usize) -> bool {
+    value & (align as u64 - 1) == 0
+}
+
+impl<D: Drive> AsRawFd for DirectFile<D> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl<D: Drive> Drop for DirectFile<D> {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.fd); }
+    }
+}
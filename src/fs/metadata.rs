@@ -0,0 +1,252 @@
+use std::fmt;
+use std::fs::Permissions;
+use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use iou::sqe::StatxMode;
+
+const STATX_MNT_ID: u32 = 0x1000;
+
+bitflags::bitflags! {
+    /// Attributes of a file, as reported in the `stx_attributes` field of `statx(2)`.
+    pub struct Attributes: u64 {
+        /// The file is compressed by the filesystem.
+        const COMPRESSED    = 0x0004;
+        /// The file cannot be modified, deleted or renamed.
+        const IMMUTABLE     = 0x0010;
+        /// The file can only be opened in append mode for writing.
+        const APPEND        = 0x0020;
+        /// The file is not a candidate for backup by dump(8).
+        const NODUMP        = 0x0040;
+        /// The file requires a key to be decrypted by the filesystem.
+        const ENCRYPTED     = 0x0800;
+        /// The file is an automount trigger.
+        const AUTOMOUNT     = 0x1000;
+        /// The file is the root of a mount.
+        const MOUNT_ROOT    = 0x2000;
+        /// The file has fs-verity enabled.
+        const VERITY        = 0x0010_0000;
+        /// The file is in DAX (direct access) mode.
+        const DAX           = 0x0020_0000;
+    }
+}
+
+/// Metadata about a file, as reported by `statx(2)`
+///
+/// The kernel may not fill in every field (for example, some filesystems do not record when a
+/// file was created). [`fields`](Metadata::fields) reports which fields were filled in; accessors
+/// for fields which are not always available return an error or `None` if they were not.
+#[derive(Clone)]
+pub struct Metadata {
+    statx: libc::statx,
+}
+
+/// The type of a file
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct FileType {
+    mode: u32,
+}
+
+impl Metadata {
+    /// The fields requested when ringbahn stats a file: the basic stats, the creation time and
+    /// the mount id.
+    pub fn mask() -> StatxMode {
+        // The mount id is not known to iou, but the kernel ignores any bits it does not know.
+        unsafe { StatxMode::from_bits_unchecked(StatxMode::all().bits() | STATX_MNT_ID as i32) }
+    }
+
+    /// Construct metadata from the result of a `Statx` event.
+    pub fn from_statx(statx: libc::statx) -> Metadata {
+        Metadata { statx }
+    }
+
+    /// The raw result of `statx(2)`
+    pub fn as_statx(&self) -> &libc::statx {
+        &self.statx
+    }
+
+    /// The fields the kernel filled in, from `stx_mask`
+    pub fn fields(&self) -> StatxMode {
+        StatxMode::from_bits_truncate(self.statx.stx_mask as i32)
+    }
+
+    fn has(&self, field: StatxMode) -> bool {
+        self.fields().contains(field)
+    }
+
+    /// The type of this file
+    pub fn file_type(&self) -> FileType {
+        FileType { mode: self.statx.stx_mode as u32 }
+    }
+
+    /// Returns true if this is a directory
+    pub fn is_dir(&self) -> bool {
+        self.file_type().is_dir()
+    }
+
+    /// Returns true if this is a regular file
+    pub fn is_file(&self) -> bool {
+        self.file_type().is_file()
+    }
+
+    /// Returns true if this is a symbolic link
+    pub fn is_symlink(&self) -> bool {
+        self.file_type().is_symlink()
+    }
+
+    /// The permissions of this file
+    pub fn permissions(&self) -> Permissions {
+        Permissions::from_mode(self.statx.stx_mode as u32 & 0o7777)
+    }
+
+    /// The size of this file in bytes
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> u64 {
+        self.statx.stx_size
+    }
+
+    /// The number of 512 byte blocks allocated to this file
+    pub fn blocks(&self) -> u64 {
+        self.statx.stx_blocks
+    }
+
+    /// The preferred block size for IO on this file
+    pub fn blksize(&self) -> u32 {
+        self.statx.stx_blksize
+    }
+
+    /// The number of hard links to this file
+    pub fn nlink(&self) -> u32 {
+        self.statx.stx_nlink
+    }
+
+    /// The user id of the owner of this file
+    pub fn uid(&self) -> u32 {
+        self.statx.stx_uid
+    }
+
+    /// The group id of the owner of this file
+    pub fn gid(&self) -> u32 {
+        self.statx.stx_gid
+    }
+
+    /// The inode number of this file
+    pub fn ino(&self) -> u64 {
+        self.statx.stx_ino
+    }
+
+    /// The id of the device containing this file
+    pub fn dev(&self) -> u64 {
+        libc::makedev(self.statx.stx_dev_major, self.statx.stx_dev_minor)
+    }
+
+    /// The id of the device this file represents, if it is a device file
+    pub fn rdev(&self) -> u64 {
+        libc::makedev(self.statx.stx_rdev_major, self.statx.stx_rdev_minor)
+    }
+
+    /// The id of the mount containing this file, if the kernel reported it
+    pub fn mount_id(&self) -> Option<u64> {
+        match self.statx.stx_mask & STATX_MNT_ID {
+            0   => None,
+            _   => Some(self.statx.stx_mnt_id),
+        }
+    }
+
+    /// The attributes of this file which are set
+    pub fn attributes(&self) -> Attributes {
+        Attributes::from_bits_truncate(self.statx.stx_attributes & self.statx.stx_attributes_mask)
+    }
+
+    /// The attributes this file's filesystem supports, whether or not they are set
+    pub fn supported_attributes(&self) -> Attributes {
+        Attributes::from_bits_truncate(self.statx.stx_attributes_mask)
+    }
+
+    /// The time this file was last accessed
+    pub fn accessed(&self) -> io::Result<SystemTime> {
+        self.time(StatxMode::STATX_ATIME, &self.statx.stx_atime)
+    }
+
+    /// The time this file's contents were last modified
+    pub fn modified(&self) -> io::Result<SystemTime> {
+        self.time(StatxMode::STATX_MTIME, &self.statx.stx_mtime)
+    }
+
+    /// The time this file's metadata was last changed
+    pub fn changed(&self) -> io::Result<SystemTime> {
+        self.time(StatxMode::STATX_CTIME, &self.statx.stx_ctime)
+    }
+
+    /// The time this file was created
+    pub fn created(&self) -> io::Result<SystemTime> {
+        self.time(StatxMode::STATX_BTIME, &self.statx.stx_btime)
+    }
+
+    fn time(&self, field: StatxMode, time: &libc::statx_timestamp) -> io::Result<SystemTime> {
+        if !self.has(field) {
+            let msg = "timestamp not reported by the kernel";
+            return Err(io::Error::new(io::ErrorKind::Unsupported, msg));
+        }
+        let nanos = Duration::from_nanos(time.tv_nsec as u64);
+        Ok(match time.tv_sec {
+            secs if secs >= 0   => UNIX_EPOCH + Duration::from_secs(secs as u64) + nanos,
+            secs                => UNIX_EPOCH - Duration::from_secs(secs.unsigned_abs()) + nanos,
+        })
+    }
+}
+
+impl fmt::Debug for Metadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Metadata")
+            .field("file_type", &self.file_type())
+            .field("permissions", &self.permissions())
+            .field("len", &self.len())
+            .field("ino", &self.ino())
+            .field("dev", &self.dev())
+            .field("fields", &self.fields())
+            .finish()
+    }
+}
+
+impl FileType {
+    /// Returns true if this is a directory
+    pub fn is_dir(&self) -> bool {
+        self.is(libc::S_IFDIR)
+    }
+
+    /// Returns true if this is a regular file
+    pub fn is_file(&self) -> bool {
+        self.is(libc::S_IFREG)
+    }
+
+    /// Returns true if this is a symbolic link
+    pub fn is_symlink(&self) -> bool {
+        self.is(libc::S_IFLNK)
+    }
+
+    /// Returns true if this is a block device
+    pub fn is_block_device(&self) -> bool {
+        self.is(libc::S_IFBLK)
+    }
+
+    /// Returns true if this is a character device
+    pub fn is_char_device(&self) -> bool {
+        self.is(libc::S_IFCHR)
+    }
+
+    /// Returns true if this is a FIFO
+    pub fn is_fifo(&self) -> bool {
+        self.is(libc::S_IFIFO)
+    }
+
+    /// Returns true if this is a socket
+    pub fn is_socket(&self) -> bool {
+        self.is(libc::S_IFSOCK)
+    }
+
+    fn is(&self, kind: libc::mode_t) -> bool {
+        self.mode & libc::S_IFMT == kind
+    }
+}
@@ -0,0 +1,1008 @@
+//! Interact with the file system using io-uring
+
+mod contents;
+mod dir;
+mod direct;
+mod metadata;
+mod open_options;
+mod read_ahead;
+mod split;
+mod write_behind;
+
+use std::fs;
+use std::future::{self, Future};
+use std::io;
+use std::mem::{self, ManuallyDrop};
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::path::Path;
+use std::pin::Pin;
+use std::ptr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::{Context, Poll};
+
+use futures_core::ready;
+use futures_io::{AsyncRead, AsyncBufRead, AsyncWrite, AsyncSeek};
+use iou::sqe::{OFlag, Mode, StatxFlags, FsyncFlags, FallocateFlags, PosixFadviseAdvice};
+use nix::fcntl::AtFlags;
+
+use crate::blocking;
+use crate::buf::DEFAULT_CAPACITY;
+use crate::drive::Drive;
+use crate::drive::demo::DemoDriver;
+use crate::duplex::{Fd, Half};
+use crate::ring::{Ring, Cancellation};
+use crate::event::{OpenAt, UnlinkAt, RenameAt, RenameFlags, MkdirAt, LinkAt, SymlinkAt, Statx};
+use crate::event::{Read, Write, Fadvise, Fallocate, Ftruncate, SyncFileRangeFlags, prep_sync_file_range};
+use crate::{Event, Submission};
+
+use read_ahead::ReadAhead;
+use write_behind::WriteBehind;
+
+pub use contents::{read, read_on_driver, read_to_string, read_to_string_on_driver};
+pub use contents::{write, write_on_driver, copy, copy_on_driver};
+pub use dir::Dir;
+pub use direct::DirectFile;
+pub use metadata::{Attributes, FileType, Metadata};
+pub use open_options::OpenOptions;
+pub use split::{OwnedReadHalf, OwnedWriteHalf};
+
+/// A file handle that runs on io-uring
+///
+/// Reads and writes have separate buffers, but share the file's cursor, so a write cancels any
+/// read in flight: use [`into_split`](File::into_split) to read and write at the same time. Both
+/// start at the file's cursor and advance it when they complete. Data which has been read into a
+/// buffer but not consumed is not past the cursor: a write or seek discards it, along with any
+/// read still in flight, and starts from the end of the data that has been consumed.
+///
+/// If the file was opened for appending, every write goes to the end of the file, and the cursor
+/// is moved to the end of the file once it is next used.
+pub struct File<D: Drive = DemoDriver> {
+    read: Half<D>,
+    read_ahead: Option<ReadAhead<D>>,
+    write: Half<D>,
+    write_behind: Option<WriteBehind<D>>,
+    ring: Ring<D>,
+    statx: Option<Box<libc::statx>>,
+    fd: RawFd,
+    active: Op,
+    // The offset the next read starts at. The cursor is behind it by the data which has been read
+    // into the buffers but not consumed.
+    pos: u64,
+    // Whether the file was opened with O_APPEND, and whether a write has been appended since the
+    // cursor was last moved to the end of the file.
+    append: bool,
+    appended: bool,
+}
+
+// Set once a truncate through io-uring has failed, but the same truncate succeeded on a thread.
+static FTRUNCATE_UNSUPPORTED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Op {
+    Close,
+    Nothing,
+    Statx,
+    Fsync(FsyncFlags),
+    SyncRange(u64, u32, SyncFileRangeFlags),
+    Closed,
+}
+
+impl File {
+    /// Open a file using the default driver
+    pub fn open(path: impl AsRef<Path>) -> Open {
+        File::open_on_driver(path, DemoDriver::default())
+    }
+
+    /// Create a new file using the default driver
+    pub fn create(path: impl AsRef<Path>) -> Create {
+        File::create_on_driver(path, DemoDriver::default())
+    }
+
+    /// Take an existing file and run its IO on the default driver, with read and write buffers of
+    /// `capacity` bytes
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0 or not less than 4 GiB.
+    pub fn with_capacity(capacity: usize, file: fs::File) -> File {
+        File::with_capacity_on_driver(capacity, file, DemoDriver::default())
+    }
+
+    /// Return a new [`OpenOptions`], to open a file with options other than those used by
+    /// `open` and `create`
+    pub fn options() -> OpenOptions {
+        OpenOptions::new()
+    }
+}
+
+impl<D: Drive + Clone> File<D> {
+    /// Open a file
+    pub fn open_on_driver(path: impl AsRef<Path>, driver: D) -> Open<D> {
+        OpenOptions::new().read(true).open_on_driver(path, driver)
+    }
+
+    /// Create a file
+    pub fn create_on_driver(path: impl AsRef<Path>, driver: D) -> Create<D> {
+        let flags = OFlag::O_CLOEXEC | OFlag::O_WRONLY | OFlag::O_CREAT | OFlag::O_TRUNC;
+        Create(driver.submit(OpenAt::without_dir(path, flags, Mode::from_bits(0o666).unwrap())))
+    }
+
+    /// Take an existing file and run its IO on an io-uring driver
+    pub fn run_on_driver(file: fs::File, driver: D) -> File<D> {
+        let file = ManuallyDrop::new(file);
+        File::from_fd(file.as_raw_fd(), driver)
+    }
+
+    /// Take an existing file and run its IO on an io-uring driver, with read and write buffers of
+    /// `capacity` bytes rather than the default of 8 KiB
+    ///
+    /// Reads into a slice larger than the read buffer are made in one event of up to 64 times its
+    /// capacity, as long as there is no buffered data left to return first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0 or not less than 4 GiB.
+    pub fn with_capacity_on_driver(capacity: usize, file: fs::File, driver: D) -> File<D> {
+        let file = ManuallyDrop::new(file);
+        File::from_fd_with_capacity(file.as_raw_fd(), driver, capacity)
+    }
+
+    /// Query the metadata of this file
+    ///
+    /// If write-behind is on, this returns an error while it holds data which has not been
+    /// flushed, which the metadata would not reflect.
+    pub async fn metadata(&self) -> io::Result<Metadata> {
+        self.guard_write_behind()?;
+        let statx = Statx::without_path(self.fd, StatxFlags::empty(), Metadata::mask());
+        let (statx, result) = self.ring.driver().clone().submit(statx).await;
+        result?;
+        Ok(Metadata::from_statx(*statx.statx))
+    }
+
+    /// Read from the file at `offset` into `buf`, without using or moving the file's cursor
+    ///
+    /// The data is read into a buffer owned by this future and then copied into `buf`, so the
+    /// file's own read buffer is not disturbed and any number of positional reads and writes can
+    /// be in flight on the same file at once.
+    ///
+    /// Positional IO does not go through write-behind: if it is on, this returns an error while
+    /// it holds data which has not been flushed, which this read might not see.
+    pub async fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        self.guard_io();
+        self.guard_write_behind()?;
+        let read = Read { fd: self.fd, buf: vec![0; buf.len()].into_boxed_slice(), offset };
+        let (read, result) = self.ring.driver().clone().submit(read).await;
+        let n = result? as usize;
+        buf[..n].copy_from_slice(&read.buf[..n]);
+        Ok(n)
+    }
+
+    /// Read exactly enough bytes to fill `buf` from the file at `offset`, without using or moving
+    /// the file's cursor
+    pub async fn read_exact_at(&self, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+        while !buf.is_empty() {
+            match self.read_at(buf, offset).await? {
+                0   => return Err(io::ErrorKind::UnexpectedEof.into()),
+                n   => {
+                    buf = &mut buf[n..];
+                    offset += n as u64;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Write `buf` to the file at `offset`, without using or moving the file's cursor
+    ///
+    /// As with `read_at`, the data is copied into a buffer owned by this future, and this returns
+    /// an error while write-behind holds data which has not been flushed, which could later
+    /// overwrite this write.
+    pub async fn write_at(&self, buf: &[u8], offset: u64) -> io::Result<usize> {
+        self.guard_io();
+        self.guard_write_behind()?;
+        let write = Write { fd: self.fd, buf: buf.into(), offset };
+        let (_, result) = self.ring.driver().clone().submit(write).await;
+        Ok(result? as usize)
+    }
+
+    /// Write all of `buf` to the file at `offset`, without using or moving the file's cursor
+    pub async fn write_all_at(&self, mut buf: &[u8], mut offset: u64) -> io::Result<()> {
+        while !buf.is_empty() {
+            match self.write_at(buf, offset).await? {
+                0   => return Err(io::ErrorKind::WriteZero.into()),
+                n   => {
+                    buf = &buf[n..];
+                    offset += n as u64;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Truncate or extend the file to `len` bytes, after flushing any buffered writes
+    ///
+    /// The file's cursor is not moved, even if it is now past the end of the file. On kernels
+    /// which cannot truncate files through io-uring, the truncate runs on a thread pool instead.
+    pub async fn set_len(&mut self, len: u64) -> io::Result<()> where D: Unpin {
+        self.flush_writes().await?;
+        if !FTRUNCATE_UNSUPPORTED.load(Ordering::Relaxed) {
+            let (_, result) = self.ring.driver().clone().submit(Ftruncate { fd: self.fd, len }).await;
+            match result {
+                // Kernels without the ftruncate opcode fail it with EINVAL, but so does an invalid
+                // truncate, so the fallback decides which this was.
+                Err(err) if err.raw_os_error() == Some(libc::EINVAL)    => { }
+                result                                                  => return result.map(drop),
+            }
+        }
+
+        // The thread uses its own file descriptor, in case this future is dropped and the file
+        // closed before the truncate runs.
+        let fd = unsafe { libc::dup(self.fd) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let result = blocking::spawn(move || unsafe {
+            let result = match libc::ftruncate(fd, len as libc::off_t) {
+                0   => Ok(()),
+                _   => Err(io::Error::last_os_error()),
+            };
+            libc::close(fd);
+            result
+        }).await;
+        if result.is_ok() {
+            FTRUNCATE_UNSUPPORTED.store(true, Ordering::Relaxed);
+        }
+        result
+    }
+
+    /// Allocate disk space for a range of the file, after flushing any buffered writes, extending
+    /// the file if the range is past its end
+    pub async fn allocate(&mut self, offset: u64, len: u64) -> io::Result<()> where D: Unpin {
+        self.fallocate(offset, len, FallocateFlags::empty()).await
+    }
+
+    /// Deallocate a range of the file, after flushing any buffered writes, so that it reads as
+    /// zeroes without changing the file's size
+    pub async fn punch_hole(&mut self, offset: u64, len: u64) -> io::Result<()> where D: Unpin {
+        let flags = FallocateFlags::FALLOC_FL_PUNCH_HOLE | FallocateFlags::FALLOC_FL_KEEP_SIZE;
+        self.fallocate(offset, len, flags).await
+    }
+
+    /// Zero a range of the file, after flushing any buffered writes, extending the file if the
+    /// range is past its end
+    ///
+    /// Unlike `punch_hole`, the space for the range stays allocated.
+    pub async fn zero_range(&mut self, offset: u64, len: u64) -> io::Result<()> where D: Unpin {
+        self.fallocate(offset, len, FallocateFlags::FALLOC_FL_ZERO_RANGE).await
+    }
+
+    /// Advise the kernel how a range of the file will be accessed, as with `posix_fadvise(2)`
+    ///
+    /// A `len` of zero applies the advice to the end of the file. The event only takes a 32 bit
+    /// length, so a larger `len` is an `InvalidInput` error.
+    pub async fn advise(&self, offset: u64, len: u64, advice: PosixFadviseAdvice) -> io::Result<()> {
+        if len > u32::MAX as u64 {
+            let msg = "fadvise length does not fit in 32 bits";
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+        }
+        let fadvise = Fadvise { fd: self.fd, offset, size: len, flags: advice };
+        let (_, result) = self.ring.driver().clone().submit(fadvise).await;
+        result.map(drop)
+    }
+
+    async fn fallocate(&mut self, offset: u64, len: u64, flags: FallocateFlags) -> io::Result<()>
+        where D: Unpin
+    {
+        self.flush_writes().await?;
+        let fallocate = Fallocate { fd: self.fd, offset, size: len, flags };
+        let (_, result) = self.ring.driver().clone().submit(fallocate).await;
+        result.map(drop)
+    }
+
+    /// Keep `depth` sequential reads of `size` bytes each in flight ahead of the reader
+    ///
+    /// Once the reader has consumed the data from one read, it is submitted again for the data
+    /// after the last read in flight. Seeking discards the data which has been read ahead.
+    /// Setting a depth of 0 turns read-ahead off, which is the default; any data which has been
+    /// read ahead but not consumed is discarded, and the cursor is moved back to the start of it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is 0 or not less than 4 GiB, and read-ahead is being turned on.
+    pub fn set_read_ahead(&mut self, depth: usize, size: usize) {
+        if let Some(mut read_ahead) = self.read_ahead.take() {
+            self.pos -= read_ahead.buffered().len() as u64;
+            read_ahead.discard();
+        }
+        if depth > 0 {
+            self.read_ahead = Some(ReadAhead::new(self.ring.driver(), depth, size));
+        }
+    }
+
+    /// Buffer writes in buffers of `size` bytes, keeping up to `depth` of them in flight at once
+    ///
+    /// Writes are copied into a buffer and accepted without waiting for them to complete, unless
+    /// every buffer is in flight. A buffer is submitted once it is full, when the next write
+    /// does not follow it, or when the file is flushed, synced or read from. An error from a
+    /// write in flight is returned by the next write, flush or read; `poll_flush` waits for every
+    /// write and returns the first error.
+    /// Closing the file flushes it first, but any data which has not been flushed when the file
+    /// is dropped is lost.
+    ///
+    /// Any buffered writes are flushed before the new setting takes effect. Setting a depth of 0
+    /// turns write-behind off, which is the default.
+    ///
+    /// Write-behind cannot be turned on for a file opened for appending, because the writes in
+    /// flight could be appended in any order; this returns an `InvalidInput` error instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is 0 or not less than 4 GiB, and write-behind is being turned on.
+    pub async fn set_write_behind(&mut self, depth: usize, size: usize) -> io::Result<()>
+        where D: Unpin
+    {
+        if self.append && depth > 0 {
+            let msg = "write-behind cannot be used on a file opened for appending";
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+        }
+        if self.write_behind.is_some() {
+            self.flush_writes().await?;
+        }
+        self.write_behind = match depth {
+            0   => None,
+            _   => Some(WriteBehind::new(self.ring.driver(), depth, size)),
+        };
+        Ok(())
+    }
+
+    fn from_fd(fd: RawFd, driver: D) -> File<D> {
+        File::from_fd_with_capacity(fd, driver, DEFAULT_CAPACITY)
+    }
+
+    fn from_fd_with_capacity(fd: RawFd, driver: D, capacity: usize) -> File<D> {
+        let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+        File {
+            read: Half::with_capacity(driver.clone(), capacity),
+            read_ahead: None,
+            write: Half::with_capacity(driver.clone(), capacity),
+            write_behind: None,
+            ring: Ring::new(driver),
+            statx: None,
+            active: Op::Nothing,
+            pos: 0,
+            append: flags != -1 && flags & libc::O_APPEND != 0,
+            appended: false,
+            fd,
+        }
+    }
+}
+
+impl<D: Drive> File<D> {
+    /// Access any data that has been read into the buffer, but not consumed
+    ///
+    /// This is similar to the fill_buf method from AsyncBufRead, but instead of performing IO if
+    /// the buffer is empty, it will just return an empty slice. This method can be used to copy
+    /// out any left over buffered data before closing or performing a write.
+    pub fn read_buffered(&self) -> &[u8] {
+        match (self.read.buffered_from_read(), &self.read_ahead) {
+            (&[], Some(read_ahead)) => read_ahead.buffered(),
+            (buffered, _)           => buffered,
+        }
+    }
+
+    /// Flush any buffered writes, then sync the file's data and metadata to disk
+    pub async fn sync_all(&mut self) -> io::Result<()> where D: Unpin {
+        self.sync(Op::Fsync(FsyncFlags::empty())).await
+    }
+
+    /// Flush any buffered writes, then sync the file's data to disk, along with only the metadata
+    /// needed to read it back
+    pub async fn sync_data(&mut self) -> io::Result<()> where D: Unpin {
+        self.sync(Op::Fsync(FsyncFlags::FSYNC_DATASYNC)).await
+    }
+
+    /// Flush any buffered writes, then write back the dirty pages in a range of the file, as
+    /// with `sync_file_range(2)`
+    ///
+    /// This does not sync the file's metadata, so unlike `sync_data` it does not make the data
+    /// durable by itself. A `len` of zero syncs to the end of the file.
+    pub async fn sync_range(&mut self, offset: u64, len: u32, flags: SyncFileRangeFlags)
+        -> io::Result<()> where D: Unpin
+    {
+        self.sync(Op::SyncRange(offset, len, flags)).await
+    }
+
+    /// Split the file into a read half and a write half which own it, and can be moved to
+    /// different tasks.
+    ///
+    /// Each half has its own cursor, which starts at the file's cursor and is only moved by
+    /// reading or writing through that half. A read and a write can be in flight at the same time,
+    /// and a write does not discard the data the read half has buffered, even if it overwrites it.
+    ///
+    /// Any buffered writes are flushed first; if that fails, the error is returned and the file is
+    /// closed. Data in the read buffer is kept by the read half, but data which has been read ahead
+    /// is discarded. The file is closed once both halves have been dropped.
+    pub async fn into_split(mut self) -> io::Result<(OwnedReadHalf<D>, OwnedWriteHalf<D>)>
+        where D: Unpin
+    {
+        self.flush_writes().await?;
+        future::poll_fn(|ctx| Pin::new(&mut self).poll_appended(ctx)).await?;
+
+        let write_pos = self.cursor();
+        let read_pos = write_pos + self.read.buffered_from_read().len() as u64;
+        self.discard_read_ahead();
+        if !self.ring.is_idle() {
+            self.ring.cancel(Cancellation::from(self.statx.take()));
+        }
+        let idle = self.ring.is_idle()
+            && self.read_ahead.iter().all(ReadAhead::is_idle)
+            && self.write_behind.iter().all(WriteBehind::is_idle);
+
+        let mut this = ManuallyDrop::new(self);
+        let (read, write) = unsafe { (ptr::read(&this.read), ptr::read(&this.write)) };
+        unsafe {
+            ptr::drop_in_place(&mut this.read_ahead);
+            ptr::drop_in_place(&mut this.write_behind);
+            ptr::drop_in_place(&mut this.ring);
+            ptr::drop_in_place(&mut this.statx);
+        }
+        let fd = Arc::new(Fd(this.fd));
+        if !idle {
+            // The kernel may still be using the file descriptor, so it is leaked.
+            mem::forget(fd.clone());
+        }
+        let read = OwnedReadHalf::new(read, fd.clone(), read_pos);
+        Ok((read, OwnedWriteHalf::new(write, fd, write_pos)))
+    }
+
+    async fn flush_writes(&mut self) -> io::Result<()> where D: Unpin {
+        self.guard_io();
+        future::poll_fn(|ctx| Pin::new(&mut *self).poll_flush(ctx)).await
+    }
+
+    async fn sync(&mut self, op: Op) -> io::Result<()> where D: Unpin {
+        future::poll_fn(|ctx| Pin::new(&mut *self).poll_sync(ctx, op)).await
+    }
+
+    fn poll_sync(mut self: Pin<&mut Self>, ctx: &mut Context<'_>, op: Op) -> Poll<io::Result<()>> {
+        self.guard_io();
+        // A write still in flight must complete before the sync is submitted.
+        ready!(self.as_mut().poll_flush(ctx))?;
+        self.as_mut().guard_op(op);
+        let fd = self.fd;
+        ready!(self.ring().poll(ctx, 1, |sqs| {
+            let mut sqe = sqs.single().unwrap();
+            unsafe {
+                match op {
+                    Op::Fsync(flags)                    => sqe.prep_fsync(fd, flags),
+                    Op::SyncRange(offset, len, flags)   => {
+                        prep_sync_file_range(&mut sqe, fd, offset, len, flags)
+                    }
+                    _                                   => unreachable!(),
+                }
+            }
+            sqe
+        }))?;
+        Poll::Ready(Ok(()))
+    }
+
+    fn guard_io(&self) {
+        if matches!(self.active, Op::Close | Op::Closed) {
+            panic!("Attempted to perform IO on a closed File");
+        }
+    }
+
+    fn guard_write_behind(&self) -> io::Result<()> {
+        match &self.write_behind {
+            Some(write_behind) if !write_behind.is_flushed() => {
+                let msg = "the file must be flushed before positional IO while write-behind is on";
+                Err(io::Error::new(io::ErrorKind::InvalidInput, msg))
+            }
+            _                                               => Ok(()),
+        }
+    }
+
+    fn guard_op(self: Pin<&mut Self>, op: Op) {
+        let this = unsafe { Pin::get_unchecked_mut(self) };
+        if this.active == Op::Closed {
+            panic!("Attempted to perform IO on a closed File");
+        } else if this.active != Op::Nothing && this.active != op {
+            this.ring.cancel(Cancellation::from(this.statx.take()));
+        }
+        this.active = op;
+    }
+
+    fn cancel(&mut self) {
+        self.active = Op::Nothing;
+        self.read.cancel();
+        self.discard_read_ahead();
+        self.write.cancel();
+        if let Some(write_behind) = &mut self.write_behind {
+            write_behind.discard();
+        }
+        self.ring.cancel(Cancellation::from(self.statx.take()));
+    }
+
+    fn is_idle(&self) -> bool {
+        self.read.is_idle() && self.write.is_idle() && self.ring.is_idle()
+            && self.read_ahead.iter().all(ReadAhead::is_idle)
+            && self.write_behind.iter().all(WriteBehind::is_idle)
+    }
+
+    fn poll_file_size(mut self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        static EMPTY: libc::c_char = 0;
+        use std::ffi::CStr;
+
+        self.as_mut().guard_op(Op::Statx);
+        let fd = self.fd;
+        let (ring, statx) = self.split_with_statx();
+        let flags = iou::sqe::StatxFlags::AT_EMPTY_PATH;
+        let mask = iou::sqe::StatxMode::STATX_SIZE;
+        ready!(ring.poll(ctx, 1, |sqs| {
+            let mut sqe = sqs.single().unwrap();
+            unsafe {
+                sqe.prep_statx(fd, CStr::from_ptr(&EMPTY), flags, mask, statx);
+            }
+            sqe
+        }))?;
+        Poll::Ready(Ok(statx.stx_size))
+    }
+
+    #[inline(always)]
+    fn split_with_statx(self: Pin<&mut Self>) -> (Pin<&mut Ring<D>>, &mut libc::statx) {
+        unsafe {
+            let this = Pin::get_unchecked_mut(self);
+            let statx = this.statx.get_or_insert_with(|| Box::new(mem::zeroed()));
+            (Pin::new_unchecked(&mut this.ring), statx)
+        }
+    }
+
+    #[inline(always)]
+    fn split_with_read(self: Pin<&mut Self>) -> (Pin<&mut Half<D>>, &mut u64) {
+        unsafe {
+            let this = Pin::get_unchecked_mut(self);
+            (Pin::new_unchecked(&mut this.read), &mut this.pos)
+        }
+    }
+
+    #[inline(always)]
+    fn split_with_write(self: Pin<&mut Self>) -> (Pin<&mut Half<D>>, &mut u64) {
+        unsafe {
+            let this = Pin::get_unchecked_mut(self);
+            (Pin::new_unchecked(&mut this.write), &mut this.pos)
+        }
+    }
+
+    #[inline(always)]
+    fn ring(self: Pin<&mut Self>) -> Pin<&mut Ring<D>> {
+        unsafe { Pin::map_unchecked_mut(self, |this| &mut this.ring) }
+    }
+
+    // Move the cursor, which is no longer at the end of any appended writes.
+    fn set_pos(self: Pin<&mut Self>, pos: u64) {
+        let this = unsafe { Pin::get_unchecked_mut(self) };
+        this.pos = pos;
+        this.appended = false;
+    }
+
+    // Move the cursor to the end of the file if a write has been appended to it since the cursor
+    // was last moved.
+    fn poll_appended(mut self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if self.appended {
+            let size = ready!(self.as_mut().poll_file_size(ctx))?;
+            self.set_pos(size);
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    // A sync which was submitted before a write completed does not cover that write, so it must
+    // not be mistaken for the result of a later sync.
+    fn abandon_sync(self: Pin<&mut Self>) {
+        if matches!(self.active, Op::Fsync(_) | Op::SyncRange(..)) {
+            self.guard_op(Op::Nothing);
+        }
+    }
+
+    // Write out any data buffered by write-behind, so that reads and file size queries see it.
+    fn poll_write_behind(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let fd = self.fd;
+        match unsafe { &mut Pin::get_unchecked_mut(self).write_behind } {
+            Some(write_behind)  => write_behind.poll_flush(ctx, fd),
+            None                => Poll::Ready(Ok(())),
+        }
+    }
+
+    // The logical position of the file's cursor.
+    fn cursor(&self) -> u64 {
+        let read_ahead = self.read_ahead.as_ref().map_or(0, |read_ahead| read_ahead.buffered().len());
+        self.pos - (self.read.buffered_from_read().len() + read_ahead) as u64
+    }
+
+    // Discard any data which has been read but not consumed, and cancel any reads in flight,
+    // moving the read position back to the cursor.
+    fn discard_reads(self: Pin<&mut Self>) {
+        let cursor = self.cursor();
+        let this = unsafe { Pin::get_unchecked_mut(self) };
+        unsafe { Pin::new_unchecked(&mut this.read).discard(); }
+        this.discard_read_ahead();
+        this.pos = cursor;
+    }
+
+    fn discard_read_ahead(&mut self) {
+        if let Some(read_ahead) = &mut self.read_ahead {
+            read_ahead.discard();
+        }
+    }
+
+    fn confirm_close(self: Pin<&mut Self>) {
+        unsafe { Pin::get_unchecked_mut(self).active = Op::Closed; }
+    }
+}
+
+impl<D: Drive> AsyncRead for File<D> {
+    fn poll_read(mut self: Pin<&mut Self>, ctx: &mut Context<'_>, buf: &mut [u8])
+        -> Poll<io::Result<usize>>
+    {
+        let mut inner = ready!(self.as_mut().poll_fill_buf_sized(ctx, buf.len()))?;
+        let len = io::Read::read(&mut inner, buf)?;
+        self.consume(len);
+        Poll::Ready(Ok(len))
+    }
+}
+
+impl<D: Drive> AsyncBufRead for File<D> {
+    fn poll_fill_buf(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        self.poll_fill_buf_sized(ctx, 0)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        if self.read.buffered_from_read().is_empty() {
+            if let Some(read_ahead) = unsafe { &mut Pin::get_unchecked_mut(self).read_ahead } {
+                read_ahead.consume(amt);
+            }
+        } else {
+            self.split_with_read().0.consume(amt);
+        }
+    }
+}
+
+impl<D: Drive> File<D> {
+    // Fill the read buffer, reading up to `len` bytes at once if that is more than it holds.
+    fn poll_fill_buf_sized(mut self: Pin<&mut Self>, ctx: &mut Context<'_>, len: usize)
+        -> Poll<io::Result<&[u8]>>
+    {
+        self.guard_io();
+        ready!(self.as_mut().poll_write_behind(ctx))?;
+        ready!(self.as_mut().poll_appended(ctx))?;
+        let fd = self.fd;
+        if self.read.buffered_from_read().is_empty() && self.read_ahead.is_some() {
+            // Safety: the read-ahead is not pinned
+            let this = unsafe { Pin::get_unchecked_mut(self) };
+            let read_ahead = this.read_ahead.as_mut().unwrap();
+            let (data, fresh) = ready!(read_ahead.poll_fill_buf(ctx, fd, this.pos))?;
+            if fresh {
+                this.pos += data.len() as u64;
+            }
+            return Poll::Ready(Ok(data));
+        }
+        let (read, pos) = self.split_with_read();
+        let filling = read.buffered_from_read().is_empty();
+        let data = ready!(read.poll_fill_buf_sized(ctx, fd, *pos, len))?;
+        if filling {
+            *pos += data.len() as u64;
+        }
+        Poll::Ready(Ok(data))
+    }
+}
+
+impl<D: Drive> AsyncWrite for File<D> {
+    fn poll_write(mut self: Pin<&mut Self>, ctx: &mut Context<'_>, slice: &[u8]) -> Poll<io::Result<usize>> {
+        self.guard_io();
+        let fd = self.fd;
+        if !slice.is_empty() {
+            // Reads in flight may not see this write, and the data they have read ahead of the
+            // cursor may be overwritten.
+            self.as_mut().discard_reads();
+        }
+        // Safety: the write-behind is not pinned
+        let this = unsafe { self.as_mut().get_unchecked_mut() };
+        if let Some(write_behind) = &mut this.write_behind {
+            let n = ready!(write_behind.poll_write(ctx, fd, this.pos, slice))?;
+            this.pos += n as u64;
+            if n > 0 {
+                self.abandon_sync();
+            }
+            return Poll::Ready(Ok(n));
+        }
+        let (write, pos) = self.as_mut().split_with_write();
+        let n = ready!(write.poll_write(ctx, fd, *pos, slice))?;
+        *pos += n as u64;
+        if n > 0 {
+            // Safety: setting a flag does not move the file
+            unsafe { self.as_mut().get_unchecked_mut().appended = self.append; }
+            self.abandon_sync();
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if self.write_behind.is_some() {
+            self.guard_io();
+            return self.poll_write_behind(ctx);
+        }
+        ready!(self.poll_write(ctx, &[]))?;
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if self.active != Op::Close {
+            ready!(self.as_mut().poll_write_behind(ctx))?;
+            unsafe { self.as_mut().get_unchecked_mut().discard_read_ahead(); }
+            self.as_mut().split_with_read().0.cancel_pinned();
+            self.as_mut().split_with_write().0.cancel_pinned();
+        }
+        self.as_mut().guard_op(Op::Close);
+        let fd = self.fd;
+        ready!(self.as_mut().ring().poll(ctx, 1, |sqs| {
+            let mut sqe = sqs.single().unwrap();
+            unsafe {
+                sqe.prep_close(fd);
+            }
+            sqe
+        }))?;
+        self.confirm_close();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<D: Drive> AsyncSeek for File<D> {
+    fn poll_seek(mut self: Pin<&mut Self>, ctx: &mut Context, pos: io::SeekFrom)
+        -> Poll<io::Result<u64>>
+    {
+        if let io::SeekFrom::Current(_) = pos {
+            ready!(self.as_mut().poll_appended(ctx))?;
+        }
+        let buffered = self.read_buffered().len() as u64;
+        let (whence, offset) = match pos {
+            io::SeekFrom::Start(n) => {
+                self.as_mut().discard_reads();
+                self.as_mut().set_pos(n);
+                return Poll::Ready(Ok(self.pos));
+            }
+            // A seek forward within the buffered data just consumes it.
+            io::SeekFrom::Current(n) if n >= 0 && n as u64 <= buffered => {
+                self.as_mut().consume(n as usize);
+                return Poll::Ready(Ok(self.cursor()));
+            }
+            io::SeekFrom::Current(n) => (self.cursor(), n),
+            io::SeekFrom::End(n)     => {
+                ready!(self.as_mut().poll_write_behind(ctx))?;
+                (ready!(self.as_mut().poll_file_size(ctx))?, n)
+            }
+        };
+        let valid_seek = if offset.is_negative() {
+            match whence.checked_sub(offset.unsigned_abs()) {
+                Some(valid_seek) => valid_seek,
+                None => {
+                    let invalid = io::Error::from(io::ErrorKind::InvalidInput);
+                    return Poll::Ready(Err(invalid));
+                }
+            }
+        } else {
+            match whence.checked_add(offset as u64) {
+                Some(valid_seek) => valid_seek,
+                None => {
+                    let overflow = io::Error::from_raw_os_error(libc::EOVERFLOW);
+                    return Poll::Ready(Err(overflow));
+                }
+            }
+        };
+        self.as_mut().discard_reads();
+        self.as_mut().set_pos(valid_seek);
+        Poll::Ready(Ok(self.pos))
+    }
+}
+
+impl From<fs::File> for File {
+    fn from(file: fs::File) -> File {
+        File::run_on_driver(file, DemoDriver::default())
+    }
+}
+
+impl<D: Drive> From<File<D>> for fs::File {
+    fn from(mut file: File<D>) -> fs::File {
+        file.cancel();
+        let file = ManuallyDrop::new(file);
+        unsafe {
+            fs::File::from_raw_fd(file.fd)
+        }
+    }
+}
+
+impl<D: Drive> AsRawFd for File<D> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl<D: Drive> Drop for File<D> {
+    fn drop(&mut self) {
+        match self.active {
+            Op::Closed                  => { }
+            _ if self.is_idle()         => unsafe { libc::close(self.fd); },
+            // The kernel may still be using the file descriptor, so it is leaked.
+            _                           => self.cancel(),
+        }
+    }
+}
+
+/// A future representing an opening file.
+///
+/// If the file was opened with invalid [`OpenOptions`], the future resolves to an error without
+/// submitting any IO.
+pub struct Open<D: Drive = DemoDriver>(Result<Submission<OpenAt, D>, Option<io::Error>>);
+
+impl<D: Drive> Open<D> {
+    fn inner(self: Pin<&mut Self>) -> Result<Pin<&mut Submission<OpenAt, D>>, &mut Option<io::Error>> {
+        unsafe {
+            match &mut Pin::get_unchecked_mut(self).0 {
+                Ok(submission)  => Ok(Pin::new_unchecked(submission)),
+                Err(err)        => Err(err),
+            }
+        }
+    }
+}
+
+impl<D: Drive + Clone> Future for Open<D> {
+    type Output = io::Result<File<D>>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<File<D>>> {
+        let mut inner = match self.inner() {
+            Ok(inner)   => inner,
+            Err(err)    => return Poll::Ready(Err(err.take().expect("polled Open after completion"))),
+        };
+        let (_, result) = ready!(inner.as_mut().poll(ctx));
+        let fd = result? as i32;
+        let driver = inner.driver().clone();
+        Poll::Ready(Ok(File::from_fd(fd, driver)))
+    }
+}
+
+/// A future representing a file being created.
+pub struct Create<D: Drive = DemoDriver>(Submission<OpenAt, D>);
+
+impl<D: Drive> Create<D> {
+    fn inner(self: Pin<&mut Self>) -> Pin<&mut Submission<OpenAt, D>> {
+        unsafe { Pin::map_unchecked_mut(self, |this| &mut this.0) }
+    }
+}
+
+impl<D: Drive + Clone> Future for Create<D> {
+    type Output = io::Result<File<D>>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<File<D>>> {
+        let mut inner = self.inner();
+        let (_, result) = ready!(inner.as_mut().poll(ctx));
+        let fd = result? as i32;
+        let driver = inner.driver().clone();
+        Poll::Ready(Ok(File::from_fd(fd, driver)))
+    }
+}
+
+/// Query the metadata of the file at a path using the default driver, following symbolic links
+pub async fn metadata(path: impl AsRef<Path>) -> io::Result<Metadata> {
+    metadata_on_driver(path, DemoDriver::default()).await
+}
+
+/// Query the metadata of the file at a path, following symbolic links
+pub async fn metadata_on_driver<D: Drive + Clone>(path: impl AsRef<Path>, driver: D)
+    -> io::Result<Metadata>
+{
+    stat(Statx::without_dir(path, StatxFlags::empty(), Metadata::mask()), driver).await
+}
+
+/// Query the metadata of the file at a path using the default driver, without following a
+/// symbolic link at that path
+pub async fn symlink_metadata(path: impl AsRef<Path>) -> io::Result<Metadata> {
+    symlink_metadata_on_driver(path, DemoDriver::default()).await
+}
+
+/// Query the metadata of the file at a path, without following a symbolic link at that path
+pub async fn symlink_metadata_on_driver<D: Drive + Clone>(path: impl AsRef<Path>, driver: D)
+    -> io::Result<Metadata>
+{
+    // iou defines AT_SYMLINK_NOFOLLOW with the value of AT_SYMLINK_FOLLOW, so the flag is set
+    // from libc instead.
+    let flags = unsafe { StatxFlags::from_bits_unchecked(libc::AT_SYMLINK_NOFOLLOW) };
+    stat(Statx::without_dir(path, flags, Metadata::mask()), driver).await
+}
+
+/// Remove a file using the default driver
+pub async fn remove_file(path: impl AsRef<Path>) -> io::Result<()> {
+    remove_file_on_driver(path, DemoDriver::default()).await
+}
+
+/// Remove a file
+pub async fn remove_file_on_driver<D: Drive + Clone>(path: impl AsRef<Path>, driver: D)
+    -> io::Result<()>
+{
+    run(UnlinkAt::without_dir(path, AtFlags::empty()), driver).await
+}
+
+/// Remove an empty directory using the default driver
+pub async fn remove_dir(path: impl AsRef<Path>) -> io::Result<()> {
+    remove_dir_on_driver(path, DemoDriver::default()).await
+}
+
+/// Remove an empty directory
+pub async fn remove_dir_on_driver<D: Drive + Clone>(path: impl AsRef<Path>, driver: D)
+    -> io::Result<()>
+{
+    run(UnlinkAt::without_dir(path, AtFlags::AT_REMOVEDIR), driver).await
+}
+
+/// Rename a file or directory using the default driver, replacing the new path if it exists
+pub async fn rename(from: impl AsRef<Path>, to: impl AsRef<Path>) -> io::Result<()> {
+    rename_on_driver(from, to, DemoDriver::default()).await
+}
+
+/// Rename a file or directory, replacing the new path if it exists
+pub async fn rename_on_driver<D: Drive + Clone>(
+    from: impl AsRef<Path>,
+    to: impl AsRef<Path>,
+    driver: D,
+) -> io::Result<()> {
+    run(RenameAt::without_dir(from, to, RenameFlags::empty()), driver).await
+}
+
+/// Create a directory using the default driver
+pub async fn create_dir(path: impl AsRef<Path>) -> io::Result<()> {
+    create_dir_on_driver(path, DemoDriver::default()).await
+}
+
+/// Create a directory
+pub async fn create_dir_on_driver<D: Drive + Clone>(path: impl AsRef<Path>, driver: D)
+    -> io::Result<()>
+{
+    run(MkdirAt::without_dir(path, Mode::from_bits(0o777).unwrap()), driver).await
+}
+
+/// Create a hard link at `link` to the file at `original` using the default driver
+pub async fn hard_link(original: impl AsRef<Path>, link: impl AsRef<Path>) -> io::Result<()> {
+    hard_link_on_driver(original, link, DemoDriver::default()).await
+}
+
+/// Create a hard link at `link` to the file at `original`
+pub async fn hard_link_on_driver<D: Drive + Clone>(
+    original: impl AsRef<Path>,
+    link: impl AsRef<Path>,
+    driver: D,
+) -> io::Result<()> {
+    run(LinkAt::without_dir(original, link, AtFlags::empty()), driver).await
+}
+
+/// Create a symbolic link at `link` which points to `original` using the default driver
+pub async fn symlink(original: impl AsRef<Path>, link: impl AsRef<Path>) -> io::Result<()> {
+    symlink_on_driver(original, link, DemoDriver::default()).await
+}
+
+/// Create a symbolic link at `link` which points to `original`
+pub async fn symlink_on_driver<D: Drive + Clone>(
+    original: impl AsRef<Path>,
+    link: impl AsRef<Path>,
+    driver: D,
+) -> io::Result<()> {
+    run(SymlinkAt::without_dir(original, link), driver).await
+}
+
+async fn stat<D: Drive>(statx: Statx, driver: D) -> io::Result<Metadata> {
+    let (statx, result) = driver.submit(statx).await;
+    result?;
+    Ok(Metadata::from_statx(*statx.statx))
+}
+
+async fn run<E: Event, D: Drive>(event: E, driver: D) -> io::Result<()> {
+    let (_, result) = driver.submit(event).await;
+    result.map(drop)
+}
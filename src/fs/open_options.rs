@@ -0,0 +1,202 @@
+use std::io;
+use std::path::Path;
+
+use iou::sqe::{OFlag, Mode};
+
+use crate::drive::Drive;
+use crate::drive::demo::DemoDriver;
+use crate::event::OpenAt;
+
+use super::Open;
+
+/// Options and flags which can be used to configure how a file is opened
+///
+/// This mirrors `std::fs::OpenOptions` (including the `mode` and `custom_flags` methods of
+/// `std::os::unix::fs::OpenOptionsExt`), with a few extra Linux-specific flags. Files are always
+/// opened with `O_CLOEXEC`.
+#[derive(Clone, Debug)]
+pub struct OpenOptions {
+    read: bool,
+    write: bool,
+    append: bool,
+    truncate: bool,
+    create: bool,
+    create_new: bool,
+    tmpfile: bool,
+    direct: bool,
+    sync: bool,
+    dsync: bool,
+    custom_flags: OFlag,
+    mode: Mode,
+}
+
+impl Default for OpenOptions {
+    fn default() -> OpenOptions {
+        OpenOptions::new()
+    }
+}
+
+impl OpenOptions {
+    /// Create a blank set of options, with every option set to false
+    pub fn new() -> OpenOptions {
+        OpenOptions {
+            read: false,
+            write: false,
+            append: false,
+            truncate: false,
+            create: false,
+            create_new: false,
+            tmpfile: false,
+            direct: false,
+            sync: false,
+            dsync: false,
+            custom_flags: OFlag::empty(),
+            mode: Mode::from_bits_truncate(0o666),
+        }
+    }
+
+    /// Open the file for reading
+    pub fn read(&mut self, read: bool) -> &mut OpenOptions {
+        self.read = read;
+        self
+    }
+
+    /// Open the file for writing
+    pub fn write(&mut self, write: bool) -> &mut OpenOptions {
+        self.write = write;
+        self
+    }
+
+    /// Open the file for appending, so that every write goes to the end of the file. This implies
+    /// `write`.
+    ///
+    /// After a write, the file's cursor is at the end of the file. Write-behind cannot be enabled
+    /// on a file opened for appending.
+    pub fn append(&mut self, append: bool) -> &mut OpenOptions {
+        self.append = append;
+        self
+    }
+
+    /// Truncate the file to length 0 if it exists. The file must be opened for writing.
+    pub fn truncate(&mut self, truncate: bool) -> &mut OpenOptions {
+        self.truncate = truncate;
+        self
+    }
+
+    /// Create the file if it does not exist. The file must be opened for writing or appending.
+    pub fn create(&mut self, create: bool) -> &mut OpenOptions {
+        self.create = create;
+        self
+    }
+
+    /// Create the file, failing if it already exists (`O_EXCL`). This overrides `create` and
+    /// `truncate`.
+    pub fn create_new(&mut self, create_new: bool) -> &mut OpenOptions {
+        self.create_new = create_new;
+        self
+    }
+
+    /// Set the permissions of the file if it is created. The default is `0o666`, which is
+    /// modified by the process umask.
+    pub fn mode(&mut self, mode: u32) -> &mut OpenOptions {
+        self.mode = Mode::from_bits_truncate(mode);
+        self
+    }
+
+    /// Pass additional flags to `openat`. The access mode flags and `O_CLOEXEC` are always
+    /// determined by the other options, and the flags set by `direct`, `sync` and `dsync` are
+    /// added to these.
+    pub fn custom_flags(&mut self, flags: i32) -> &mut OpenOptions {
+        self.custom_flags = OFlag::from_bits_truncate(flags);
+        self
+    }
+
+    /// Bypass the page cache (`O_DIRECT`). Reads and writes to the file must be aligned to the
+    /// logical block size of the underlying device.
+    pub fn direct(&mut self, direct: bool) -> &mut OpenOptions {
+        self.direct = direct;
+        self
+    }
+
+    /// Complete every write only once the data and metadata are durable (`O_SYNC`).
+    pub fn sync(&mut self, sync: bool) -> &mut OpenOptions {
+        self.sync = sync;
+        self
+    }
+
+    /// Complete every write only once the data is durable (`O_DSYNC`).
+    pub fn dsync(&mut self, dsync: bool) -> &mut OpenOptions {
+        self.dsync = dsync;
+        self
+    }
+
+    /// Create an unnamed temporary file in the directory at the path being opened
+    /// (`O_TMPFILE`). The file must be opened for writing; `create` and `truncate` are ignored,
+    /// and `create_new` prevents the file from ever being linked into the filesystem.
+    pub fn tmpfile(&mut self, tmpfile: bool) -> &mut OpenOptions {
+        self.tmpfile = tmpfile;
+        self
+    }
+
+    /// Open a file at `path` with these options, using the default driver
+    pub fn open(&self, path: impl AsRef<Path>) -> Open {
+        self.open_on_driver(path, DemoDriver::default())
+    }
+
+    /// Open a file at `path` with these options
+    pub fn open_on_driver<D: Drive + Clone>(&self, path: impl AsRef<Path>, driver: D) -> Open<D> {
+        match self.flags() {
+            Ok(flags)   => Open(Ok(driver.submit(OpenAt::without_dir(path, flags, self.mode)))),
+            Err(err)    => Open(Err(Some(err))),
+        }
+    }
+
+    fn flags(&self) -> io::Result<OFlag> {
+        let access = match (self.read, self.write, self.append) {
+            (true, false, false)    => OFlag::O_RDONLY,
+            (false, true, false)    => OFlag::O_WRONLY,
+            (true, true, false)     => OFlag::O_RDWR,
+            (false, _, true)        => OFlag::O_WRONLY | OFlag::O_APPEND,
+            (true, _, true)         => OFlag::O_RDWR | OFlag::O_APPEND,
+            (false, false, false)   => return Err(invalid()),
+        };
+
+        let writable = self.write || self.append;
+        let creation = if self.tmpfile {
+            if !writable {
+                return Err(invalid());
+            }
+            match self.create_new {
+                true    => OFlag::O_TMPFILE | OFlag::O_EXCL,
+                false   => OFlag::O_TMPFILE,
+            }
+        } else {
+            if !writable && (self.truncate || self.create || self.create_new) {
+                return Err(invalid());
+            }
+            if self.append && self.truncate && !self.create_new {
+                return Err(invalid());
+            }
+            match (self.create, self.truncate, self.create_new) {
+                (_, _, true)            => OFlag::O_CREAT | OFlag::O_EXCL,
+                (true, true, false)     => OFlag::O_CREAT | OFlag::O_TRUNC,
+                (true, false, false)    => OFlag::O_CREAT,
+                (false, true, false)    => OFlag::O_TRUNC,
+                (false, false, false)   => OFlag::empty(),
+            }
+        };
+
+        let mut custom = self.custom_flags & !(OFlag::O_ACCMODE | OFlag::O_CLOEXEC);
+        for (set, flag) in [(self.direct, OFlag::O_DIRECT), (self.sync, OFlag::O_SYNC),
+                            (self.dsync, OFlag::O_DSYNC)] {
+            if set {
+                custom |= flag;
+            }
+        }
+        Ok(OFlag::O_CLOEXEC | access | creation | custom)
+    }
+}
+
+fn invalid() -> io::Error {
+    io::Error::from_raw_os_error(libc::EINVAL)
+}
@@ -0,0 +1,172 @@
+use std::collections::VecDeque;
+use std::io;
+use std::os::unix::io::RawFd;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::drive::Drive;
+use crate::ring::{Ring, Cancellation};
+
+/// Sequential reads which are kept in flight ahead of the consumer.
+///
+/// Each read has its own ring and buffer. The reads are kept in file order: the front read is the
+/// one being consumed, and once it has been consumed it is submitted again for the data after the
+/// last read in the queue.
+pub(super) struct ReadAhead<D: Drive> {
+    reads: VecDeque<AheadRead<D>>,
+    size: usize,
+    // The offset the next read submitted should start at.
+    next: u64,
+}
+
+struct AheadRead<D: Drive> {
+    // Boxed so that the ring does not move when the queue does.
+    ring: Pin<Box<Ring<D>>>,
+    buf: Option<Box<[u8]>>,
+    offset: u64,
+    state: ReadState,
+}
+
+#[derive(Copy, Clone)]
+enum ReadState {
+    Unsubmitted,
+    InFlight,
+    Filled { pos: u32, cap: u32 },
+}
+
+impl<D: Drive + Clone> ReadAhead<D> {
+    pub fn new(driver: &D, depth: usize, size: usize) -> ReadAhead<D> {
+        assert!(depth > 0 && size > 0, "read-ahead needs at least one read of at least one byte");
+        assert!(size <= u32::MAX as usize, "read-ahead reads must be smaller than 4 GiB");
+        let reads = (0..depth).map(|_| AheadRead {
+            ring: Box::pin(Ring::new(driver.clone())),
+            buf: None,
+            offset: 0,
+            state: ReadState::Unsubmitted,
+        }).collect();
+        ReadAhead { reads, size, next: 0 }
+    }
+}
+
+impl<D: Drive> ReadAhead<D> {
+    /// Return the data from the front read, submitting reads starting at `offset` if there are
+    /// none in flight. The second value is true if the data has just been read, rather than left
+    /// unconsumed by an earlier call.
+    pub fn poll_fill_buf(&mut self, ctx: &mut Context<'_>, fd: RawFd, offset: u64)
+        -> Poll<io::Result<(&[u8], bool)>>
+    {
+        if self.reads.iter().all(|read| matches!(read.state, ReadState::Unsubmitted)) {
+            self.next = offset;
+        }
+
+        loop {
+            self.submit(ctx, fd);
+
+            match self.reads[0].state {
+                ReadState::Filled { pos, cap } if pos < cap => break,
+                ReadState::Filled { .. }                    => {
+                    // The front read has been consumed, so it is reused for the next read.
+                    let mut read = self.reads.pop_front().unwrap();
+                    read.state = ReadState::Unsubmitted;
+                    self.reads.push_back(read);
+                    continue;
+                }
+                _                                           => { }
+            }
+
+            let AheadRead { ring, buf, offset, state } = &mut self.reads[0];
+            let (buf, offset) = (buf.as_mut().unwrap(), *offset);
+            let result = ring.as_mut().poll(ctx, 1, |sqs| {
+                let mut sqe = sqs.single().unwrap();
+                unsafe { sqe.prep_read(fd, &mut buf[..], offset); }
+                sqe
+            });
+
+            match result {
+                Poll::Ready(Ok(0))      => {
+                    // The end of the file. The read is submitted again next time, in case the
+                    // file has grown.
+                    self.next = offset;
+                    self.reset(0);
+                    return Poll::Ready(Ok((&[], true)));
+                }
+                Poll::Ready(Ok(n))      => {
+                    *state = ReadState::Filled { pos: 0, cap: n };
+                    if (n as usize) < self.size {
+                        // A short read, usually because it reached the end of the file. The reads
+                        // after it would leave a gap, so they are discarded and reading resumes
+                        // after the data that was read.
+                        self.next = offset + n as u64;
+                        self.reset(1);
+                    }
+                    return Poll::Ready(Ok((self.buffered(), true)));
+                }
+                Poll::Ready(Err(err))   => {
+                    self.next = offset;
+                    self.reset(0);
+                    return Poll::Ready(Err(err));
+                }
+                Poll::Pending           => return Poll::Pending,
+            }
+        }
+
+        Poll::Ready(Ok((self.buffered(), false)))
+    }
+
+    /// The unconsumed data from the front read, if it has completed.
+    pub fn buffered(&self) -> &[u8] {
+        match self.reads.front() {
+            Some(AheadRead { buf: Some(buf), state: ReadState::Filled { pos, cap }, .. }) => {
+                &buf[*pos as usize..*cap as usize]
+            }
+            _   => &[],
+        }
+    }
+
+    pub fn consume(&mut self, amt: usize) {
+        if let Some(AheadRead { state: ReadState::Filled { pos, cap }, .. }) = self.reads.front_mut() {
+            *pos = (*pos + amt as u32).min(*cap);
+        }
+    }
+
+    /// Discard every read, cancelling those which are in flight.
+    pub fn discard(&mut self) {
+        self.reset(0);
+    }
+
+    /// Returns true if no reads are in flight.
+    pub fn is_idle(&self) -> bool {
+        self.reads.iter().all(|read| read.ring.is_idle())
+    }
+
+    // Submit every read which has not been submitted.
+    fn submit(&mut self, ctx: &mut Context<'_>, fd: RawFd) {
+        let size = self.size;
+        for read in self.reads.iter_mut().filter(|read| matches!(read.state, ReadState::Unsubmitted)) {
+            read.offset = self.next;
+            self.next += size as u64;
+            let buf = read.buf.get_or_insert_with(|| vec![0; size].into_boxed_slice());
+            let offset = read.offset;
+            // Polling a ring with no event in flight prepares and submits the read, which cannot
+            // complete until the ring is polled again. If the driver has no room for the read, it
+            // is prepared when the ring is polled again instead.
+            let _ = read.ring.as_mut().poll(ctx, 1, |sqs| {
+                let mut sqe = sqs.single().unwrap();
+                unsafe { sqe.prep_read(fd, &mut buf[..], offset); }
+                sqe
+            });
+            read.state = ReadState::InFlight;
+        }
+    }
+
+    // Discard every read after the first `keep`, cancelling any which are in flight. Their rings
+    // are kept, and cancel the old reads when they are next used.
+    fn reset(&mut self, keep: usize) {
+        for read in self.reads.iter_mut().skip(keep) {
+            if !read.ring.is_idle() {
+                read.ring.as_mut().cancel_pinned(Cancellation::from(read.buf.take()));
+            }
+            read.state = ReadState::Unsubmitted;
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use std::io;
+use std::mem::ManuallyDrop;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures_core::ready;
+use futures_io::{AsyncRead, AsyncBufRead, AsyncWrite};
+
+use crate::drive::{Drive, demo::DemoDriver};
+use crate::duplex::{Fd, Half, drop_half};
+
+/// The read half of a file, created by `into_split`.
+///
+/// The half has its own cursor, which is only moved by reading from it.
+pub struct OwnedReadHalf<D: Drive = DemoDriver> {
+    half: Half<D>,
+    fd: ManuallyDrop<Arc<Fd>>,
+    // The offset the next read starts at, past any data in the buffer.
+    pos: u64,
+}
+
+/// The write half of a file, created by `into_split`.
+///
+/// The half has its own cursor, which is only moved by writing to it. Closing the write half
+/// flushes it; the file is closed once both halves have been dropped.
+pub struct OwnedWriteHalf<D: Drive = DemoDriver> {
+    half: Half<D>,
+    fd: ManuallyDrop<Arc<Fd>>,
+    pos: u64,
+}
+
+impl<D: Drive> OwnedReadHalf<D> {
+    pub(super) fn new(half: Half<D>, fd: Arc<Fd>, pos: u64) -> OwnedReadHalf<D> {
+        OwnedReadHalf { half, fd: ManuallyDrop::new(fd), pos }
+    }
+
+    /// The offset in the file of the next byte this half will return.
+    pub fn position(&self) -> u64 {
+        self.pos - self.half.buffered_from_read().len() as u64
+    }
+
+    fn poll_fill_buf_sized(self: Pin<&mut Self>, ctx: &mut Context<'_>, len: usize)
+        -> Poll<io::Result<&[u8]>>
+    {
+        let this = unsafe { Pin::get_unchecked_mut(self) };
+        let half = unsafe { Pin::new_unchecked(&mut this.half) };
+        let filling = half.buffered_from_read().is_empty();
+        let data = ready!(half.poll_fill_buf_sized(ctx, this.fd.0, this.pos, len))?;
+        if filling {
+            this.pos += data.len() as u64;
+        }
+        Poll::Ready(Ok(data))
+    }
+
+    fn half(self: Pin<&mut Self>) -> Pin<&mut Half<D>> {
+        unsafe { Pin::map_unchecked_mut(self, |this| &mut this.half) }
+    }
+}
+
+impl<D: Drive> OwnedWriteHalf<D> {
+    pub(super) fn new(half: Half<D>, fd: Arc<Fd>, pos: u64) -> OwnedWriteHalf<D> {
+        OwnedWriteHalf { half, fd: ManuallyDrop::new(fd), pos }
+    }
+
+    /// The offset in the file the next write through this half will start at.
+    ///
+    /// If the file was opened for appending, writes go to the end of the file instead.
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+}
+
+impl<D: Drive> AsyncRead for OwnedReadHalf<D> {
+    fn poll_read(mut self: Pin<&mut Self>, ctx: &mut Context<'_>, buf: &mut [u8])
+        -> Poll<io::Result<usize>>
+    {
+        let mut inner = ready!(self.as_mut().poll_fill_buf_sized(ctx, buf.len()))?;
+        let len = io::Read::read(&mut inner, buf)?;
+        self.half().consume(len);
+        Poll::Ready(Ok(len))
+    }
+}
+
+impl<D: Drive> AsyncBufRead for OwnedReadHalf<D> {
+    fn poll_fill_buf(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        self.poll_fill_buf_sized(ctx, 0)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.half().consume(amt);
+    }
+}
+
+impl<D: Drive> AsyncWrite for OwnedWriteHalf<D> {
+    fn poll_write(self: Pin<&mut Self>, ctx: &mut Context<'_>, slice: &[u8]) -> Poll<io::Result<usize>> {
+        let this = unsafe { Pin::get_unchecked_mut(self) };
+        let half = unsafe { Pin::new_unchecked(&mut this.half) };
+        let n = ready!(half.poll_write(ctx, this.fd.0, this.pos, slice))?;
+        this.pos += n as u64;
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_write(ctx, &[]))?;
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush(ctx)
+    }
+}
+
+impl<D: Drive> AsRawFd for OwnedReadHalf<D> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.0
+    }
+}
+
+impl<D: Drive> AsRawFd for OwnedWriteHalf<D> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.0
+    }
+}
+
+impl<D: Drive> Drop for OwnedReadHalf<D> {
+    fn drop(&mut self) {
+        drop_half(&mut self.half, &mut self.fd);
+    }
+}
+
+impl<D: Drive> Drop for OwnedWriteHalf<D> {
+    fn drop(&mut self) {
+        drop_half(&mut self.half, &mut self.fd);
+    }
+}
@@ -0,0 +1,206 @@
+use std::io;
+use std::os::unix::io::RawFd;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::ready;
+
+use crate::drive::Drive;
+use crate::ring::{Ring, Cancellation};
+
+/// Sequential writes which are buffered and kept in flight behind the writer.
+///
+/// Each write has its own ring and buffer. Data is copied into one buffer until it is full or
+/// the writer moves elsewhere in the file, and then that buffer is submitted while the next one
+/// is filled. The writes in flight always cover consecutive ranges of the file, so they can
+/// complete in any order.
+pub(super) struct WriteBehind<D: Drive> {
+    writes: Vec<BehindWrite<D>>,
+    size: usize,
+    // The index of the write being filled, if there is one.
+    filling: Option<usize>,
+    // The offset just after the last byte accepted.
+    end: u64,
+    // The first error from a write which has not been reported.
+    error: Option<io::Error>,
+}
+
+struct BehindWrite<D: Drive> {
+    // Boxed so that the ring does not move when the writes do.
+    ring: Pin<Box<Ring<D>>>,
+    buf: Option<Box<[u8]>>,
+    offset: u64,
+    state: WriteState,
+}
+
+#[derive(Copy, Clone)]
+enum WriteState {
+    Empty,
+    Filling { len: u32 },
+    InFlight { pos: u32, len: u32 },
+}
+
+impl<D: Drive + Clone> WriteBehind<D> {
+    pub fn new(driver: &D, depth: usize, size: usize) -> WriteBehind<D> {
+        assert!(depth > 0 && size > 0, "write-behind needs at least one write of at least one byte");
+        assert!(size <= u32::MAX as usize, "write-behind writes must be smaller than 4 GiB");
+        let writes = (0..depth).map(|_| BehindWrite {
+            ring: Box::pin(Ring::new(driver.clone())),
+            buf: None,
+            offset: 0,
+            state: WriteState::Empty,
+        }).collect();
+        WriteBehind { writes, size, filling: None, end: 0, error: None }
+    }
+}
+
+impl<D: Drive> WriteBehind<D> {
+    /// Copy as much of `slice` as fits into a buffer, to be written to `fd` at `offset`.
+    ///
+    /// This only waits if every buffer is in flight, or if `offset` does not follow the data
+    /// accepted before it, in which case every write in flight must complete first. An error
+    /// from an earlier write is returned instead of accepting any data.
+    pub fn poll_write(&mut self, ctx: &mut Context<'_>, fd: RawFd, offset: u64, slice: &[u8])
+        -> Poll<io::Result<usize>>
+    {
+        self.poll_writes(ctx, fd);
+        if let Some(err) = self.error.take() {
+            return Poll::Ready(Err(err));
+        }
+        if slice.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        if offset != self.end {
+            // A write to a range which overlaps one in flight could complete before it.
+            ready!(self.poll_flush(ctx, fd))?;
+            self.end = offset;
+        }
+
+        let index = match self.filling {
+            Some(index) => index,
+            None        => {
+                let size = self.size;
+                let empty = self.writes.iter().position(|write| matches!(write.state, WriteState::Empty));
+                let index = match empty {
+                    Some(index) => index,
+                    // Every write is in flight, and the task will be woken when one completes.
+                    None        => return Poll::Pending,
+                };
+                let write = &mut self.writes[index];
+                write.buf.get_or_insert_with(|| vec![0; size].into_boxed_slice());
+                write.offset = offset;
+                write.state = WriteState::Filling { len: 0 };
+                self.filling = Some(index);
+                index
+            }
+        };
+
+        let write = &mut self.writes[index];
+        let n = match &mut write.state {
+            WriteState::Filling { len }  => {
+                let buf = &mut write.buf.as_mut().unwrap()[*len as usize..];
+                let n = slice.len().min(buf.len());
+                buf[..n].copy_from_slice(&slice[..n]);
+                *len += n as u32;
+                n
+            }
+            _                           => unreachable!(),
+        };
+        self.end += n as u64;
+        if self.end - write.offset == self.size as u64 {
+            self.submit(ctx, fd);
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    /// Submit any buffered data, then wait for every write in flight to complete, returning the
+    /// first error any of them failed with.
+    pub fn poll_flush(&mut self, ctx: &mut Context<'_>, fd: RawFd) -> Poll<io::Result<()>> {
+        self.submit(ctx, fd);
+        self.poll_writes(ctx, fd);
+        if self.writes.iter().any(|write| matches!(write.state, WriteState::InFlight { .. })) {
+            return Poll::Pending;
+        }
+        match self.error.take() {
+            Some(err)   => Poll::Ready(Err(err)),
+            None        => Poll::Ready(Ok(())),
+        }
+    }
+
+    /// Returns true if no writes are in flight.
+    pub fn is_idle(&self) -> bool {
+        self.writes.iter().all(|write| write.ring.is_idle())
+    }
+
+    /// Returns true if no data is being buffered or written.
+    pub fn is_flushed(&self) -> bool {
+        self.filling.is_none()
+            && self.writes.iter().all(|write| matches!(write.state, WriteState::Empty))
+    }
+
+    /// Discard every buffer, cancelling the writes which are in flight.
+    pub fn discard(&mut self) {
+        for write in &mut self.writes {
+            if !write.ring.is_idle() {
+                write.ring.as_mut().cancel_pinned(Cancellation::from(write.buf.take()));
+            }
+            write.state = WriteState::Empty;
+        }
+        self.filling = None;
+        self.error = None;
+    }
+
+    // Submit the buffer being filled, if there is one.
+    fn submit(&mut self, ctx: &mut Context<'_>, fd: RawFd) {
+        if let Some(index) = self.filling.take() {
+            let write = &mut self.writes[index];
+            if let WriteState::Filling { len } = write.state {
+                write.state = WriteState::InFlight { pos: 0, len };
+            }
+            // Polling a ring with no event in flight prepares and submits the write, which cannot
+            // complete until the ring is polled again.
+            let _ = poll_write(write, ctx, fd);
+        }
+    }
+
+    // Poll every write in flight, resubmitting the rest of any which were short.
+    fn poll_writes(&mut self, ctx: &mut Context<'_>, fd: RawFd) {
+        for write in &mut self.writes {
+            while let WriteState::InFlight { pos, len } = write.state {
+                let result = match poll_write(write, ctx, fd) {
+                    Poll::Ready(result) => result,
+                    Poll::Pending       => break,
+                };
+                write.state = match result {
+                    Ok(n) if n > 0 && pos + n < len => WriteState::InFlight { pos: pos + n, len },
+                    Ok(n) if n > 0                  => WriteState::Empty,
+                    Ok(_)                           => {
+                        self.error.get_or_insert_with(|| io::ErrorKind::WriteZero.into());
+                        WriteState::Empty
+                    }
+                    Err(err)                        => {
+                        self.error.get_or_insert(err);
+                        WriteState::Empty
+                    }
+                };
+            }
+        }
+    }
+}
+
+fn poll_write<D: Drive>(write: &mut BehindWrite<D>, ctx: &mut Context<'_>, fd: RawFd)
+    -> Poll<io::Result<u32>>
+{
+    let (pos, len) = match write.state {
+        WriteState::InFlight { pos, len }   => (pos as usize, len as usize),
+        _                                   => unreachable!(),
+    };
+    let BehindWrite { ring, buf, offset, .. } = write;
+    let (buf, offset) = (&buf.as_ref().unwrap()[pos..len], *offset + pos as u64);
+    ring.as_mut().poll(ctx, 1, |sqs| {
+        let mut sqe = sqs.single().unwrap();
+        unsafe { sqe.prep_write(fd, buf, offset); }
+        sqe
+    })
+}
@@ -0,0 +1,68 @@
+mod pipe;
+mod splice;
+mod stdin;
+mod stdio;
+
+use std::borrow::Cow;
+
+use crate::Drive;
+
+pub use pipe::{pipe, pipe_on_driver, PipeReader, PipeWriter};
+pub use splice::{copy_zero_copy, copy_zero_copy_on_driver};
+pub use stdin::{stdin, stdin_on_driver, Stdin};
+pub use stdio::{stderr, stderr_on_driver, stdout, stdout_on_driver};
+pub use stdio::{Stderr, StderrLock, Stdout, StdoutLock};
+
+pub(crate) use splice::splice;
+
+// Reading or writing at this offset uses the file's offset in the kernel and advances it, and
+// reads or writes streams such as TTYs and pipes as usual.
+const CURRENT_OFFSET: u64 = u64::MAX;
+
+#[macro_export]
+macro_rules! print {
+    ($driver:expr, $($arg:tt)*) => {{
+        let mut s = format!($($arg)*);
+        $crate::io::__print($driver, s.into_bytes())
+    }};
+}
+
+#[macro_export]
+macro_rules! println {
+    ($driver:expr) => {$crate::io::__print($driver, b"\n")};
+    ($driver:expr, $($arg:tt)*) => {{
+        let mut s = format!($($arg)*);
+        s.push('\n');
+        $crate::io::__print($driver, s.into_bytes())
+    }};
+}
+
+#[macro_export]
+macro_rules! eprint {
+    ($driver:expr, $($arg:tt)*) => {{
+        let mut s = format!($($arg)*);
+        $crate::io::__eprint($driver, s.into_bytes())
+    }};
+}
+
+#[macro_export]
+macro_rules! eprintln {
+    ($driver:expr) => {$crate::io::__eprint($driver, b"\n")};
+    ($driver:expr, $($arg:tt)*) => {{
+        let mut s = format!($($arg)*);
+        s.push('\n');
+        $crate::io::__eprint($driver, s.into_bytes())
+    }};
+}
+
+#[doc(hidden)]
+pub async fn __print<D: Drive>(driver: D, bytes: impl Into<Cow<'static, [u8]>>) {
+    let bytes = bytes.into();
+    stdio::print(driver, &stdio::STDOUT, &bytes).await.expect("printing to stdout failed")
+}
+
+#[doc(hidden)]
+pub async fn __eprint<D: Drive>(driver: D, bytes: impl Into<Cow<'static, [u8]>>) {
+    let bytes = bytes.into();
+    stdio::print(driver, &stdio::STDERR, &bytes).await.expect("printing to stderr failed")
+}
@@ -0,0 +1,198 @@
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::ready;
+use futures_io::{AsyncRead, AsyncBufRead, AsyncWrite};
+use iou::sqe::SpliceFlags;
+
+use crate::buf::DEFAULT_CAPACITY;
+use crate::drive::{Drive, demo::DemoDriver};
+use crate::duplex::Half;
+use crate::event::Tee;
+
+/// The read end of a pipe, created by [`pipe`]
+pub struct PipeReader<D: Drive = DemoDriver> {
+    half: Half<D>,
+    fd: RawFd,
+}
+
+/// The write end of a pipe, created by [`pipe`]
+///
+/// Closing the writer closes its end of the pipe, so that the reader sees the end of the pipe
+/// once every other write end has been closed as well.
+pub struct PipeWriter<D: Drive = DemoDriver> {
+    half: Half<D>,
+    fd: RawFd,
+    state: State,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum State {
+    Open,
+    Closing,
+    Closed,
+}
+
+/// Create a pipe using the default driver, returning its read end and its write end
+pub fn pipe() -> io::Result<(PipeReader, PipeWriter)> {
+    pipe_on_driver(DemoDriver::default())
+}
+
+/// Create a pipe, returning its read end and its write end
+pub fn pipe_on_driver<D: Drive + Clone>(driver: D)
+    -> io::Result<(PipeReader<D>, PipeWriter<D>)>
+{
+    let (read, write) = pipe_fds()?;
+    Ok((PipeReader::from_fd(read, driver.clone()), PipeWriter::from_fd(write, driver)))
+}
+
+pub(super) fn pipe_fds() -> io::Result<(RawFd, RawFd)> {
+    let mut fds = [0; 2];
+    match unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } {
+        0   => Ok((fds[0], fds[1])),
+        _   => Err(io::Error::last_os_error()),
+    }
+}
+
+impl<D: Drive + Clone> PipeReader<D> {
+    /// Duplicate up to `len` bytes from this pipe into the pipe written to by `writer`, without
+    /// copying them or consuming them from this pipe, returning the number of bytes duplicated
+    ///
+    /// This waits until there is data in this pipe and room in the other. Only data still in the
+    /// pipe is duplicated, not data this reader has already read into its buffer.
+    pub async fn tee<E: Drive>(&self, writer: &PipeWriter<E>, len: u32) -> io::Result<usize> {
+        let flags = SpliceFlags::empty();
+        let tee = Tee { fd_in: self.fd, fd_out: writer.fd, bytes: len, flags };
+        let (_, result) = self.half.driver().clone().submit(tee).await;
+        Ok(result? as usize)
+    }
+}
+
+impl<D: Drive> PipeReader<D> {
+    /// Take ownership of the read end of a pipe.
+    pub(crate) fn from_fd(fd: RawFd, driver: D) -> PipeReader<D> {
+        PipeReader { half: Half::with_capacity(driver, DEFAULT_CAPACITY), fd }
+    }
+
+    fn half(self: Pin<&mut Self>) -> Pin<&mut Half<D>> {
+        unsafe { Pin::map_unchecked_mut(self, |this| &mut this.half) }
+    }
+}
+
+impl<D: Drive> PipeWriter<D> {
+    /// Take ownership of the write end of a pipe.
+    pub(crate) fn from_fd(fd: RawFd, driver: D) -> PipeWriter<D> {
+        PipeWriter { half: Half::with_capacity(driver, DEFAULT_CAPACITY), fd, state: State::Open }
+    }
+
+    fn half(self: Pin<&mut Self>) -> Pin<&mut Half<D>> {
+        unsafe { Pin::map_unchecked_mut(self, |this| &mut this.half) }
+    }
+
+    fn guard_open(&self) {
+        if self.state != State::Open {
+            panic!("Attempted to perform IO on a closed pipe");
+        }
+    }
+
+    fn set_state(self: Pin<&mut Self>, state: State) {
+        unsafe { Pin::get_unchecked_mut(self).state = state; }
+    }
+}
+
+impl<D: Drive> AsyncRead for PipeReader<D> {
+    fn poll_read(self: Pin<&mut Self>, ctx: &mut Context<'_>, buf: &mut [u8])
+        -> Poll<io::Result<usize>>
+    {
+        let fd = self.fd;
+        self.half().poll_read(ctx, fd, 0, buf)
+    }
+}
+
+impl<D: Drive> AsyncBufRead for PipeReader<D> {
+    fn poll_fill_buf(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let fd = self.fd;
+        self.half().poll_fill_buf(ctx, fd, 0)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.half().consume(amt);
+    }
+}
+
+impl<D: Drive> AsyncWrite for PipeWriter<D> {
+    fn poll_write(self: Pin<&mut Self>, ctx: &mut Context<'_>, slice: &[u8])
+        -> Poll<io::Result<usize>>
+    {
+        self.guard_open();
+        let fd = self.fd;
+        self.half().poll_write(ctx, fd, 0, slice)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_write(ctx, &[]))?;
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.state {
+            State::Open     => {
+                // Unlike a socket, a pipe has no way to discard what was written, so it is
+                // flushed before it is closed.
+                ready!(self.as_mut().poll_flush(ctx))?;
+                self.as_mut().set_state(State::Closing);
+            }
+            State::Closing  => { }
+            State::Closed   => return Poll::Ready(Ok(())),
+        }
+        let fd = self.fd;
+        ready!(self.as_mut().half().ring().poll(ctx, 1, |sqs| {
+            let mut sqe = sqs.single().unwrap();
+            unsafe {
+                sqe.prep_close(fd);
+            }
+            sqe
+        }))?;
+        self.set_state(State::Closed);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<D: Drive> AsRawFd for PipeReader<D> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl<D: Drive> AsRawFd for PipeWriter<D> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl<D: Drive> Drop for PipeReader<D> {
+    fn drop(&mut self) {
+        if self.half.is_idle() {
+            unsafe { libc::close(self.fd); }
+        } else {
+            // The kernel may still be using the file descriptor, so it is leaked.
+            self.half.cancel();
+        }
+    }
+}
+
+impl<D: Drive> Drop for PipeWriter<D> {
+    fn drop(&mut self) {
+        if self.state == State::Closed {
+            return;
+        }
+        if self.half.is_idle() {
+            unsafe { libc::close(self.fd); }
+        } else {
+            // The kernel may still be using the file descriptor, so it is leaked.
+            self.half.cancel();
+        }
+    }
+}
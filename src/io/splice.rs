@@ -0,0 +1,218 @@
+use std::cmp;
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+
+use iou::sqe::{PollFlags, SpliceFlags};
+use iou::{SQE, SQEs};
+
+use crate::drive::Drive;
+use crate::drive::demo::DemoDriver;
+use crate::event::{Event, PollAdd, Splice, prep_splice};
+
+/// Copy all of the data from `src` to `dst` using the default driver, without copying it into
+/// userspace, returning the number of bytes copied
+///
+/// See [`copy_zero_copy_on_driver`] for details.
+pub async fn copy_zero_copy(src: &impl AsRawFd, dst: &impl AsRawFd) -> io::Result<u64> {
+    copy_zero_copy_on_driver(src, dst, DemoDriver::default()).await
+}
+
+/// Copy all of the data from `src` to `dst`, without copying it into userspace, returning the
+/// number of bytes copied
+///
+/// `src` and `dst` can be any file descriptors which support `splice(2)`, such as files, sockets
+/// and pipes. The data is spliced from `src` into an internal pipe and from the pipe into `dst`,
+/// with both splices linked together so that each chunk is moved with one round trip. The copy
+/// continues until `src` reaches its end, and waits for readiness if either file descriptor is
+/// non-blocking and not ready.
+///
+/// Files are read and written at their offset in the kernel, which is separate from the cursor
+/// of a [`File`](crate::fs::File), and any data buffered by a ringbahn IO object is bypassed.
+pub async fn copy_zero_copy_on_driver<D: Drive + Clone>(
+    src: &impl AsRawFd,
+    dst: &impl AsRawFd,
+    driver: D,
+) -> io::Result<u64> {
+    splice(src.as_raw_fd(), None, dst.as_raw_fd(), u64::MAX, driver).await
+}
+
+/// Splice up to `len` bytes from `src` into `dst` through a pipe, reading `src` at `offset` if it
+/// is given and at its own offset otherwise.
+pub(crate) async fn splice<D: Drive + Clone>(
+    src: RawFd,
+    mut offset: Option<u64>,
+    dst: RawFd,
+    mut len: u64,
+    driver: D,
+) -> io::Result<u64> {
+    let mut pipe = Pipe::new()?;
+    let mut copied = 0;
+    // The number of bytes in the pipe which have not been spliced into `dst`.
+    let mut buffered = 0;
+
+    loop {
+        if buffered > 0 {
+            let drain = Splice {
+                fd_in: pipe.read,
+                off_in: -1,
+                fd_out: dst,
+                off_out: -1,
+                bytes: buffered,
+                flags: SpliceFlags::empty(),
+            };
+            match pipe.submit(drain, &driver).await {
+                Ok(0)                           => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n)                           => {
+                    buffered -= n;
+                    copied += n as u64;
+                }
+                Err(err) if would_block(&err)   => wait(dst, PollFlags::POLLOUT, &driver).await?,
+                Err(err)                        => return Err(err),
+            }
+            continue;
+        }
+
+        if len == 0 {
+            return Ok(copied);
+        }
+
+        // Fill the pipe and drain it into `dst` with one round trip. The splices are soft linked,
+        // so if the first one is short the second is cancelled, and the first one's result is
+        // found from what it left in the pipe.
+        let chunk = cmp::min(len, pipe.size as u64) as u32;
+        let off_in = offset.map_or(-1, |offset| offset as i64);
+        let (pipe_read, pipe_write) = (pipe.read, pipe.write);
+        let pair = SplicePair { src, off_in, pipe_read, pipe_write, dst, chunk };
+        let filled = match pipe.submit(pair, &driver).await {
+            Ok(n)                               => {
+                copied += n as u64;
+                buffered = chunk - n;
+                chunk
+            }
+            Err(err) if cancelled(&err)         => {
+                buffered = match pipe.buffered()? {
+                    // Nothing was spliced, so splice again on its own to find out why.
+                    0   => {
+                        let fill = Splice {
+                            fd_in: src,
+                            off_in,
+                            fd_out: pipe.write,
+                            off_out: -1,
+                            bytes: chunk,
+                            flags: SpliceFlags::empty(),
+                        };
+                        match pipe.submit(fill, &driver).await {
+                            Ok(0)                           => return Ok(copied),
+                            Ok(n)                           => n,
+                            Err(err) if would_block(&err)   => {
+                                wait(src, PollFlags::POLLIN, &driver).await?;
+                                0
+                            }
+                            Err(err)                        => return Err(err),
+                        }
+                    }
+                    n   => n,
+                };
+                buffered
+            }
+            // The pipe was filled, but `dst` was not ready for any of it.
+            Err(err) if would_block(&err)       => {
+                wait(dst, PollFlags::POLLOUT, &driver).await?;
+                buffered = chunk;
+                chunk
+            }
+            Err(err)                            => return Err(err),
+        };
+        offset = offset.map(|offset| offset + filled as u64);
+        len -= filled as u64;
+    }
+}
+
+// The second of two linked events completes with `ECANCELED` if the first was short or failed.
+fn cancelled(err: &io::Error) -> bool {
+    err.raw_os_error() == Some(libc::ECANCELED)
+}
+
+fn would_block(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::WouldBlock
+}
+
+async fn wait<D: Drive + Clone>(fd: RawFd, flags: PollFlags, driver: &D) -> io::Result<()> {
+    let (_, result) = driver.clone().submit(PollAdd { fd, flags }).await;
+    result.map(drop)
+}
+
+struct Pipe {
+    read: RawFd,
+    write: RawFd,
+    size: u32,
+    // A splice through the pipe may be in flight, so closing it could close a file descriptor
+    // which has been reused by the time the kernel gets to the splice.
+    busy: bool,
+}
+
+impl Pipe {
+    fn new() -> io::Result<Pipe> {
+        let (read, write) = super::pipe::pipe_fds()?;
+        let mut pipe = Pipe { read, write, size: 0, busy: false };
+        match unsafe { libc::fcntl(pipe.write, libc::F_GETPIPE_SZ) } {
+            size if size > 0    => pipe.size = size as u32,
+            _                   => return Err(io::Error::last_os_error()),
+        }
+        Ok(pipe)
+    }
+
+    // The number of bytes in the pipe.
+    fn buffered(&self) -> io::Result<u32> {
+        let mut n: libc::c_int = 0;
+        match unsafe { libc::ioctl(self.read, libc::FIONREAD, &mut n) } {
+            0   => Ok(n as u32),
+            _   => Err(io::Error::last_os_error()),
+        }
+    }
+
+    async fn submit<E: Event, D: Drive + Clone>(&mut self, event: E, driver: &D)
+        -> io::Result<u32>
+    {
+        self.busy = true;
+        let (_, result) = driver.clone().submit(event).await;
+        self.busy = false;
+        result
+    }
+}
+
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        if !self.busy {
+            unsafe {
+                libc::close(self.read);
+                libc::close(self.write);
+            }
+        }
+    }
+}
+
+// Splice `chunk` bytes from `src` into an empty pipe, then from the pipe into `dst`.
+struct SplicePair {
+    src: RawFd,
+    off_in: i64,
+    pipe_read: RawFd,
+    pipe_write: RawFd,
+    dst: RawFd,
+    chunk: u32,
+}
+
+impl Event for SplicePair {
+    fn sqes_needed(&self) -> u32 { 2 }
+
+    unsafe fn prepare<'sq>(&mut self, sqs: &mut SQEs<'sq>) -> SQE<'sq> {
+        let flags = SpliceFlags::empty();
+        let mut linked = sqs.soft_linked();
+        let mut fill = linked.next().unwrap();
+        prep_splice(&mut fill, self.src, self.off_in, self.pipe_write, -1, self.chunk, flags);
+        drop(fill);
+        let mut drain = linked.terminate().unwrap();
+        prep_splice(&mut drain, self.pipe_read, -1, self.dst, -1, self.chunk, flags);
+        drain
+    }
+}
@@ -0,0 +1,105 @@
+use std::io;
+use std::mem::MaybeUninit;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_io::{AsyncRead, AsyncBufRead};
+
+use crate::buf::DEFAULT_CAPACITY;
+use crate::drive::{Drive, demo::DemoDriver};
+use crate::duplex::Half;
+
+use super::CURRENT_OFFSET;
+
+/// A handle to the standard input of the current process.
+///
+/// Reads are buffered, so lines can be read with `AsyncBufReadExt::read_line` without an event
+/// per byte. A TTY completes a read at the end of each line, so lines typed at a terminal are
+/// returned as they are entered.
+///
+/// Standard input is read at its offset in the kernel, so if it is a regular file, reading
+/// continues from wherever the file was left by this or another process. Each handle has its
+/// own buffer, so data buffered by one handle is not seen by another; when a handle is dropped,
+/// the offset of a regular file is moved back over what it buffered but did not consume, so that
+/// a later reader continues from the right place.
+pub struct Stdin<D: Drive = DemoDriver> {
+    half: Half<D>,
+    seekable: bool,
+}
+
+/// Constructs a new `stdin` handle run on the demo driver.
+/// ```no_run
+/// use ringbahn::io;
+///
+/// # use futures::AsyncBufReadExt;
+/// # fn main() -> std::io::Result<()> { futures::executor::block_on(async {
+/// let mut line = String::new();
+/// io::stdin().read_line(&mut line).await?;
+/// # Ok(())
+/// # })
+/// # }
+/// ```
+pub fn stdin() -> Stdin<DemoDriver> {
+    stdin_on_driver(DemoDriver::default())
+}
+
+/// Constructs a new `stdin` handle run on the provided driver.
+pub fn stdin_on_driver<D: Drive>(driver: D) -> Stdin<D> {
+    Stdin {
+        half: Half::with_capacity(driver, DEFAULT_CAPACITY),
+        seekable: is_seekable(libc::STDIN_FILENO),
+    }
+}
+
+fn is_seekable(fd: RawFd) -> bool {
+    let mut stat = MaybeUninit::<libc::stat>::uninit();
+    if unsafe { libc::fstat(fd, stat.as_mut_ptr()) } < 0 {
+        return false;
+    }
+    let mode = unsafe { stat.assume_init() }.st_mode & libc::S_IFMT;
+    mode == libc::S_IFREG || mode == libc::S_IFBLK
+}
+
+impl<D: Drive> Stdin<D> {
+    fn half(self: Pin<&mut Self>) -> Pin<&mut Half<D>> {
+        unsafe { Pin::map_unchecked_mut(self, |this| &mut this.half) }
+    }
+}
+
+impl<D: Drive> AsyncRead for Stdin<D> {
+    fn poll_read(self: Pin<&mut Self>, ctx: &mut Context<'_>, buf: &mut [u8])
+        -> Poll<io::Result<usize>>
+    {
+        self.half().poll_read(ctx, libc::STDIN_FILENO, CURRENT_OFFSET, buf)
+    }
+}
+
+impl<D: Drive> AsyncBufRead for Stdin<D> {
+    fn poll_fill_buf(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        self.half().poll_fill_buf(ctx, libc::STDIN_FILENO, CURRENT_OFFSET)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.half().consume(amt);
+    }
+}
+
+impl<D: Drive> AsRawFd for Stdin<D> {
+    fn as_raw_fd(&self) -> RawFd {
+        libc::STDIN_FILENO
+    }
+}
+
+impl<D: Drive> Drop for Stdin<D> {
+    fn drop(&mut self) {
+        if !self.half.is_idle() {
+            self.half.cancel();
+            return;
+        }
+        let unread = self.half.buffered_from_read().len() as libc::off_t;
+        if self.seekable && unread > 0 {
+            unsafe { libc::lseek(libc::STDIN_FILENO, -unread, libc::SEEK_CUR); }
+        }
+    }
+}
@@ -0,0 +1,448 @@
+use std::any::Any;
+use std::future::{self, Future};
+use std::io;
+use std::mem;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::{Context, Poll};
+
+use event_listener::{Event, EventListener};
+use futures_core::ready;
+use futures_io::AsyncWrite;
+
+use crate::buf::{self, Buffer, DEFAULT_CAPACITY};
+use crate::drive::{Drive, demo::DemoDriver};
+use crate::ring::{Cancellation, Ring};
+
+use super::CURRENT_OFFSET;
+
+/// An output stream shared by every handle to it in the process, with a lock which is held while
+/// each line is written.
+pub(super) struct Stream {
+    fd: RawFd,
+    locked: AtomicBool,
+    unlocked: Event,
+    // Partial lines from handles dropped while another handle held the lock, which are written
+    // before the lock is released.
+    kept: Mutex<Vec<u8>>,
+}
+
+pub(super) static STDOUT: Stream = Stream::new(libc::STDOUT_FILENO);
+pub(super) static STDERR: Stream = Stream::new(libc::STDERR_FILENO);
+
+impl Stream {
+    const fn new(fd: RawFd) -> Stream {
+        Stream {
+            fd,
+            locked: AtomicBool::new(false),
+            unlocked: Event::new(),
+            kept: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn try_lock(&self) -> bool {
+        !self.locked.swap(true, Ordering::Acquire)
+    }
+
+    fn unlock(&self) {
+        loop {
+            let kept = mem::take(&mut *self.kept());
+            self.write_blocking(&kept);
+            self.locked.store(false, Ordering::Release);
+            self.unlocked.notify(1);
+            // A line kept after it was taken is written by whichever thread takes the lock again.
+            if self.kept().is_empty() || !self.try_lock() {
+                break;
+            }
+        }
+    }
+
+    // Keep a partial line to be written by the handle holding the lock when it releases it.
+    fn keep(&self, data: &[u8]) {
+        self.kept().extend_from_slice(data);
+        // The lock may have been released before the line was kept.
+        if self.try_lock() {
+            self.unlock();
+        }
+    }
+
+    fn kept(&self) -> MutexGuard<'_, Vec<u8>> {
+        self.kept.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Write all of `data` with blocking writes, for handles which cannot wait. Errors are ignored.
+    fn write_blocking(&self, mut data: &[u8]) {
+        while !data.is_empty() {
+            let n = unsafe { libc::write(self.fd, data.as_ptr().cast(), data.len()) };
+            if n <= 0 {
+                break;
+            }
+            data = &data[n as usize..];
+        }
+    }
+}
+
+/// A handle to the standard output of the current process.
+///
+/// Every handle writes to one process-wide stream. Output is buffered until a line is complete,
+/// and each line is written in full while holding the stream's lock, so lines written through
+/// different handles are never interleaved. A partial line is written when the handle is flushed
+/// or dropped. Use [`lock`](Stdout::lock) to write several lines without other output between
+/// them.
+pub struct Stdout<D: Drive = DemoDriver> {
+    writer: Writer<D>,
+}
+
+/// A handle to the standard error of the current process.
+///
+/// Standard error is written in the same way as [`Stdout`], a line at a time.
+pub struct Stderr<D: Drive = DemoDriver> {
+    writer: Writer<D>,
+}
+
+/// A locked handle to the standard output, created by [`Stdout::lock`].
+///
+/// No other handle writes to the standard output until this is dropped.
+pub struct StdoutLock<'a, D: Drive = DemoDriver> {
+    writer: Pin<&'a mut Writer<D>>,
+}
+
+/// A locked handle to the standard error, created by [`Stderr::lock`].
+///
+/// No other handle writes to the standard error until this is dropped.
+pub struct StderrLock<'a, D: Drive = DemoDriver> {
+    writer: Pin<&'a mut Writer<D>>,
+}
+
+/// Constructs a new `stdout` handle run on the demo driver.
+/// ```no_run
+/// use ringbahn::io;
+///
+/// # use futures::AsyncWriteExt;
+/// # fn main() -> std::io::Result<()> { futures::executor::block_on(async {
+/// io::stdout().write(b"hello, world").await?;
+/// # Ok(())
+/// # })
+/// # }
+/// ```
+pub fn stdout() -> Stdout<DemoDriver> {
+    stdout_on_driver(DemoDriver::default())
+}
+
+/// Constructs a new `stdout` handle run on the provided driver.
+pub fn stdout_on_driver<D: Drive>(driver: D) -> Stdout<D> {
+    Stdout { writer: Writer::new(driver, &STDOUT) }
+}
+
+/// Constructs a new `stderr` handle run on the demo driver.
+pub fn stderr() -> Stderr<DemoDriver> {
+    stderr_on_driver(DemoDriver::default())
+}
+
+/// Constructs a new `stderr` handle run on the provided driver.
+pub fn stderr_on_driver<D: Drive>(driver: D) -> Stderr<D> {
+    Stderr { writer: Writer::new(driver, &STDERR) }
+}
+
+impl<D: Drive> Stdout<D> {
+    /// Lock the standard output, waiting until no other handle is writing to it.
+    ///
+    /// Anything written through the lock is still written a line at a time, but no other output
+    /// is written until the lock is dropped.
+    pub async fn lock(&mut self) -> StdoutLock<'_, D> where D: Unpin {
+        StdoutLock { writer: Pin::new(&mut self.writer).lock().await }
+    }
+
+    fn writer(self: Pin<&mut Self>) -> Pin<&mut Writer<D>> {
+        unsafe { Pin::map_unchecked_mut(self, |this| &mut this.writer) }
+    }
+}
+
+impl<D: Drive> Stderr<D> {
+    /// Lock the standard error, waiting until no other handle is writing to it.
+    ///
+    /// Anything written through the lock is still written a line at a time, but no other output
+    /// is written until the lock is dropped.
+    pub async fn lock(&mut self) -> StderrLock<'_, D> where D: Unpin {
+        StderrLock { writer: Pin::new(&mut self.writer).lock().await }
+    }
+
+    fn writer(self: Pin<&mut Self>) -> Pin<&mut Writer<D>> {
+        unsafe { Pin::map_unchecked_mut(self, |this| &mut this.writer) }
+    }
+}
+
+/// Write all of `bytes` to `stream` while holding its lock.
+pub(super) async fn print<D: Drive>(driver: D, stream: &'static Stream, mut bytes: &[u8])
+    -> io::Result<()>
+{
+    let mut writer = Writer::new(driver, stream);
+    // Safety: the writer is shadowed, so it cannot be moved again
+    let writer = unsafe { Pin::new_unchecked(&mut writer) };
+    let mut writer = writer.lock().await;
+    while !bytes.is_empty() {
+        let n = future::poll_fn(|ctx| writer.as_mut().poll_write(ctx, bytes)).await?;
+        bytes = &bytes[n..];
+    }
+    future::poll_fn(|ctx| writer.as_mut().poll_flush(ctx)).await
+}
+
+macro_rules! impl_write {
+    ($($t:ident)*) => {$(
+        impl<D: Drive> AsyncWrite for $t<D> {
+            fn poll_write(self: Pin<&mut Self>, ctx: &mut Context<'_>, slice: &[u8])
+                -> Poll<io::Result<usize>>
+            {
+                self.writer().poll_write(ctx, slice)
+            }
+
+            fn poll_flush(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<()>> {
+                self.writer().poll_flush(ctx)
+            }
+
+            fn poll_close(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<()>> {
+                self.writer().poll_flush(ctx)
+            }
+        }
+
+        impl<D: Drive> AsRawFd for $t<D> {
+            fn as_raw_fd(&self) -> RawFd {
+                self.writer.stream.fd
+            }
+        }
+    )*}
+}
+
+impl_write!(Stdout Stderr);
+
+macro_rules! impl_lock {
+    ($($t:ident)*) => {$(
+        impl<D: Drive> AsyncWrite for $t<'_, D> {
+            fn poll_write(mut self: Pin<&mut Self>, ctx: &mut Context<'_>, slice: &[u8])
+                -> Poll<io::Result<usize>>
+            {
+                self.writer.as_mut().poll_write(ctx, slice)
+            }
+
+            fn poll_flush(mut self: Pin<&mut Self>, ctx: &mut Context<'_>)
+                -> Poll<io::Result<()>>
+            {
+                self.writer.as_mut().poll_flush(ctx)
+            }
+
+            fn poll_close(mut self: Pin<&mut Self>, ctx: &mut Context<'_>)
+                -> Poll<io::Result<()>>
+            {
+                self.writer.as_mut().poll_flush(ctx)
+            }
+        }
+
+        impl<D: Drive> AsRawFd for $t<'_, D> {
+            fn as_raw_fd(&self) -> RawFd {
+                self.writer.stream.fd
+            }
+        }
+
+        impl<D: Drive> Drop for $t<'_, D> {
+            fn drop(&mut self) {
+                self.writer.as_mut().unlock();
+            }
+        }
+    )*}
+}
+
+impl_lock!(StdoutLock StderrLock);
+
+// A line buffered writer to a stream, which holds the stream's lock while it writes.
+struct Writer<D: Drive> {
+    ring: Ring<D>,
+    buf: Buffer,
+    stream: &'static Stream,
+    listener: Option<EventListener>,
+    // This writer holds the stream's lock.
+    held: bool,
+    // A lock handle is keeping the lock held between writes.
+    locked: bool,
+    // The number of bytes taken from the slice being written, while the lines it completed are
+    // written.
+    accepted: Option<usize>,
+}
+
+impl<D: Drive> Writer<D> {
+    fn new(driver: D, stream: &'static Stream) -> Writer<D> {
+        let pool = driver.fixed_pool();
+        Writer {
+            ring: Ring::new(driver),
+            buf: Buffer::with_pool(DEFAULT_CAPACITY, pool),
+            stream,
+            listener: None,
+            held: false,
+            locked: false,
+            accepted: None,
+        }
+    }
+
+    async fn lock(mut self: Pin<&mut Self>) -> Pin<&mut Self> {
+        future::poll_fn(|ctx| self.as_mut().poll_lock(ctx)).await;
+        unsafe { Pin::get_unchecked_mut(self.as_mut()).locked = true; }
+        self
+    }
+
+    fn unlock(self: Pin<&mut Self>) {
+        let this = unsafe { Pin::get_unchecked_mut(self) };
+        this.locked = false;
+        // If lines are still being written, the lock is released once they have been.
+        if this.ring.is_idle() {
+            this.release();
+        }
+    }
+
+    fn poll_lock(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<()> {
+        let this = unsafe { Pin::get_unchecked_mut(self) };
+        loop {
+            if this.held {
+                this.listener = None;
+                return Poll::Ready(());
+            }
+            if this.stream.try_lock() {
+                this.held = true;
+                continue;
+            }
+            match &mut this.listener {
+                Some(listener)  => {
+                    ready!(Pin::new(listener).poll(ctx));
+                    this.listener = None;
+                }
+                None            => this.listener = Some(this.stream.unlocked.listen()),
+            }
+        }
+    }
+
+    fn release(&mut self) {
+        if self.held && !self.locked {
+            self.held = false;
+            self.stream.unlock();
+        }
+    }
+
+    fn poll_write(mut self: Pin<&mut Self>, ctx: &mut Context<'_>, slice: &[u8])
+        -> Poll<io::Result<usize>>
+    {
+        if self.accepted.is_none() {
+            // Only take up to the end of the last line in the slice, so that the rest of it
+            // starts a new line in the buffer once the lines it completes have been written.
+            let line = match slice.iter().rposition(|&byte| byte == b'\n') {
+                Some(end)   => &slice[..=end],
+                None        => slice,
+            };
+            let mut n = self.as_mut().buf().append(line);
+            if n == 0 && !line.is_empty() {
+                // The buffer is full of a partial line, which is written out to make room.
+                ready!(self.as_mut().poll_flush_buf(ctx))?;
+                n = self.as_mut().buf().append(line);
+            }
+            unsafe { Pin::get_unchecked_mut(self.as_mut()).accepted = Some(n); }
+        }
+        let buffered = self.buf.buffered_from_read();
+        if self.buf.is_full() || buffered.ends_with(b"\n") {
+            let result = ready!(self.as_mut().poll_flush_buf(ctx));
+            let n = unsafe { Pin::get_unchecked_mut(self.as_mut()).accepted.take().unwrap() };
+            Poll::Ready(result.map(|()| n))
+        } else {
+            let n = unsafe { Pin::get_unchecked_mut(self.as_mut()).accepted.take().unwrap() };
+            Poll::Ready(Ok(n))
+        }
+    }
+
+    // Any bytes accepted by a write which has not returned are left to be returned by the next
+    // write, so that they are not written again.
+    fn poll_flush(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush_buf(ctx)
+    }
+
+    // Write everything in the buffer while holding the lock, retrying short writes.
+    fn poll_flush_buf(mut self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if self.buf.buffered_from_read().is_empty() {
+            return Poll::Ready(Ok(()));
+        }
+        ready!(self.as_mut().poll_lock(ctx));
+        let this = unsafe { Pin::get_unchecked_mut(self) };
+        let fd = this.stream.fd;
+        let result = loop {
+            let (data, index) = (this.buf.buffered_from_read(), this.buf.fixed_index());
+            if data.is_empty() {
+                break Ok(());
+            }
+            let ring = unsafe { Pin::new_unchecked(&mut this.ring) };
+            let n = ready!(ring.poll(ctx, 1, |sqs| {
+                let mut sqe = sqs.single().unwrap();
+                unsafe {
+                    sqe.prep_write(fd, data, CURRENT_OFFSET);
+                    if let Some(index) = index {
+                        buf::fix(&mut sqe, index);
+                    }
+                }
+                sqe
+            }));
+            match n {
+                Ok(0)   => break Err(io::ErrorKind::WriteZero.into()),
+                Ok(n)   => this.buf.consume(n as usize),
+                Err(e)  => break Err(e),
+            }
+        };
+        this.buf.clear();
+        this.release();
+        Poll::Ready(result)
+    }
+
+    fn buf(self: Pin<&mut Self>) -> &mut Buffer {
+        unsafe { &mut Pin::get_unchecked_mut(self).buf }
+    }
+}
+
+impl<D: Drive> Drop for Writer<D> {
+    fn drop(&mut self) {
+        self.locked = false;
+        if !self.ring.is_idle() {
+            // The lock is held until the cancelled write completes, so that no other output is
+            // written into the middle of its line.
+            let stream = match mem::replace(&mut self.held, false) {
+                true    => Some(self.stream),
+                false   => None,
+            };
+            let cancelled = Cancelled { stream, _buf: self.buf.cancellation() };
+            let cancelled: Box<dyn Any + Send + Sync> = Box::new(cancelled);
+            self.ring.cancel(Cancellation::from(cancelled));
+            return;
+        }
+        // A partial line is written out with blocking writes, since the handle cannot wait, but
+        // only while holding the lock. Otherwise, it is written when the lock is released.
+        let data = self.buf.buffered_from_read();
+        if !data.is_empty() {
+            if self.held || self.stream.try_lock() {
+                self.held = true;
+                self.stream.write_blocking(data);
+            } else {
+                self.stream.keep(data);
+            }
+        }
+        self.release();
+    }
+}
+
+// The buffer of a write which was cancelled when its handle was dropped, with the lock the handle
+// held, which is released once the write completes.
+struct Cancelled {
+    stream: Option<&'static Stream>,
+    _buf: Cancellation,
+}
+
+impl Drop for Cancelled {
+    fn drop(&mut self) {
+        if let Some(stream) = self.stream {
+            stream.unlock();
+        }
+    }
+}
@@ -7,11 +7,18 @@ pub mod event;
 pub mod ring;
 
 pub mod io;
+pub mod process;
+pub mod signal;
 
-mod buf;
+pub mod buf;
+
+mod blocking;
+mod duplex;
 mod submission;
+mod submission_set;
 
 pub use submission::Submission;
+pub use submission_set::{SubmissionSet, JoinAll};
 
 #[doc(inline)]
 pub use drive::Drive;
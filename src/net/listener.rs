@@ -51,11 +51,11 @@ impl<D: Drive> TcpListener<D> {
         })
     }
 
-    pub fn close(&mut self) -> Close<D> where D: Unpin {
+    pub fn close(&mut self) -> Close<'_, D> where D: Unpin {
         Pin::new(self).close_pinned()
     }
 
-    pub fn close_pinned(self: Pin<&mut Self>) -> Close<D> {
+    pub fn close_pinned(self: Pin<&mut Self>) -> Close<'_, D> {
         Close { socket: self }
     }
 
@@ -167,7 +167,7 @@ impl<D: Drive + Clone> TcpListener<D> {
             }
         };
 
-        Poll::Ready(Ok((TcpStream::from_fd(fd, self.ring().clone()), addr)))
+        Poll::Ready(Ok((TcpStream::from_fd(fd, self.ring.driver().clone()), addr)))
     }
 
     pub fn poll_accept_no_addr(mut self: Pin<&mut Self>, ctx: &mut Context<'_>)
@@ -182,7 +182,7 @@ impl<D: Drive + Clone> TcpListener<D> {
             }
             sqe
         }))? as RawFd;
-        Poll::Ready(Ok(TcpStream::from_fd(fd, self.ring().clone())))
+        Poll::Ready(Ok(TcpStream::from_fd(fd, self.ring.driver().clone())))
     }
 }
 
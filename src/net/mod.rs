@@ -1,4 +1,5 @@
 mod listener;
+mod split;
 mod stream;
 
 use std::io;
@@ -6,6 +7,7 @@ use std::net::{SocketAddr, ToSocketAddrs};
 use std::os::unix::io::RawFd;
 
 pub use listener::{TcpListener, Accept, AcceptNoAddr, Close, Incoming, IncomingNoAddr};
+pub use split::{ReadHalf, WriteHalf, OwnedReadHalf, OwnedWriteHalf};
 pub use stream::{TcpStream, Connect};
 
 use nix::sys::socket as nix;
@@ -0,0 +1,197 @@
+use std::io;
+use std::mem::ManuallyDrop;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures_core::ready;
+use futures_io::{AsyncRead, AsyncBufRead, AsyncWrite};
+
+use crate::drive::{Drive, demo::DemoDriver};
+use crate::duplex::{Fd, Half, drop_half};
+
+/// The read half of a stream, borrowed from it by `split`.
+pub struct ReadHalf<'a, D: Drive> {
+    half: Pin<&'a mut Half<D>>,
+    fd: RawFd,
+}
+
+/// The write half of a stream, borrowed from it by `split`.
+///
+/// Closing the write half shuts down the writing side of the socket, without closing it.
+pub struct WriteHalf<'a, D: Drive> {
+    half: Pin<&'a mut Half<D>>,
+    fd: RawFd,
+}
+
+/// The read half of a stream, created by `into_split`.
+pub struct OwnedReadHalf<D: Drive = DemoDriver> {
+    half: Half<D>,
+    fd: ManuallyDrop<Arc<Fd>>,
+}
+
+/// The write half of a stream, created by `into_split`.
+///
+/// Closing the write half shuts down the writing side of the socket. The socket is closed once
+/// both halves have been dropped.
+pub struct OwnedWriteHalf<D: Drive = DemoDriver> {
+    half: Half<D>,
+    fd: ManuallyDrop<Arc<Fd>>,
+}
+
+impl<'a, D: Drive> ReadHalf<'a, D> {
+    pub(super) fn new(half: Pin<&'a mut Half<D>>, fd: RawFd) -> ReadHalf<'a, D> {
+        ReadHalf { half, fd }
+    }
+}
+
+impl<'a, D: Drive> WriteHalf<'a, D> {
+    pub(super) fn new(half: Pin<&'a mut Half<D>>, fd: RawFd) -> WriteHalf<'a, D> {
+        WriteHalf { half, fd }
+    }
+}
+
+impl<D: Drive> OwnedReadHalf<D> {
+    pub(super) fn new(half: Half<D>, fd: Arc<Fd>) -> OwnedReadHalf<D> {
+        OwnedReadHalf { half, fd: ManuallyDrop::new(fd) }
+    }
+
+    fn half(self: Pin<&mut Self>) -> Pin<&mut Half<D>> {
+        unsafe { Pin::map_unchecked_mut(self, |this| &mut this.half) }
+    }
+}
+
+impl<D: Drive> OwnedWriteHalf<D> {
+    pub(super) fn new(half: Half<D>, fd: Arc<Fd>) -> OwnedWriteHalf<D> {
+        OwnedWriteHalf { half, fd: ManuallyDrop::new(fd) }
+    }
+
+    fn half(self: Pin<&mut Self>) -> Pin<&mut Half<D>> {
+        unsafe { Pin::map_unchecked_mut(self, |this| &mut this.half) }
+    }
+}
+
+fn poll_read<D: Drive>(
+    half: Pin<&mut Half<D>>,
+    ctx: &mut Context<'_>,
+    fd: RawFd,
+    buf: &mut [u8],
+) -> Poll<io::Result<usize>> {
+    half.poll_read(ctx, fd, 0, buf)
+}
+
+fn poll_flush<D: Drive>(half: Pin<&mut Half<D>>, ctx: &mut Context<'_>, fd: RawFd)
+    -> Poll<io::Result<()>>
+{
+    ready!(half.poll_write(ctx, fd, 0, &[]))?;
+    Poll::Ready(Ok(()))
+}
+
+fn poll_shutdown<D: Drive>(half: Pin<&mut Half<D>>, ctx: &mut Context<'_>, fd: RawFd)
+    -> Poll<io::Result<()>>
+{
+    ready!(poll_flush(half, ctx, fd))?;
+    match unsafe { libc::shutdown(fd, libc::SHUT_WR) } {
+        n if n < 0  => Poll::Ready(Err(io::Error::last_os_error())),
+        _           => Poll::Ready(Ok(())),
+    }
+}
+
+impl<'a, D: Drive> AsyncRead for ReadHalf<'a, D> {
+    fn poll_read(mut self: Pin<&mut Self>, ctx: &mut Context<'_>, buf: &mut [u8])
+        -> Poll<io::Result<usize>>
+    {
+        let fd = self.fd;
+        poll_read(self.half.as_mut(), ctx, fd, buf)
+    }
+}
+
+impl<'a, D: Drive> AsyncBufRead for ReadHalf<'a, D> {
+    fn poll_fill_buf(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let this = Pin::into_inner(self);
+        this.half.as_mut().poll_fill_buf(ctx, this.fd, 0)
+    }
+
+    fn consume(mut self: Pin<&mut Self>, amt: usize) {
+        self.half.as_mut().consume(amt);
+    }
+}
+
+impl<'a, D: Drive> AsyncWrite for WriteHalf<'a, D> {
+    fn poll_write(mut self: Pin<&mut Self>, ctx: &mut Context<'_>, slice: &[u8]) -> Poll<io::Result<usize>> {
+        let fd = self.fd;
+        self.half.as_mut().poll_write(ctx, fd, 0, slice)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let fd = self.fd;
+        poll_flush(self.half.as_mut(), ctx, fd)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let fd = self.fd;
+        poll_shutdown(self.half.as_mut(), ctx, fd)
+    }
+}
+
+impl<D: Drive> AsyncRead for OwnedReadHalf<D> {
+    fn poll_read(self: Pin<&mut Self>, ctx: &mut Context<'_>, buf: &mut [u8])
+        -> Poll<io::Result<usize>>
+    {
+        let fd = self.fd.0;
+        poll_read(self.half(), ctx, fd, buf)
+    }
+}
+
+impl<D: Drive> AsyncBufRead for OwnedReadHalf<D> {
+    fn poll_fill_buf(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let fd = self.fd.0;
+        self.half().poll_fill_buf(ctx, fd, 0)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.half().consume(amt);
+    }
+}
+
+impl<D: Drive> AsyncWrite for OwnedWriteHalf<D> {
+    fn poll_write(self: Pin<&mut Self>, ctx: &mut Context<'_>, slice: &[u8]) -> Poll<io::Result<usize>> {
+        let fd = self.fd.0;
+        self.half().poll_write(ctx, fd, 0, slice)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let fd = self.fd.0;
+        poll_flush(self.half(), ctx, fd)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let fd = self.fd.0;
+        poll_shutdown(self.half(), ctx, fd)
+    }
+}
+
+impl<D: Drive> AsRawFd for OwnedReadHalf<D> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.0
+    }
+}
+
+impl<D: Drive> AsRawFd for OwnedWriteHalf<D> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.0
+    }
+}
+
+impl<D: Drive> Drop for OwnedReadHalf<D> {
+    fn drop(&mut self) {
+        drop_half(&mut self.half, &mut self.fd);
+    }
+}
+
+impl<D: Drive> Drop for OwnedWriteHalf<D> {
+    fn drop(&mut self) {
+        drop_half(&mut self.half, &mut self.fd);
+    }
+}
@@ -1,8 +1,11 @@
 use std::io;
-use std::future::Future;
-use std::net::ToSocketAddrs;
-use std::os::unix::io::RawFd;
+use std::future::{self, Future};
+use std::net::{self, ToSocketAddrs};
+use std::mem::ManuallyDrop;
+use std::os::unix::io::{AsRawFd, IntoRawFd, RawFd};
 use std::pin::Pin;
+use std::ptr;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
 use futures_core::ready;
@@ -10,27 +13,32 @@ use futures_io::{AsyncRead, AsyncBufRead, AsyncWrite};
 use iou::sqe::SockAddr;
 use nix::sys::socket::SockProtocol;
 
-use crate::buf::Buffer;
+use crate::buf::DEFAULT_CAPACITY;
 use crate::drive::{Drive, demo::DemoDriver};
-use crate::ring::Ring;
+use crate::duplex::{Fd, Half};
 use crate::event;
+use crate::fs::File;
 use crate::Submission;
 
 use super::socket;
+use super::split::{ReadHalf, WriteHalf, OwnedReadHalf, OwnedWriteHalf};
 
+/// A TCP stream that runs on io-uring
+///
+/// Reads and writes have separate buffers and are submitted independently, so one task can read
+/// from the stream while another writes to it. Use `split` or `into_split` to obtain halves of the
+/// stream which can be used separately.
 pub struct TcpStream<D: Drive = DemoDriver> {
-    ring: Ring<D>,
-    buf: Buffer,
-    active: Op,
+    read: Half<D>,
+    write: Half<D>,
+    state: State,
     fd: RawFd,
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
-enum Op {
-    Read,
-    Write,
-    Close,
-    Nothing,
+enum State {
+    Open,
+    Closing,
     Closed,
 }
 
@@ -38,6 +46,16 @@ impl TcpStream {
     pub fn connect<A: ToSocketAddrs>(addr: A) -> Connect {
         TcpStream::connect_on_driver(addr, DemoDriver::default())
     }
+
+    /// Take a connected stream and run its IO on the default driver, with read and write buffers
+    /// of `capacity` bytes
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0 or not less than 4 GiB.
+    pub fn with_capacity(capacity: usize, stream: net::TcpStream) -> TcpStream {
+        TcpStream::with_capacity_on_driver(capacity, stream, DemoDriver::default())
+    }
 }
 
 impl<D: Drive + Clone> TcpStream<D> {
@@ -49,52 +67,101 @@ impl<D: Drive + Clone> TcpStream<D> {
         let addr = Box::new(SockAddr::Inet(nix::sys::socket::InetAddr::from_std(&addr)));
         Connect(Ok(driver.submit(event::Connect { fd, addr })))
     }
-}
 
-impl<D: Drive> TcpStream<D> {
-    pub(crate) fn from_fd(fd: RawFd, ring: Ring<D>) -> TcpStream<D> {
+    /// Take a connected stream and run its IO on an io-uring driver, with read and write buffers
+    /// of `capacity` bytes rather than the default of 8 KiB
+    ///
+    /// Reads into a slice larger than the read buffer are made in one event of up to 64 times its
+    /// capacity, as long as there is no buffered data left to return first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0 or not less than 4 GiB.
+    pub fn with_capacity_on_driver(capacity: usize, stream: net::TcpStream, driver: D)
+        -> TcpStream<D>
+    {
+        TcpStream::from_fd_with_capacity(stream.into_raw_fd(), driver, capacity)
+    }
+
+    pub(crate) fn from_fd(fd: RawFd, driver: D) -> TcpStream<D> {
+        TcpStream::from_fd_with_capacity(fd, driver, DEFAULT_CAPACITY)
+    }
+
+    pub(crate) fn from_fd_with_capacity(fd: RawFd, driver: D, capacity: usize) -> TcpStream<D> {
         TcpStream {
-            buf: Buffer::default(),
-            active: Op::Nothing,
-            fd, ring,
+            read: Half::with_capacity(driver.clone(), capacity),
+            write: Half::with_capacity(driver, capacity),
+            state: State::Open,
+            fd,
         }
     }
+}
+
+impl<D: Drive + Clone> TcpStream<D> {
+    /// Send `len` bytes of `file` starting at `offset` over the stream, without copying them into
+    /// userspace, returning the number of bytes sent
+    ///
+    /// Like `sendfile(2)`, the data is spliced from the file into the socket through a pipe. Fewer
+    /// than `len` bytes are sent if the file ends first. Anything written to the stream is flushed
+    /// before the file is sent, but data `file` has buffered and not yet written is not seen.
+    pub async fn send_file<F: Drive>(&mut self, file: &File<F>, offset: u64, len: u64)
+        -> io::Result<u64> where D: Unpin
+    {
+        future::poll_fn(|ctx| Pin::new(&mut *self).poll_flush(ctx)).await?;
+        let driver = self.write.driver().clone();
+        crate::io::splice(file.as_raw_fd(), Some(offset), self.fd, len, driver).await
+    }
+}
+
+impl<D: Drive> TcpStream<D> {
+    /// Split the stream into a read half and a write half which borrow it.
+    pub fn split(&mut self) -> (ReadHalf<'_, D>, WriteHalf<'_, D>) where D: Unpin {
+        Pin::new(self).split_pinned()
+    }
 
-    fn guard_op(self: Pin<&mut Self>, op: Op) {
-        let (ring, buf, active) = self.split();
-        if *active == Op::Closed {
-            panic!("Attempted to perform IO on a closed stream");
-        } else if *active != Op::Nothing && *active != op {
-            ring.cancel_pinned(buf.cancellation());
+    /// Split the stream into a read half and a write half which borrow it, from a pinned
+    /// reference.
+    pub fn split_pinned(self: Pin<&mut Self>) -> (ReadHalf<'_, D>, WriteHalf<'_, D>) {
+        self.guard_open();
+        let fd = self.fd;
+        let this = unsafe { Pin::get_unchecked_mut(self) };
+        unsafe {
+            let read = ReadHalf::new(Pin::new_unchecked(&mut this.read), fd);
+            let write = WriteHalf::new(Pin::new_unchecked(&mut this.write), fd);
+            (read, write)
         }
-        *active = op;
     }
 
-    fn cancel(&mut self) {
-        self.active = Op::Nothing;
-        self.ring.cancel(self.buf.cancellation());
+    /// Split the stream into a read half and a write half which own it, and can be moved to
+    /// different tasks.
+    ///
+    /// The socket is closed once both halves have been dropped.
+    pub fn into_split(self) -> (OwnedReadHalf<D>, OwnedWriteHalf<D>) {
+        self.guard_open();
+        let this = ManuallyDrop::new(self);
+        let (read, write) = unsafe { (ptr::read(&this.read), ptr::read(&this.write)) };
+        let fd = Arc::new(Fd(this.fd));
+        (OwnedReadHalf::new(read, fd.clone()), OwnedWriteHalf::new(write, fd))
     }
 
-    #[inline(always)]
-    fn ring(self: Pin<&mut Self>) -> Pin<&mut Ring<D>> {
-        self.split().0
+    fn guard_open(&self) {
+        if self.state != State::Open {
+            panic!("Attempted to perform IO on a closed stream");
+        }
     }
 
     #[inline(always)]
-    fn buf(self: Pin<&mut Self>) -> &mut Buffer {
-        self.split().1
+    fn read(self: Pin<&mut Self>) -> Pin<&mut Half<D>> {
+        unsafe { Pin::map_unchecked_mut(self, |this| &mut this.read) }
     }
 
     #[inline(always)]
-    fn split(self: Pin<&mut Self>) -> (Pin<&mut Ring<D>>, &mut Buffer, &mut Op) {
-        unsafe {
-            let this = Pin::get_unchecked_mut(self);
-            (Pin::new_unchecked(&mut this.ring), &mut this.buf, &mut this.active)
-        }
+    fn write(self: Pin<&mut Self>) -> Pin<&mut Half<D>> {
+        unsafe { Pin::map_unchecked_mut(self, |this| &mut this.write) }
     }
 
-    fn confirm_close(self: Pin<&mut Self>) {
-        *self.split().2 = Op::Closed;
+    fn set_state(self: Pin<&mut Self>, state: State) {
+        unsafe { Pin::get_unchecked_mut(self).state = state; }
     }
 }
 
@@ -111,7 +178,7 @@ impl<D: Drive + Clone> Future for Connect<D> {
                 let (connect, result) = ready!(submission.as_mut().poll(ctx));
                 result?;
                 let driver = submission.driver().clone();
-                Poll::Ready(Ok(TcpStream::from_fd(connect.fd, Ring::new(driver))))
+                Poll::Ready(Ok(TcpStream::from_fd(connect.fd, driver)))
             }
             Err(err)        => {
                 let err = err.take().expect("polled Connect future after completion");
@@ -135,55 +202,32 @@ impl<D: Drive> Connect<D> {
 }
 
 impl<D: Drive> AsyncRead for TcpStream<D> {
-    fn poll_read(mut self: Pin<&mut Self>, ctx: &mut Context<'_>, buf: &mut [u8])
+    fn poll_read(self: Pin<&mut Self>, ctx: &mut Context<'_>, buf: &mut [u8])
         -> Poll<io::Result<usize>>
     {
-        let mut inner = ready!(self.as_mut().poll_fill_buf(ctx))?;
-        let len = io::Read::read(&mut inner, buf)?;
-        self.consume(len);
-        Poll::Ready(Ok(len))
+        self.guard_open();
+        let fd = self.fd;
+        self.read().poll_read(ctx, fd, 0, buf)
     }
 }
 
 impl<D: Drive> AsyncBufRead for TcpStream<D> {
-    fn poll_fill_buf(mut self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
-        self.as_mut().guard_op(Op::Read);
+    fn poll_fill_buf(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        self.guard_open();
         let fd = self.fd;
-        let (ring, buf, ..) = self.split();
-        buf.fill_buf(|buf| {
-            let n = ready!(ring.poll(ctx, 1, |sqs| { 
-                let mut sqe = sqs.single().unwrap();
-                unsafe {
-                    sqe.prep_read(fd, buf, 0);
-                }
-                sqe
-            }))?;
-            Poll::Ready(Ok(n as u32))
-        })
+        self.read().poll_fill_buf(ctx, fd, 0)
     }
 
     fn consume(self: Pin<&mut Self>, amt: usize) {
-        self.buf().consume(amt);
+        self.read().consume(amt);
     }
 }
 
 impl<D: Drive> AsyncWrite for TcpStream<D> {
-    fn poll_write(mut self: Pin<&mut Self>, ctx: &mut Context<'_>, slice: &[u8]) -> Poll<io::Result<usize>> {
-        self.as_mut().guard_op(Op::Write);
+    fn poll_write(self: Pin<&mut Self>, ctx: &mut Context<'_>, slice: &[u8]) -> Poll<io::Result<usize>> {
+        self.guard_open();
         let fd = self.fd;
-        let (ring, buf, ..) = self.split();
-        let data = ready!(buf.fill_buf(|mut buf| {
-            Poll::Ready(Ok(io::Write::write(&mut buf, slice)? as u32))
-        }))?;
-        let n = ready!(ring.poll(ctx, 1, |sqs| {
-            let mut sqe = sqs.single().unwrap();
-            unsafe {
-                sqe.prep_write(fd, data, 0);
-            }
-            sqe
-        }))?;
-        buf.clear();
-        Poll::Ready(Ok(n as usize))
+        self.write().poll_write(ctx, fd, 0, slice)
     }
 
     fn poll_flush(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<()>> {
@@ -192,26 +236,46 @@ impl<D: Drive> AsyncWrite for TcpStream<D> {
     }
 
     fn poll_close(mut self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        self.as_mut().guard_op(Op::Close);
+        match self.state {
+            State::Open     => {
+                self.as_mut().read().cancel_pinned();
+                self.as_mut().write().cancel_pinned();
+                self.as_mut().set_state(State::Closing);
+            }
+            State::Closing  => { }
+            State::Closed   => panic!("Attempted to perform IO on a closed stream"),
+        }
         let fd = self.fd;
-        ready!(self.as_mut().ring().poll(ctx, 1, |sqs| {
+        ready!(self.as_mut().write().ring().poll(ctx, 1, |sqs| {
             let mut sqe = sqs.single().unwrap();
             unsafe {
                 sqe.prep_close(fd);
             }
             sqe
         }))?;
-        self.confirm_close();
+        self.set_state(State::Closed);
         Poll::Ready(Ok(()))
     }
 }
 
+impl<D: Drive> AsRawFd for TcpStream<D> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
 impl<D: Drive> Drop for TcpStream<D> {
     fn drop(&mut self) {
-        match self.active {
-            Op::Closed  => { }
-            Op::Nothing => unsafe { libc::close(self.fd); },
-            _           => self.cancel(),
+        if self.state == State::Closed {
+            return;
+        }
+
+        if self.read.is_idle() && self.write.is_idle() {
+            unsafe { libc::close(self.fd); }
+        } else {
+            // The kernel may still be using the file descriptor, so it is leaked.
+            self.read.cancel();
+            self.write.cancel();
         }
     }
 }
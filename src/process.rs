@@ -0,0 +1,314 @@
+//! Spawn child processes, with their standard IO piped through io-uring
+
+use std::ffi::OsStr;
+use std::future;
+use std::io;
+use std::mem;
+use std::os::unix::io::{AsRawFd, IntoRawFd, RawFd};
+use std::os::unix::process::ExitStatusExt;
+use std::path::Path;
+use std::pin::Pin;
+use std::process;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::{Context, Poll};
+
+use futures_io::AsyncBufRead;
+use iou::sqe::PollFlags;
+
+use crate::drive::{Drive, demo::DemoDriver};
+use crate::event::{PollAdd, WaitId};
+use crate::io::{PipeReader, PipeWriter};
+
+pub use std::process::{ExitStatus, Output, Stdio};
+
+// Set once the kernel has failed a waitid event, so that exits are waited for by polling pidfds.
+static NO_WAITID: AtomicBool = AtomicBool::new(false);
+
+/// A builder for child processes, like `std::process::Command`
+///
+/// Standard IO set to [`Stdio::piped`] is exposed on the spawned [`Child`] as pipes which are
+/// read and written with io-uring.
+#[derive(Debug)]
+pub struct Command {
+    inner: process::Command,
+}
+
+/// A child process spawned by [`Command`]
+///
+/// Dropping a `Child` neither kills it nor waits for it, so it should be waited for to release
+/// its resources once it has exited.
+pub struct Child<D: Drive = DemoDriver> {
+    /// The child's standard input, if it was piped.
+    pub stdin: Option<PipeWriter<D>>,
+    /// The child's standard output, if it was piped.
+    pub stdout: Option<PipeReader<D>>,
+    /// The child's standard error, if it was piped.
+    pub stderr: Option<PipeReader<D>>,
+    pid: u32,
+    pidfd: RawFd,
+    status: Option<ExitStatus>,
+    driver: D,
+}
+
+impl Command {
+    /// Construct a command to run `program`, which is searched for in `PATH` if it is not a path.
+    pub fn new(program: impl AsRef<OsStr>) -> Command {
+        Command { inner: process::Command::new(program) }
+    }
+
+    pub fn arg(&mut self, arg: impl AsRef<OsStr>) -> &mut Command {
+        self.inner.arg(arg);
+        self
+    }
+
+    pub fn args<I, S>(&mut self, args: I) -> &mut Command
+        where I: IntoIterator<Item = S>, S: AsRef<OsStr>
+    {
+        self.inner.args(args);
+        self
+    }
+
+    pub fn env(&mut self, key: impl AsRef<OsStr>, val: impl AsRef<OsStr>) -> &mut Command {
+        self.inner.env(key, val);
+        self
+    }
+
+    pub fn envs<I, K, V>(&mut self, vars: I) -> &mut Command
+        where I: IntoIterator<Item = (K, V)>, K: AsRef<OsStr>, V: AsRef<OsStr>
+    {
+        self.inner.envs(vars);
+        self
+    }
+
+    pub fn env_remove(&mut self, key: impl AsRef<OsStr>) -> &mut Command {
+        self.inner.env_remove(key);
+        self
+    }
+
+    pub fn env_clear(&mut self) -> &mut Command {
+        self.inner.env_clear();
+        self
+    }
+
+    pub fn current_dir(&mut self, dir: impl AsRef<Path>) -> &mut Command {
+        self.inner.current_dir(dir);
+        self
+    }
+
+    pub fn stdin(&mut self, cfg: impl Into<Stdio>) -> &mut Command {
+        self.inner.stdin(cfg);
+        self
+    }
+
+    pub fn stdout(&mut self, cfg: impl Into<Stdio>) -> &mut Command {
+        self.inner.stdout(cfg);
+        self
+    }
+
+    pub fn stderr(&mut self, cfg: impl Into<Stdio>) -> &mut Command {
+        self.inner.stderr(cfg);
+        self
+    }
+
+    /// Spawn the command as a child process run on the default driver.
+    pub fn spawn(&mut self) -> io::Result<Child> {
+        self.spawn_on_driver(DemoDriver::default())
+    }
+
+    /// Spawn the command as a child process, with its pipes and its exit run on `driver`.
+    pub fn spawn_on_driver<D: Drive + Clone>(&mut self, driver: D) -> io::Result<Child<D>> {
+        let mut child = self.inner.spawn()?;
+        let pid = child.id();
+        // The child cannot have been reaped yet, so its pid still refers to it.
+        let pidfd = match unsafe { libc::syscall(libc::SYS_pidfd_open, pid, 0) } {
+            -1      => {
+                let err = io::Error::last_os_error();
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(err);
+            }
+            pidfd   => pidfd as RawFd,
+        };
+        let stdin = child.stdin.take()
+            .map(|stdin| PipeWriter::from_fd(stdin.into_raw_fd(), driver.clone()));
+        let stdout = child.stdout.take()
+            .map(|stdout| PipeReader::from_fd(stdout.into_raw_fd(), driver.clone()));
+        let stderr = child.stderr.take()
+            .map(|stderr| PipeReader::from_fd(stderr.into_raw_fd(), driver.clone()));
+        Ok(Child { stdin, stdout, stderr, pid, pidfd, status: None, driver })
+    }
+
+    /// Run the command on the default driver and wait for it to exit, returning its status
+    pub async fn status(&mut self) -> io::Result<ExitStatus> {
+        self.status_on_driver(DemoDriver::default()).await
+    }
+
+    /// Run the command and wait for it to exit, returning its status
+    pub async fn status_on_driver<D: Drive + Clone>(&mut self, driver: D)
+        -> io::Result<ExitStatus>
+    {
+        self.spawn_on_driver(driver)?.wait().await
+    }
+
+    /// Run the command on the default driver and collect its output
+    ///
+    /// See [`output_on_driver`](Command::output_on_driver) for details.
+    pub async fn output(&mut self) -> io::Result<Output> {
+        self.output_on_driver(DemoDriver::default()).await
+    }
+
+    /// Run the command and collect its output
+    ///
+    /// The child's standard output and standard error are always piped and read until they are
+    /// closed, while its standard input is configured as usual.
+    pub async fn output_on_driver<D: Drive + Clone + Unpin>(&mut self, driver: D)
+        -> io::Result<Output>
+    {
+        self.inner.stdout(Stdio::piped()).stderr(Stdio::piped());
+        self.spawn_on_driver(driver)?.wait_with_output().await
+    }
+}
+
+impl From<process::Command> for Command {
+    fn from(inner: process::Command) -> Command {
+        Command { inner }
+    }
+}
+
+impl<D: Drive + Clone> Child<D> {
+    /// The process id of the child.
+    pub fn id(&self) -> u32 {
+        self.pid
+    }
+
+    /// Wait for the child to exit, returning its status
+    ///
+    /// The child's standard input is closed first, so that it is not left waiting for input.
+    /// Its exit is waited for with a waitid event, or on older kernels by polling a pidfd for
+    /// it; either way the child is only reaped once it has exited, so this can be cancelled.
+    pub async fn wait(&mut self) -> io::Result<ExitStatus> {
+        drop(self.stdin.take());
+        loop {
+            if let Some(status) = self.try_wait()? {
+                return Ok(status);
+            }
+            self.exited().await?;
+        }
+    }
+
+    /// Return the child's status if it has exited, without waiting
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        if self.status.is_some() {
+            return Ok(self.status);
+        }
+        let mut info: libc::siginfo_t = unsafe { mem::zeroed() };
+        let options = libc::WEXITED | libc::WNOHANG;
+        if unsafe { libc::waitid(libc::P_PIDFD, self.pidfd as _, &mut info, options) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // If the child has not exited, waitid leaves `info` zeroed.
+        if unsafe { info.si_pid() } != 0 {
+            self.status = Some(exit_status(&info));
+        }
+        Ok(self.status)
+    }
+
+    /// Kill the child with `SIGKILL`
+    ///
+    /// The signal is sent through a pidfd, so it cannot reach another process which has reused
+    /// the child's pid. It is not an error if the child has already exited.
+    pub fn kill(&mut self) -> io::Result<()> {
+        if self.status.is_some() {
+            return Ok(());
+        }
+        let (pidfd, signal, info) = (self.pidfd, libc::SIGKILL, ptr::null::<libc::siginfo_t>());
+        match unsafe { libc::syscall(libc::SYS_pidfd_send_signal, pidfd, signal, info, 0) } {
+            0   => Ok(()),
+            _   => match io::Error::last_os_error() {
+                err if err.raw_os_error() == Some(libc::ESRCH)  => Ok(()),
+                err                                             => Err(err),
+            }
+        }
+    }
+
+    /// Wait for the child to exit, collecting everything it writes to its piped standard output
+    /// and standard error
+    pub async fn wait_with_output(mut self) -> io::Result<Output> where D: Unpin {
+        drop(self.stdin.take());
+        let (mut stdout, mut stderr) = (vec![], vec![]);
+        let (mut out, mut err) = (self.stdout.take(), self.stderr.take());
+        future::poll_fn(|ctx| -> Poll<io::Result<()>> {
+            let out = poll_read_to_end(ctx, &mut out, &mut stdout)?;
+            let err = poll_read_to_end(ctx, &mut err, &mut stderr)?;
+            match (out, err) {
+                (Poll::Ready(()), Poll::Ready(()))  => Poll::Ready(Ok(())),
+                _                                   => Poll::Pending,
+            }
+        }).await?;
+        let status = self.wait().await?;
+        Ok(Output { status, stdout, stderr })
+    }
+
+    // Wait until the child has exited, without reaping it.
+    async fn exited(&mut self) -> io::Result<()> {
+        if !NO_WAITID.load(Ordering::Relaxed) {
+            let options = libc::WEXITED | libc::WNOWAIT;
+            let event = WaitId::new(libc::P_PIDFD, self.pidfd as _, options);
+            match self.driver.clone().submit(event).await.1 {
+                Err(err) if err.raw_os_error() == Some(libc::EINVAL)    => {
+                    NO_WAITID.store(true, Ordering::Relaxed);
+                }
+                result                                                  => return result.map(drop),
+            }
+        }
+        // A pidfd becomes readable once its process has exited.
+        let event = PollAdd { fd: self.pidfd, flags: PollFlags::POLLIN };
+        self.driver.clone().submit(event).await.1.map(drop)
+    }
+}
+
+impl<D: Drive> AsRawFd for Child<D> {
+    /// The pidfd referring to the child.
+    fn as_raw_fd(&self) -> RawFd {
+        self.pidfd
+    }
+}
+
+impl<D: Drive> Drop for Child<D> {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.pidfd); }
+    }
+}
+
+// Read from `reader` into `buf` until it is closed, and then drop it.
+fn poll_read_to_end<R: AsyncBufRead + Unpin>(
+    ctx: &mut Context<'_>,
+    reader: &mut Option<R>,
+    buf: &mut Vec<u8>,
+) -> io::Result<Poll<()>> {
+    while let Some(inner) = reader {
+        let data = match Pin::new(&mut *inner).poll_fill_buf(ctx) {
+            Poll::Ready(result) => result?,
+            Poll::Pending       => return Ok(Poll::Pending),
+        };
+        if data.is_empty() {
+            *reader = None;
+        } else {
+            let n = data.len();
+            buf.extend_from_slice(data);
+            Pin::new(inner).consume(n);
+        }
+    }
+    Ok(Poll::Ready(()))
+}
+
+fn exit_status(info: &libc::siginfo_t) -> ExitStatus {
+    let status = unsafe { info.si_status() };
+    // Convert the status into the form `wait` returns it in.
+    ExitStatus::from_raw(match info.si_code {
+        libc::CLD_EXITED    => (status & 0xff) << 8,
+        libc::CLD_DUMPED    => status | 0x80,
+        _                   => status,
+    })
+}
@@ -17,10 +17,24 @@ pub struct Cancellation {
     data: *mut (),
     metadata: usize,
     drop: unsafe fn(*mut (), usize),
+    complete: unsafe fn(*mut (), usize, u32),
 }
 
+/// An object which can be erased into a `Cancellation`.
+///
+/// # Safety
+///
+/// Implementers must ensure that `drop_raw`, called with the values returned by `into_raw`,
+/// correctly releases the resources of the original object exactly once.
 pub unsafe trait Cancel {
     fn into_raw(self) -> (*mut (), usize);
+
+    /// Drop the object previously erased by `into_raw`.
+    ///
+    /// # Safety
+    ///
+    /// `data` and `metadata` must be the values returned by a call to `into_raw` on this type,
+    /// and this must be called at most once for each such call.
     unsafe fn drop_raw(data: *mut (), metadata: usize);
 }
 
@@ -91,6 +105,29 @@ unsafe impl Cancel for () {
     unsafe fn drop_raw(_: *mut (), _: usize) { }
 }
 
+/// A `Cancel` type which needs to see the flags of each completion of the cancelled event, for
+/// example to recycle a buffer the kernel selected for it.
+///
+/// # Safety
+///
+/// Implementers must ensure that `complete_raw`, called with the values returned by `into_raw`,
+/// does not release the resources `drop_raw` releases.
+pub unsafe trait CancelComplete: Cancel {
+    /// Handle a completion of the cancelled event with these flags.
+    ///
+    /// # Safety
+    ///
+    /// `data` and `metadata` must be the values returned by a call to `into_raw` on this type,
+    /// which have not yet been passed to `drop_raw`.
+    unsafe fn complete_raw(data: *mut (), metadata: usize, flags: u32);
+}
+
+/// A `Cancel` type which only needs the data pointer of its raw representation.
+///
+/// # Safety
+///
+/// Implementers must return `0` as the metadata from `into_raw`, and must not depend on the
+/// metadata in `drop_raw`.
 pub unsafe trait CancelNarrow: Cancel { }
 
 unsafe impl<T> CancelNarrow for Box<T> { }
@@ -113,10 +150,25 @@ unsafe impl<T: CancelNarrow, U: CancelNarrow> Cancel for (T, U) {
 impl Cancellation {
     fn new<T: Cancel>(object: T) -> Cancellation {
         let (data, metadata) = object.into_raw();
-        Cancellation { data, metadata, drop: T::drop_raw }
+        Cancellation { data, metadata, drop: T::drop_raw, complete: ignore_completion }
+    }
+
+    /// Construct a cancellation which is passed the flags of each completion of the cancelled
+    /// event before it is dropped.
+    pub fn with_completions<T: CancelComplete>(object: T) -> Cancellation {
+        let (data, metadata) = object.into_raw();
+        Cancellation { data, metadata, drop: T::drop_raw, complete: T::complete_raw }
+    }
+
+    pub(crate) fn complete(&mut self, flags: u32) {
+        unsafe {
+            (self.complete)(self.data, self.metadata, flags)
+        }
     }
 }
 
+unsafe fn ignore_completion(_: *mut (), _: usize, _: u32) { }
+
 impl<T: Cancel> From<T> for Cancellation {
     fn from(object: T) -> Cancellation {
         Cancellation::new(object)
@@ -131,7 +183,7 @@ impl<T> From<Option<T>> for Cancellation where Cancellation: From<T> {
 
 impl From<Buffer> for Cancellation {
     fn from(buffer: Buffer) -> Cancellation {
-        Cancellation::from(buffer.into_boxed_slice())
+        Cancellation::from(buffer.into_data())
     }
 }
 
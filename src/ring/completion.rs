@@ -1,120 +1,308 @@
+use std::cell::UnsafeCell;
+use std::collections::VecDeque;
 use std::io;
-use std::mem::{self, ManuallyDrop};
+use std::ptr;
+use std::hint;
+use std::sync::atomic::{AtomicPtr, AtomicU32, AtomicUsize, Ordering};
 use std::task::Waker;
 
-use parking_lot::Mutex;
-
 use crate::ring::Cancellation;
 use iou::CQE;
 
-use State::*;
+use super::slab::SLOTS;
 
-/// A completion tracks an event that has been submitted to io-uring. It is a pointer to a heap
-/// allocated object which represents the state of the event's completion. Ownership of this object
-/// is shared between the Completion type and the io-uring instance (the address of the object is
-/// passed as a user_data field with the event's SQE).
+/// A completion tracks an event that has been submitted to io-uring. It is a handle to a slot
+/// which represents the state of the event's completion. Ownership of this slot is shared between
+/// the Completion type and the io-uring instance (the index of the slot is passed as the user_data
+/// field with the event's SQE).
 ///
 /// Therefore, it requires a fair amout of unsafe code and synchronization to properly manage the
 /// lifecycle of this object. That code is encapsulated here inside a safe API for the rest of
 /// ringbahn to use.
 ///
+/// The slot is synchronized without locks: the completion and the io-uring instance each hold a
+/// reference to it, counted in its state word alongside flags recording whether the event has
+/// completed or been cancelled, and whether the waker may be read. Whichever side releases its
+/// reference last returns the slot to the slab it was allocated from.
+///
 /// This API is not publicly visible outside of this crate. (The Completion type in the public API
 /// is an opaque wrapper aroud this type). End users do not need to understand the completion API.
 pub struct Completion {
-    state: ManuallyDrop<Box<Mutex<State>>>,
+    slot: &'static Slot,
 }
 
-enum State {
-    Submitted(Waker),
-    Completed(io::Result<u32>),
-    Cancelled(Cancellation),
-    Empty,
+/// The state of an event's completion, allocated from the slab.
+pub(super) struct Slot {
+    state: AtomicUsize,
+    // Only written by the completion while WAKER_SET is not set, and only read by the io-uring
+    // side while it is.
+    waker: UnsafeCell<Option<Waker>>,
+    // Written by the io-uring side before it sets COMPLETE.
+    result: UnsafeCell<Option<(io::Result<u32>, u32)>>,
+    // The results of a multishot event which will complete again, pushed by the io-uring side.
+    streamed: AtomicPtr<Streamed>,
+    // Only accessed by the completion, while it is alive.
+    queue: UnsafeCell<VecDeque<(io::Result<u32>, u32)>>,
+    // Written by the completion before it releases its reference.
+    cancellation: UnsafeCell<Option<Cancellation>>,
+    generation: AtomicU32,
+    pub(super) index: u32,
+    pub(super) next_free: AtomicU32,
 }
 
+unsafe impl Send for Slot { }
+unsafe impl Sync for Slot { }
+
+struct Streamed {
+    result: (io::Result<u32>, u32),
+    next: *mut Streamed,
+}
+
+// The final result has been written to the slot.
+const COMPLETE: usize = 1 << 0;
+// The completion has been cancelled, so there is no task to wake.
+const CANCELLED: usize = 1 << 1;
+// The waker has been written, and the io-uring side may wake it.
+const WAKER_SET: usize = 1 << 2;
+// The io-uring side is waking the waker for a multishot result, so it cannot be replaced.
+const WAKING: usize = 1 << 3;
+// One reference to the slot; both the completion and the io-uring instance start with one.
+const REF: usize = 1 << 4;
+
+/// Set on the CQEs of a multishot event when the kernel will post more CQEs for the same event.
+pub const IORING_CQE_F_MORE: u32 = 1 << 1;
+
 impl Completion {
     /// Create a new completion for an event being prepared. When the event is completed by
     /// io-uring, the waker this completion holds will be awoken.
-    pub fn new(waker: Waker) -> Completion {
-        Completion {
-            state: ManuallyDrop::new(Box::new(Mutex::new(Submitted(waker)))),
-        }
+    pub fn new(waker: &Waker) -> Completion {
+        let slot = SLOTS.alloc();
+        // Safety: the slot is not shared with the io-uring side until the event is submitted.
+        unsafe { *slot.waker.get() = Some(waker.clone()); }
+        let generation = slot.generation.load(Ordering::Relaxed);
+        slot.generation.store(generation.wrapping_add(1), Ordering::Relaxed);
+        slot.state.store((2 * REF) | WAKER_SET, Ordering::Relaxed);
+        Completion { slot }
     }
 
     /// Get the address of this completion, so that it can set as the user_data field of the SQE
     /// being prepared.
+    ///
+    /// The address includes the generation of the slot, so that an address which outlives its
+    /// event (for example, in order to cancel it) does not refer to a later event using the same
+    /// slot.
     pub fn addr(&self) -> u64 {
-        &**self.state as *const Mutex<State> as usize as u64
+        let generation = self.slot.generation.load(Ordering::Relaxed) as u64;
+        generation << 32 | (self.slot.index as u64 + 1)
     }
 
-    /// Check if the completion has completed. If it has, the result of the completion will be
-    /// returned and the completion will be deallocated. If it has not been completed, the waker
-    /// field will be updated to the new waker if the old waker would not wake the same task.
-    pub fn check(self, waker: &Waker) -> Result<io::Result<u32>, Completion> {
-        let mut state = self.state.lock();
-        match mem::replace(&mut *state, State::Empty) {
-            Submitted(old_waker)    => {
-                let waker = if old_waker.will_wake(waker) { old_waker } else { waker.clone() };
-                *state = Submitted(waker);
-                drop(state);
-                Err(self)
-            }
-            Completed(result)       => {
-                drop(state);
-                drop(ManuallyDrop::into_inner(self.state));
-                Ok(result)
-            }
-            _                       => unreachable!()
+    /// Check if the completion has completed. If it has, the result and flags of the completion
+    /// will be returned. If it has not been completed, the waker will be updated to the new waker
+    /// if the old waker would not wake the same task.
+    ///
+    /// Multishot events complete more than once. The completion is returned alongside each result
+    /// until the final one, after which the completion is deallocated.
+    pub fn check(self, waker: &Waker) -> (Option<(io::Result<u32>, u32)>, Option<Completion>) {
+        let slot = self.slot;
+        // Safety: only the completion accesses the queue.
+        let queue = unsafe { &mut *slot.queue.get() };
+
+        if queue.is_empty() {
+            slot.take_streamed(queue);
+        }
+        if let Some(next) = queue.pop_front() {
+            return (Some(next), Some(self));
+        }
+
+        let state = slot.state.load(Ordering::Acquire);
+        if state & COMPLETE == 0 && slot.register(waker, state) & COMPLETE == 0 {
+            // Results may have been streamed while the waker was being replaced, in which case
+            // the task would not have been awoken.
+            slot.take_streamed(queue);
+            return (queue.pop_front(), Some(self));
+        }
+
+        // Results streamed before the final result must be observed first.
+        slot.take_streamed(queue);
+        if let Some(next) = queue.pop_front() {
+            return (Some(next), Some(self));
         }
+
+        // Safety: the result is written before COMPLETE is set, and not accessed by the io-uring
+        // side after that.
+        let result = unsafe { (*slot.result.get()).take().unwrap() };
+        slot.release(REF);
+        (Some(result), None)
     }
 
     /// Cancel interest in this completion. The Cancellation callback will be stored to clean up
     /// resources shared with the kernel when the event completes.
     pub fn cancel(self, callback: Cancellation) {
-        let mut state = self.state.lock();
-        match &*state {
-            Submitted(_)    => {
-                *state = Cancelled(callback);
-                drop(state);
+        let slot = self.slot;
+        unsafe { *slot.cancellation.get() = Some(callback); }
+        // Set CANCELLED and release this reference in one operation. CANCELLED cannot already be
+        // set, so subtracting the difference is the same as setting it and subtracting REF.
+        slot.release(REF - CANCELLED);
+    }
+}
+
+impl Slot {
+    pub(super) fn new(index: u32) -> Slot {
+        Slot {
+            state: AtomicUsize::new(0),
+            waker: UnsafeCell::new(None),
+            result: UnsafeCell::new(None),
+            streamed: AtomicPtr::new(ptr::null_mut()),
+            queue: UnsafeCell::new(VecDeque::new()),
+            cancellation: UnsafeCell::new(None),
+            generation: AtomicU32::new(0),
+            next_free: AtomicU32::new(0),
+            index,
+        }
+    }
+
+    // Replace the waker if it would not wake the same task as `waker`, returning the state of the
+    // slot after it has been replaced. If the event completes while the waker is being replaced,
+    // the new waker may not be stored.
+    fn register(&self, waker: &Waker, state: usize) -> usize {
+        if state & WAKER_SET != 0 {
+            // Safety: the io-uring side only reads the waker while WAKER_SET is set.
+            if let Some(old) = unsafe { &*self.waker.get() } {
+                if old.will_wake(waker) {
+                    return state;
+                }
             }
-            Completed(_)    => {
-                drop(callback);
-                drop(state);
-                drop(ManuallyDrop::into_inner(self.state));
+        }
+
+        let state = self.state.fetch_and(!WAKER_SET, Ordering::Acquire);
+        if state & COMPLETE != 0 {
+            // The io-uring side may be waking the old waker, and there is nothing left to wait
+            // for anyway.
+            return state;
+        }
+        let mut state = state;
+        while state & WAKING != 0 {
+            hint::spin_loop();
+            state = self.state.load(Ordering::Acquire);
+        }
+
+        unsafe { *self.waker.get() = Some(waker.clone()); }
+        self.state.fetch_or(WAKER_SET, Ordering::AcqRel) | WAKER_SET
+    }
+
+    fn complete(&'static self, result: io::Result<u32>, flags: u32) {
+        if flags & IORING_CQE_F_MORE != 0 {
+            // The io-uring side keeps its reference, because the event will complete again.
+            if self.state.load(Ordering::Acquire) & CANCELLED != 0 {
+                // Safety: the cancellation is written before CANCELLED is set, and the slot
+                // cannot be freed while the io-uring side holds its reference.
+                if let Some(cancellation) = unsafe { &mut *self.cancellation.get() } {
+                    cancellation.complete(flags);
+                }
+            } else {
+                self.push_streamed((result, flags));
+                let state = self.state.fetch_or(WAKING, Ordering::AcqRel);
+                if state & (WAKER_SET | CANCELLED) == WAKER_SET {
+                    self.wake();
+                }
+                self.state.fetch_and(!WAKING, Ordering::Release);
             }
-            _               => unreachable!()
+            return;
         }
+
+        unsafe { *self.result.get() = Some((result, flags)); }
+        let state = self.state.fetch_or(COMPLETE, Ordering::AcqRel);
+        if state & (WAKER_SET | CANCELLED) == WAKER_SET {
+            // The completion will not replace the waker once COMPLETE is set.
+            self.wake();
+        }
+        self.release(REF);
     }
 
-    fn complete(self, result: io::Result<u32>) {
-        let mut state = self.state.lock();
-        match mem::replace(&mut *state, State::Empty) {
-            Submitted(waker)    => {
-                *state = Completed(result);
-                waker.wake();
+    fn wake(&self) {
+        if let Some(waker) = unsafe { &*self.waker.get() } {
+            waker.wake_by_ref();
+        }
+    }
+
+    fn push_streamed(&self, result: (io::Result<u32>, u32)) {
+        let node = Box::into_raw(Box::new(Streamed { result, next: ptr::null_mut() }));
+        let mut head = self.streamed.load(Ordering::Relaxed);
+        loop {
+            unsafe { (*node).next = head; }
+            match self.streamed.compare_exchange_weak(head, node, Ordering::Release, Ordering::Relaxed) {
+                Ok(_)       => return,
+                Err(actual) => head = actual,
             }
-            Cancelled(callback) => {
-                drop(callback);
-                drop(state);
-                drop(ManuallyDrop::into_inner(self.state));
+        }
+    }
+
+    // Move every streamed result into the queue, in the order they were pushed.
+    fn take_streamed(&self, queue: &mut VecDeque<(io::Result<u32>, u32)>) {
+        if self.streamed.load(Ordering::Relaxed).is_null() {
+            return;
+        }
+
+        let mut node = self.streamed.swap(ptr::null_mut(), Ordering::Acquire);
+        let start = queue.len();
+        while !node.is_null() {
+            let streamed = unsafe { Box::from_raw(node) };
+            node = streamed.next;
+            queue.push_back(streamed.result);
+        }
+        queue.make_contiguous()[start..].reverse();
+    }
+
+    // Drop one reference (subtracting `delta` from the state), freeing the slot if it was the
+    // last reference.
+    fn release(&'static self, delta: usize) {
+        let prev = self.state.fetch_sub(delta, Ordering::AcqRel);
+        if prev / REF == 1 {
+            self.free();
+        }
+    }
+
+    fn free(&'static self) {
+        unsafe {
+            let queue = &mut *self.queue.get();
+            self.take_streamed(queue);
+            // Results which were never observed are passed to the cancellation, which may need
+            // to release what the kernel attached to them.
+            let result = (*self.result.get()).take();
+            if let Some(cancellation) = &mut *self.cancellation.get() {
+                for (_, flags) in queue.drain(..).chain(result) {
+                    cancellation.complete(flags);
+                }
             }
-            _                   => unreachable!()
+            queue.clear();
+            *self.cancellation.get() = None;
+            *self.waker.get() = None;
         }
+        SLOTS.free(self);
     }
 }
 
 pub fn complete(cqe: CQE) {
-    unsafe {
-        let result = cqe.result();
-        let user_data = cqe.user_data();
-        // iou should never raise LIBURING_UDATA_TIMEOUTs, this is just to catch bugs in iou
-        debug_assert!(user_data != uring_sys::LIBURING_UDATA_TIMEOUT);
-        let state = user_data as *mut Mutex<State>;
-
-        if !state.is_null() {
-            let completion = Completion {
-                state: ManuallyDrop::new(Box::from_raw(state))
-            };
-            completion.complete(result);
-        }
+    complete_raw(cqe.user_data(), cqe.raw_result(), cqe.raw_flags())
+}
+
+/// Complete an event from the raw fields of its CQE.
+///
+/// iou's `CQE` type discards any flags it does not know about, including the id of the buffer
+/// the kernel selected for an event using `IOSQE_BUFFER_SELECT`. Drivers which read the completion
+/// queue directly should call this with the flags exactly as the kernel reported them.
+pub fn complete_raw(user_data: u64, res: i32, flags: u32) {
+    let result = match res {
+        res if res < 0  => Err(io::Error::from_raw_os_error(-res)),
+        res             => Ok(res as u32),
     };
+    // iou should never raise LIBURING_UDATA_TIMEOUTs, this is just to catch bugs in iou
+    debug_assert!(user_data != uring_sys::LIBURING_UDATA_TIMEOUT);
+
+    let index = user_data as u32;
+    if index != 0 {
+        let slot = SLOTS.get(index - 1).expect("completed an event ringbahn did not submit");
+        debug_assert_eq!(slot.generation.load(Ordering::Relaxed), (user_data >> 32) as u32);
+        slot.complete(result, flags);
+    }
 }
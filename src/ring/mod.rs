@@ -1,17 +1,19 @@
 mod cancellation;
 pub(crate) mod completion;
+mod slab;
 
 use std::io;
 use std::mem;
 use std::pin::Pin;
-use std::task::{Context, Poll};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
 
 use futures_core::ready;
 use iou::{SQE, SQEs};
 
 use crate::drive::{self, Drive};
 
-pub use cancellation::{Cancellation, Cancel, CancelNarrow};
+pub use cancellation::{Cancellation, Cancel, CancelComplete, CancelNarrow};
 pub(crate) use completion::Completion;
 
 use State::*;
@@ -68,6 +70,14 @@ impl<D: Drive> Ring<D> {
         &self.driver
     }
 
+    /// Returns true if this ring has no event in flight.
+    ///
+    /// A ring whose last event was cancelled is not idle, because the kernel may still be running
+    /// that event.
+    pub fn is_idle(&self) -> bool {
+        matches!(self.state, Inert)
+    }
+
     /// Poll the ring state machine.
     ///
     /// This accepts a callback, `prepare`, which prepares an event to be submitted to io-uring.
@@ -76,11 +86,26 @@ impl<D: Drive> Ring<D> {
     /// not prepare any additional events.
     #[inline]
     pub fn poll(
-        mut self: Pin<&mut Self>,
+        self: Pin<&mut Self>,
         ctx: &mut Context<'_>,
         count: u32,
         prepare: impl for<'sq> FnOnce(&mut SQEs<'sq>) -> SQE<'sq>,
     ) -> Poll<io::Result<u32>> {
+        self.poll_with_flags(ctx, count, prepare).map(|(result, _)| result)
+    }
+
+    /// Poll the ring state machine, returning the flags of the completion as well as its result.
+    ///
+    /// This behaves exactly like `poll`, but also returns the flags the kernel set on the CQE,
+    /// which some events (such as reads using buffer selection) use to report additional
+    /// information.
+    #[inline]
+    pub fn poll_with_flags(
+        mut self: Pin<&mut Self>,
+        ctx: &mut Context<'_>,
+        count: u32,
+        prepare: impl for<'sq> FnOnce(&mut SQEs<'sq>) -> SQE<'sq>,
+    ) -> Poll<(io::Result<u32>, u32)> {
         match self.state {
             Inert | Cancelled(_) => {
                 ready!(self.as_mut().poll_prepare(ctx, count, prepare));
@@ -145,34 +170,27 @@ impl<D: Drive> Ring<D> {
     }
 
     #[inline(always)]
-    fn poll_complete(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<u32>> {
+    fn poll_complete(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<(io::Result<u32>, u32)> {
         let (_, state) = self.split();
-        match mem::replace(state, Lost) {
-            Prepared(completion)    => {
-                match completion.check(ctx.waker()) {
-                    Ok(result)      => {
-                        *state = Inert;
-                        Poll::Ready(result)
-                    }
-                    Err(completion) => {
-                        *state = Prepared(completion);
-                        Poll::Pending
-                    }
-                }
+        let (completion, submitted) = match mem::replace(state, Lost) {
+            Prepared(completion)    => (completion, false),
+            Submitted(completion)   => (completion, true),
+            _                       => unreachable!(),
+        };
+        match completion.check(ctx.waker()) {
+            (Some(result), None)                => {
+                *state = Inert;
+                Poll::Ready(result)
             }
-            Submitted(completion)   => {
-                match completion.check(ctx.waker()) {
-                    Ok(result)      => {
-                        *state = Inert;
-                        Poll::Ready(result)
-                    }
-                    Err(completion) => {
-                        *state = Submitted(completion);
-                        Poll::Pending
-                    }
-                }
+            (Some(result), Some(completion))    => {
+                *state = Submitted(completion);
+                Poll::Ready(result)
             }
-            _                       => unreachable!(),
+            (None, Some(completion))            => {
+                *state = if submitted { Submitted(completion) } else { Prepared(completion) };
+                Poll::Pending
+            }
+            (None, None)                        => unreachable!(),
         }
     }
 
@@ -192,6 +210,35 @@ impl<D: Drive> Ring<D> {
         self.split().1.cancel(cancellation);
     }
 
+    /// Cancel any ongoing IO, and submit an async cancel for it to the kernel immediately.
+    ///
+    /// `cancel` only cancels the event in the kernel once the ring prepares its next event, which
+    /// is enough for events which complete on their own. Events like a multishot recv stay armed
+    /// until they are cancelled, so rings running them should use this when they are dropped. If
+    /// the driver cannot prepare another event right away, the event is left running.
+    pub fn cancel_now(self: Pin<&mut Self>, cancellation: Cancellation) {
+        let (mut driver, state) = self.split();
+        state.cancel(cancellation);
+        let prev = match *state {
+            Cancelled(prev) => prev,
+            _               => return,
+        };
+
+        let waker = Waker::from(Arc::new(Noop));
+        let mut ctx = Context::from_waker(&waker);
+        let prepared = driver.as_mut().poll_prepare(&mut ctx, 1, |mut sqs, ctx| {
+            let mut sqe = sqs.single().unwrap();
+            unsafe { sqe.prep_cancel(prev, 0); }
+            drive::Completion::new(sqe, sqs, ctx)
+        });
+        if let Poll::Ready(completion) = prepared {
+            *state = Inert;
+            // Nobody waits for the cancel event, and it holds no resources.
+            completion.real.cancel(Cancellation::from(()));
+            let _ = driver.poll_submit(&mut ctx);
+        }
+    }
+
     fn split(self: Pin<&mut Self>) -> (Pin<&mut D>, &mut State) {
         unsafe {
             let this = Pin::get_unchecked_mut(self);
@@ -213,3 +260,9 @@ impl State {
         }
     }
 }
+
+struct Noop;
+
+impl Wake for Noop {
+    fn wake(self: Arc<Self>) { }
+}
@@ -0,0 +1,99 @@
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, AtomicU64, AtomicUsize, Ordering};
+
+use super::completion::Slot;
+
+const CHUNK_SIZE: usize = 256;
+const MAX_CHUNKS: usize = 16384;
+
+/// The slab all completion slots are allocated from.
+pub(super) static SLOTS: Slab = Slab::new();
+
+/// A lock-free allocator for completion slots.
+///
+/// Slots are allocated in chunks which are never deallocated, so a slot is always safe to read
+/// from once it has been handed out, even after it has been freed. Free slots are kept in an
+/// intrusive stack; the head of the stack is tagged with a counter which is incremented on every
+/// update, so that a slot being freed and reallocated during a pop cannot corrupt the stack.
+pub(super) struct Slab {
+    chunks: [AtomicPtr<Slot>; MAX_CHUNKS],
+    len: AtomicUsize,
+    // The low 32 bits are the index of the first free slot plus one, or zero if there are no
+    // free slots. The high 32 bits are the tag.
+    head: AtomicU64,
+}
+
+impl Slab {
+    const fn new() -> Slab {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const EMPTY: AtomicPtr<Slot> = AtomicPtr::new(ptr::null_mut());
+        Slab {
+            chunks: [EMPTY; MAX_CHUNKS],
+            len: AtomicUsize::new(0),
+            head: AtomicU64::new(0),
+        }
+    }
+
+    /// Take a free slot, allocating a new chunk of slots if there are none.
+    pub fn alloc(&self) -> &'static Slot {
+        let mut head = self.head.load(Ordering::Acquire);
+        loop {
+            let first = head as u32;
+            if first == 0 {
+                return self.grow();
+            }
+
+            let slot = self.get(first - 1).unwrap();
+            let next = slot.next_free.load(Ordering::Relaxed);
+            let new = tagged(head, next);
+            match self.head.compare_exchange_weak(head, new, Ordering::Acquire, Ordering::Acquire) {
+                Ok(_)       => return slot,
+                Err(actual) => head = actual,
+            }
+        }
+    }
+
+    /// Return a slot to the free stack.
+    pub fn free(&self, slot: &'static Slot) {
+        let mut head = self.head.load(Ordering::Relaxed);
+        loop {
+            slot.next_free.store(head as u32, Ordering::Relaxed);
+            let new = tagged(head, slot.index + 1);
+            match self.head.compare_exchange_weak(head, new, Ordering::Release, Ordering::Relaxed) {
+                Ok(_)       => return,
+                Err(actual) => head = actual,
+            }
+        }
+    }
+
+    /// Look up a slot by its index, if it has been allocated.
+    pub fn get(&self, index: u32) -> Option<&'static Slot> {
+        let index = index as usize;
+        let chunk = self.chunks.get(index / CHUNK_SIZE)?.load(Ordering::Acquire);
+        if chunk.is_null() {
+            return None;
+        }
+        unsafe { Some(&*chunk.add(index % CHUNK_SIZE)) }
+    }
+
+    fn grow(&self) -> &'static Slot {
+        let n = self.len.fetch_add(1, Ordering::Relaxed);
+        assert!(n < MAX_CHUNKS, "too many io-uring events in flight");
+
+        let base = (n * CHUNK_SIZE) as u32;
+        let chunk: Box<[Slot]> = (0..CHUNK_SIZE as u32).map(|i| Slot::new(base + i)).collect();
+        let chunk: &'static [Slot] = Box::leak(chunk);
+        self.chunks[n].store(chunk.as_ptr() as *mut Slot, Ordering::Release);
+
+        let (first, rest) = chunk.split_first().unwrap();
+        for slot in rest {
+            self.free(slot);
+        }
+        first
+    }
+}
+
+fn tagged(head: u64, first: u32) -> u64 {
+    let tag = (head >> 32).wrapping_add(1);
+    tag << 32 | first as u64
+}
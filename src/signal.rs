@@ -0,0 +1,278 @@
+//! Receive signals through a signalfd read with io-uring
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::mem;
+use std::pin::Pin;
+use std::ptr;
+use std::sync::{Arc, Weak};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread;
+
+use futures_core::Stream;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+
+use crate::drive::{Drive, demo::DemoDriver};
+use crate::event::Read;
+
+const RECORD_LEN: usize = mem::size_of::<libc::signalfd_siginfo>();
+// The number of records read from the signalfd at once.
+const RECORDS: usize = 16;
+
+type ReadFuture = Pin<Box<dyn Future<Output = (Read, io::Result<u32>)> + Send>>;
+
+// The signalfd shared by every subscriber, which receives the union of their signals.
+struct Registry {
+    fd: libc::c_int,
+    mask: libc::sigset_t,
+    subscribers: Vec<Weak<Subscriber>>,
+}
+
+static REGISTRY: Lazy<Mutex<Registry>> = Lazy::new(|| {
+    let mut mask = unsafe { mem::zeroed() };
+    unsafe { libc::sigemptyset(&mut mask); }
+    Mutex::new(Registry { fd: -1, mask, subscribers: vec![] })
+});
+
+// The read of the signalfd in flight, which is driven by whichever subscriber polls it. It is
+// always locked before the registry.
+static READ: Lazy<Mutex<Option<ReadFuture>>> = Lazy::new(|| Mutex::new(None));
+
+// The read wakes every subscriber when it completes, so that it is driven on even if the
+// subscriber which last polled it has gone away.
+static WAKER: Lazy<Waker> = Lazy::new(|| Waker::from(Arc::new(WakeSubscribers)));
+
+/// A stream of the signals a subscriber has asked for, created by [`signals`]
+///
+/// The signals are blocked in the thread which subscribed to them, and received through a
+/// signalfd shared by every subscriber in the process. Each subscriber to a signal receives each
+/// instance of it which arrives while it is subscribed, though like any pending signal, several
+/// instances of a standard signal which arrive before it is read are merged into one.
+///
+/// Only the subscribing thread and the threads it spawns afterwards block the signals, so they
+/// should be subscribed to before any other threads are spawned; otherwise a signal sent to the
+/// process may be delivered to another thread instead. The threads this crate starts itself block
+/// every signal, so they never take a signal meant for the signalfd.
+pub struct Signals<D: Drive = DemoDriver> {
+    subscriber: Arc<Subscriber>,
+    driver: D,
+}
+
+struct Subscriber {
+    mask: libc::sigset_t,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    queue: VecDeque<SignalInfo>,
+    waker: Option<Waker>,
+}
+
+/// A signal received by [`Signals`], decoded from a `signalfd_siginfo` record
+#[derive(Copy, Clone)]
+pub struct SignalInfo {
+    info: libc::signalfd_siginfo,
+}
+
+/// Subscribe to `signals` using the default driver
+pub fn signals(signals: &[libc::c_int]) -> io::Result<Signals> {
+    signals_on_driver(signals, DemoDriver::default())
+}
+
+/// Subscribe to `signals`, which are read on `driver`
+///
+/// This blocks the signals in the calling thread. `SIGKILL` and `SIGSTOP` cannot be received, and
+/// return an `InvalidInput` error like any other invalid signal.
+pub fn signals_on_driver<D: Drive + Clone>(signals: &[libc::c_int], driver: D)
+    -> io::Result<Signals<D>>
+{
+    let mut mask = unsafe { mem::zeroed() };
+    unsafe { libc::sigemptyset(&mut mask); }
+    for &signal in signals {
+        if signal == libc::SIGKILL || signal == libc::SIGSTOP
+            || unsafe { libc::sigaddset(&mut mask, signal) } < 0
+        {
+            let msg = format!("signal {} cannot be received", signal);
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+        }
+    }
+    match unsafe { libc::pthread_sigmask(libc::SIG_BLOCK, &mask, ptr::null_mut()) } {
+        0       => { }
+        errno   => return Err(io::Error::from_raw_os_error(errno)),
+    }
+
+    let mut registry = REGISTRY.lock();
+    let mut all = registry.mask;
+    for &signal in signals {
+        unsafe { libc::sigaddset(&mut all, signal); }
+    }
+    // Changing the mask of an existing signalfd does not disturb a read in flight on it.
+    let fd = unsafe { libc::signalfd(registry.fd, &all, libc::SFD_CLOEXEC) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    registry.fd = fd;
+    registry.mask = all;
+    let subscriber = Arc::new(Subscriber { mask, state: Mutex::new(State::default()) });
+    registry.subscribers.retain(|subscriber| subscriber.strong_count() > 0);
+    registry.subscribers.push(Arc::downgrade(&subscriber));
+    Ok(Signals { subscriber, driver })
+}
+
+impl<D: Drive + Clone + Send + 'static> Stream for Signals<D> {
+    type Item = io::Result<SignalInfo>;
+
+    fn poll_next(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            {
+                let mut state = self.subscriber.state.lock();
+                if let Some(info) = state.queue.pop_front() {
+                    return Poll::Ready(Some(Ok(info)));
+                }
+                state.waker = Some(ctx.waker().clone());
+            }
+
+            let (event, result) = {
+                let mut read = READ.lock();
+                let submission = read.get_or_insert_with(|| {
+                    let fd = REGISTRY.lock().fd;
+                    let buf = vec![0; RECORD_LEN * RECORDS].into_boxed_slice();
+                    Box::pin(self.driver.clone().submit(Read { fd, buf, offset: 0 }))
+                });
+                match submission.as_mut().poll(&mut Context::from_waker(&WAKER)) {
+                    Poll::Ready(output) => {
+                        *read = None;
+                        output
+                    }
+                    Poll::Pending       => return Poll::Pending,
+                }
+            };
+            match result {
+                Ok(n)   => dispatch(&event.buf[..n as usize]),
+                Err(e)  => return Poll::Ready(Some(Err(e))),
+            }
+        }
+    }
+}
+
+// Queue each record for every subscriber to its signal, and wake them.
+fn dispatch(records: &[u8]) {
+    let mut wakers = vec![];
+    {
+        let registry = REGISTRY.lock();
+        for record in records.chunks_exact(RECORD_LEN) {
+            let info = unsafe { ptr::read_unaligned(record.as_ptr().cast()) };
+            let info = SignalInfo { info };
+            for subscriber in registry.subscribers.iter().filter_map(Weak::upgrade) {
+                if unsafe { libc::sigismember(&subscriber.mask, info.signal()) } == 1 {
+                    let mut state = subscriber.state.lock();
+                    state.queue.push_back(info);
+                    wakers.extend(state.waker.take());
+                }
+            }
+        }
+    }
+    wakers.into_iter().for_each(Waker::wake);
+}
+
+struct WakeSubscribers;
+
+impl Wake for WakeSubscribers {
+    fn wake(self: Arc<Self>) {
+        let wakers: Vec<Waker> = REGISTRY.lock().subscribers.iter()
+            .filter_map(Weak::upgrade)
+            .filter_map(|subscriber| subscriber.state.lock().waker.take())
+            .collect();
+        wakers.into_iter().for_each(Waker::wake);
+    }
+}
+
+/// Spawn a thread with signals sent to the process blocked, so that they are only delivered to
+/// the threads of the program, and through the signalfd.
+pub(crate) fn spawn_thread<F, T>(f: F) -> thread::JoinHandle<T> where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let mut all = unsafe { mem::zeroed() };
+    let mut old = unsafe { mem::zeroed() };
+    unsafe {
+        libc::sigfillset(&mut all);
+        // Faults are sent to the thread which caused them, and are left unblocked so that their
+        // handlers, such as the one which reports stack overflows, still run.
+        for &signal in &[libc::SIGSEGV, libc::SIGBUS, libc::SIGILL, libc::SIGFPE] {
+            libc::sigdelset(&mut all, signal);
+        }
+        libc::pthread_sigmask(libc::SIG_SETMASK, &all, &mut old);
+    }
+    // The new thread inherits the mask of the thread which spawns it.
+    let thread = thread::Builder::new().spawn(f);
+    unsafe { libc::pthread_sigmask(libc::SIG_SETMASK, &old, ptr::null_mut()); }
+    thread.expect("failed to spawn thread")
+}
+
+impl<D: Drive> Drop for Signals<D> {
+    fn drop(&mut self) {
+        let mut read = READ.lock();
+        let mut registry = REGISTRY.lock();
+        let this = Arc::as_ptr(&self.subscriber);
+        registry.subscribers.retain(|subscriber| {
+            subscriber.strong_count() > 0 && subscriber.as_ptr() != this
+        });
+        // With no subscribers left, the read is cancelled, so that signals which arrive before
+        // the next subscriber stay blocked and pending rather than being read and discarded.
+        // Records the kernel has already dequeued for the read before it is cancelled are lost.
+        if registry.subscribers.is_empty() {
+            drop(registry);
+            drop(read.take());
+        }
+    }
+}
+
+impl SignalInfo {
+    /// The number of the signal.
+    pub fn signal(&self) -> libc::c_int {
+        self.info.ssi_signo as libc::c_int
+    }
+
+    /// The signal code, such as `SI_USER` for a signal sent by `kill` or `CLD_EXITED` for a
+    /// `SIGCHLD` sent when a child exits.
+    pub fn code(&self) -> libc::c_int {
+        self.info.ssi_code
+    }
+
+    /// The process id of the sender, or of the child for `SIGCHLD`.
+    pub fn pid(&self) -> u32 {
+        self.info.ssi_pid
+    }
+
+    /// The real user id of the sender, or of the child for `SIGCHLD`.
+    pub fn uid(&self) -> u32 {
+        self.info.ssi_uid
+    }
+
+    /// The exit status or signal of the child for `SIGCHLD`.
+    pub fn status(&self) -> libc::c_int {
+        self.info.ssi_status
+    }
+
+    /// The raw record read from the signalfd.
+    pub fn raw(&self) -> &libc::signalfd_siginfo {
+        &self.info
+    }
+}
+
+impl fmt::Debug for SignalInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SignalInfo")
+            .field("signal", &self.signal())
+            .field("code", &self.code())
+            .field("pid", &self.pid())
+            .field("uid", &self.uid())
+            .field("status", &self.status())
+            .finish()
+    }
+}
@@ -55,7 +55,11 @@ impl<E, D> Future for Submission<E, D> where
 
         let result = if let Some(event) = event {
             let count = event.sqes_needed();
-            ready!(ring.poll(ctx, count, |sqs| unsafe { event.prepare(sqs) }))
+            let (result, flags) = ready!(ring.poll_with_flags(ctx, count, |sqs| unsafe {
+                event.prepare(sqs)
+            }));
+            event.complete(&result, flags);
+            result
         } else {
             panic!("polled Submission after completion")
         };
@@ -0,0 +1,235 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io;
+use std::mem::ManuallyDrop;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+use futures_core::{ready, Stream};
+use parking_lot::Mutex;
+
+use crate::drive::{self, Drive};
+use crate::ring::{self, Cancellation};
+use crate::Event;
+
+/// A [`Stream`] of many independent events submitted to io-uring together
+///
+/// All of the events are prepared on the submission queue before any of them are submitted, so
+/// that the driver can submit the whole set with a single syscall. As a stream, the set yields
+/// each event and its result in the order they complete; [`join`](SubmissionSet::join) waits for
+/// all of them and returns them in the order they were submitted.
+///
+/// If the driver fails to submit the events, the stream yields that error, and tries to submit
+/// them again the next time it is polled.
+pub struct SubmissionSet<E: Event, D: Drive> {
+    driver: D,
+    state: State<E>,
+}
+
+struct State<E> {
+    entries: Vec<Entry<E>>,
+    prepared: usize,
+    submitted: bool,
+    remaining: usize,
+    ready: Arc<Ready>,
+}
+
+struct Entry<E> {
+    event: Option<E>,
+    completion: Option<ring::Completion>,
+    result: Option<io::Result<u32>>,
+    waker: Waker,
+}
+
+// The entries which have been woken since the set was last polled, and the task polling it.
+struct Ready {
+    indices: Mutex<VecDeque<usize>>,
+    task: Mutex<Option<Waker>>,
+}
+
+// The waker of a single entry, which records that the entry was woken before waking the task.
+struct EntryWaker {
+    ready: Arc<Ready>,
+    index: usize,
+}
+
+impl<E: Event, D: Drive> SubmissionSet<E, D> {
+    /// Construct a new submission set from a collection of events and a driver.
+    pub fn new(events: impl IntoIterator<Item = E>, driver: D) -> SubmissionSet<E, D> {
+        let ready = Arc::new(Ready {
+            indices: Mutex::new(VecDeque::new()),
+            task: Mutex::new(None),
+        });
+        let entries: Vec<_> = events.into_iter().enumerate().map(|(index, event)| Entry {
+            event: Some(event),
+            completion: None,
+            result: None,
+            waker: Waker::from(Arc::new(EntryWaker { ready: ready.clone(), index })),
+        }).collect();
+        SubmissionSet {
+            state: State {
+                remaining: entries.len(),
+                prepared: 0,
+                submitted: false,
+                entries,
+                ready,
+            },
+            driver,
+        }
+    }
+
+    /// Access the driver this submission set is using
+    pub fn driver(&self) -> &D {
+        &self.driver
+    }
+
+    /// Wait for every event in the set to complete, returning them in the order they were
+    /// submitted.
+    ///
+    /// If the driver fails to submit the events, that error is returned instead, and the events
+    /// are cancelled once the future is dropped.
+    pub fn join(self) -> JoinAll<E, D> {
+        JoinAll { set: self }
+    }
+
+    // Prepare and submit any events which have not been submitted, then find an event which has
+    // completed, returning its index. Returns None once every event has completed.
+    fn poll_next_index(self: Pin<&mut Self>, ctx: &mut Context<'_>)
+        -> Poll<Option<io::Result<usize>>>
+    {
+        let (mut driver, this) = self.split();
+
+        while this.prepared < this.entries.len() {
+            let entry = &mut this.entries[this.prepared];
+            let (event, waker) = (entry.event.as_mut().unwrap(), &entry.waker);
+            let count = event.sqes_needed();
+            let completion = ready!(driver.as_mut().poll_prepare(ctx, count, |mut sqs, _| {
+                let sqe = unsafe { event.prepare(&mut sqs) };
+                drive::Completion::with_waker(sqe, sqs, waker)
+            }));
+            entry.completion = Some(completion.real);
+            this.prepared += 1;
+        }
+
+        if !this.submitted {
+            if let Err(err) = ready!(driver.poll_submit(ctx)) {
+                return Poll::Ready(Some(Err(err)));
+            }
+            this.submitted = true;
+        }
+
+        if this.remaining == 0 {
+            return Poll::Ready(None);
+        }
+
+        // Only the entries which have been woken are checked. The task is registered first, so
+        // that an entry woken while the others are checked wakes it again.
+        this.ready.register(ctx.waker());
+        while let Some(index) = this.ready.pop() {
+            let entry = &mut this.entries[index];
+            // An entry can be woken again after it has completed, such as by a multishot event.
+            if let Some(completion) = entry.completion.take() {
+                match completion.check(&entry.waker) {
+                    (Some((result, flags)), completion) => {
+                        // Only the first completion of a multishot event is observed.
+                        if let Some(completion) = completion {
+                            completion.cancel(Cancellation::from(()));
+                        }
+                        entry.event.as_mut().unwrap().complete(&result, flags);
+                        entry.result = Some(result);
+                        this.remaining -= 1;
+                        return Poll::Ready(Some(Ok(index)));
+                    }
+                    (None, completion)                  => entry.completion = completion,
+                }
+            }
+        }
+
+        Poll::Pending
+    }
+
+    fn split(self: Pin<&mut Self>) -> (Pin<&mut D>, &mut State<E>) {
+        unsafe {
+            let this = Pin::get_unchecked_mut(self);
+            (Pin::new_unchecked(&mut this.driver), &mut this.state)
+        }
+    }
+}
+
+impl<E> State<E> {
+    fn take(&mut self, index: usize) -> (E, io::Result<u32>) {
+        let entry = &mut self.entries[index];
+        (entry.event.take().unwrap(), entry.result.take().unwrap())
+    }
+}
+
+impl Ready {
+    fn register(&self, waker: &Waker) {
+        let mut task = self.task.lock();
+        if !task.iter().any(|task| task.will_wake(waker)) {
+            *task = Some(waker.clone());
+        }
+    }
+
+    fn pop(&self) -> Option<usize> {
+        self.indices.lock().pop_front()
+    }
+}
+
+impl Wake for EntryWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.ready.indices.lock().push_back(self.index);
+        let task = self.ready.task.lock().clone();
+        if let Some(task) = task {
+            task.wake();
+        }
+    }
+}
+
+impl<E: Event, D: Drive> Stream for SubmissionSet<E, D> {
+    type Item = io::Result<(E, io::Result<u32>)>;
+
+    fn poll_next(mut self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match ready!(self.as_mut().poll_next_index(ctx)) {
+            Some(Ok(index)) => Poll::Ready(Some(Ok(self.split().1.take(index)))),
+            Some(Err(err))  => Poll::Ready(Some(Err(err))),
+            None            => Poll::Ready(None),
+        }
+    }
+}
+
+impl<E: Event, D: Drive> Drop for SubmissionSet<E, D> {
+    fn drop(&mut self) {
+        for entry in &mut self.state.entries {
+            if let (Some(completion), Some(event)) = (entry.completion.take(), entry.event.take()) {
+                completion.cancel(E::cancel(ManuallyDrop::new(event)));
+            }
+        }
+    }
+}
+
+/// A [`Future`] which waits for every event in a [`SubmissionSet`] to complete
+pub struct JoinAll<E: Event, D: Drive> {
+    set: SubmissionSet<E, D>,
+}
+
+impl<E: Event, D: Drive> Future for JoinAll<E, D> {
+    type Output = io::Result<Vec<(E, io::Result<u32>)>>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut set = unsafe { Pin::map_unchecked_mut(self, |this| &mut this.set) };
+        while let Some(index) = ready!(set.as_mut().poll_next_index(ctx)) {
+            if let Err(err) = index {
+                return Poll::Ready(Err(err));
+            }
+        }
+
+        let state = set.split().1;
+        Poll::Ready(Ok((0..state.entries.len()).map(|index| state.take(index)).collect()))
+    }
+}
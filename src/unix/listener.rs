@@ -46,11 +46,11 @@ impl<D: Drive> UnixListener<D> {
         })
     }
 
-    pub fn close(&mut self) -> Close<D> where D: Unpin {
+    pub fn close(&mut self) -> Close<'_, D> where D: Unpin {
         Pin::new(self).close_pinned()
     }
 
-    pub fn close_pinned(self: Pin<&mut Self>) -> Close<D> {
+    pub fn close_pinned(self: Pin<&mut Self>) -> Close<'_, D> {
         Close { socket: self }
     }
 
@@ -108,7 +108,7 @@ impl<D: Drive + Clone> UnixListener<D> {
             sqe.prep_accept(fd, None, SockFlag::empty());
             sqe
         }))? as RawFd;
-        Poll::Ready(Ok(UnixStream::from_fd(fd, self.ring().clone())))
+        Poll::Ready(Ok(UnixStream::from_fd(fd, self.ring.driver().clone())))
     }
 
 }
@@ -6,6 +6,7 @@ mod stream;
 
 pub use listener::{UnixListener, Close, Accept, Incoming};
 pub use stream::{UnixStream, Connect};
+pub use crate::net::{ReadHalf, WriteHalf, OwnedReadHalf, OwnedWriteHalf};
 
 use nix::sys::socket as nix;
 
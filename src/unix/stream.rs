@@ -1,6 +1,7 @@
 use std::io;
 use std::future::Future;
-use std::os::unix::io::RawFd;
+use std::os::unix::io::{AsRawFd, IntoRawFd, RawFd};
+use std::os::unix::net;
 use std::path::Path;
 use std::pin::Pin;
 use std::task::{Context, Poll};
@@ -12,12 +13,11 @@ use nix::sys::socket::UnixAddr;
 
 use crate::drive::{Drive, demo::DemoDriver};
 use crate::event;
-use crate::ring::Ring;
 use crate::Submission;
 
 use super::{socket, socketpair};
 
-use crate::net::TcpStream;
+use crate::net::{TcpStream, ReadHalf, WriteHalf, OwnedReadHalf, OwnedWriteHalf};
 
 pub struct UnixStream<D: Drive = DemoDriver> {
     inner: TcpStream<D>,
@@ -31,6 +31,16 @@ impl UnixStream {
     pub fn pair() -> io::Result<(UnixStream, UnixStream)> {
         UnixStream::pair_on_driver(DemoDriver::default())
     }
+
+    /// Take a connected stream and run its IO on the default driver, with read and write buffers
+    /// of `capacity` bytes
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0 or not less than 4 GiB.
+    pub fn with_capacity(capacity: usize, stream: net::UnixStream) -> UnixStream {
+        UnixStream::with_capacity_on_driver(capacity, stream, DemoDriver::default())
+    }
 }
 
 impl<D: Drive + Clone> UnixStream<D> {
@@ -45,19 +55,51 @@ impl<D: Drive + Clone> UnixStream<D> {
 
     pub fn pair_on_driver(driver: D) -> io::Result<(UnixStream<D>, UnixStream<D>)> {
         let (fd1, fd2) = socketpair()?;
-        let ring1 = Ring::new(driver.clone());
-        let ring2 = Ring::new(driver);
-        Ok((UnixStream::from_fd(fd1, ring1), UnixStream::from_fd(fd2, ring2)))
+        Ok((UnixStream::from_fd(fd1, driver.clone()), UnixStream::from_fd(fd2, driver)))
     }
-}
 
-impl<D: Drive> UnixStream<D> {
-    pub(super) fn from_fd(fd: RawFd, ring: Ring<D>) -> UnixStream<D> {
+    /// Take a connected stream and run its IO on an io-uring driver, with read and write buffers
+    /// of `capacity` bytes rather than the default of 8 KiB
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0 or not less than 4 GiB.
+    pub fn with_capacity_on_driver(capacity: usize, stream: net::UnixStream, driver: D)
+        -> UnixStream<D>
+    {
+        let fd = stream.into_raw_fd();
         UnixStream {
-            inner: TcpStream::from_fd(fd, ring),
+            inner: TcpStream::from_fd_with_capacity(fd, driver, capacity),
         }
     }
 
+    pub(super) fn from_fd(fd: RawFd, driver: D) -> UnixStream<D> {
+        UnixStream {
+            inner: TcpStream::from_fd(fd, driver),
+        }
+    }
+}
+
+impl<D: Drive> UnixStream<D> {
+    /// Split the stream into a read half and a write half which borrow it.
+    pub fn split(&mut self) -> (ReadHalf<'_, D>, WriteHalf<'_, D>) where D: Unpin {
+        self.inner.split()
+    }
+
+    /// Split the stream into a read half and a write half which borrow it, from a pinned
+    /// reference.
+    pub fn split_pinned(self: Pin<&mut Self>) -> (ReadHalf<'_, D>, WriteHalf<'_, D>) {
+        self.inner().split_pinned()
+    }
+
+    /// Split the stream into a read half and a write half which own it, and can be moved to
+    /// different tasks.
+    ///
+    /// The socket is closed once both halves have been dropped.
+    pub fn into_split(self) -> (OwnedReadHalf<D>, OwnedWriteHalf<D>) {
+        self.inner.into_split()
+    }
+
     #[inline(always)]
     fn inner(self: Pin<&mut Self>) -> Pin<&mut TcpStream<D>> {
         unsafe { Pin::map_unchecked_mut(self, |this| &mut this.inner) }
@@ -79,7 +121,7 @@ impl<D: Drive + Clone> Future for Connect<D> {
                     let (connect, result) = ready!(submission.as_mut().poll(ctx));
                     result?;
                     let driver = submission.driver().clone();
-                    Poll::Ready(Ok(UnixStream::from_fd(connect.fd, Ring::new(driver))))
+                    Poll::Ready(Ok(UnixStream::from_fd(connect.fd, driver)))
                 }
                 Err(err)        => {
                     let err = err.take().expect("polled Connect future after completion");
@@ -90,6 +132,12 @@ impl<D: Drive + Clone> Future for Connect<D> {
     }
 }
 
+impl<D: Drive> AsRawFd for UnixStream<D> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
 impl<D: Drive> AsyncRead for UnixStream<D> {
     fn poll_read(self: Pin<&mut Self>, ctx: &mut Context<'_>, buf: &mut [u8])
         -> Poll<io::Result<usize>>
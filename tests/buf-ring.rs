@@ -0,0 +1,50 @@
+use std::io::{Read, Write};
+use std::net::Shutdown;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+use futures::StreamExt;
+use iou::sqe::MsgFlags;
+
+use ringbahn::drive::{demo, Drive};
+
+const ASSERT: &[u8] = b"But this formidable power of death -";
+
+#[test]
+fn recv_multishot_recycles_buffers() {
+    let (mut tx, rx) = UnixStream::pair().unwrap();
+    let ring = demo::driver().register_buf_ring(2, 64).unwrap();
+
+    futures::executor::block_on(async move {
+        let mut stream = ring.recv_multishot(rx.as_raw_fd(), MsgFlags::empty(), demo::driver());
+        // more messages than there are buffers, so dropped buffers must return to the ring
+        for _ in 0..4 {
+            tx.write_all(ASSERT).unwrap();
+            let buf = stream.next().await.unwrap().unwrap();
+            assert_eq!(&buf[..], ASSERT);
+        }
+        tx.shutdown(Shutdown::Write).unwrap();
+        assert!(stream.next().await.is_none());
+    });
+}
+
+#[test]
+fn dropping_recv_multishot_cancels_it() {
+    let (mut tx, mut rx) = UnixStream::pair().unwrap();
+    let ring = demo::driver().register_buf_ring(2, 64).unwrap();
+
+    futures::executor::block_on(async {
+        let mut stream = ring.recv_multishot(rx.as_raw_fd(), MsgFlags::empty(), demo::driver());
+        tx.write_all(ASSERT).unwrap();
+        let buf = stream.next().await.unwrap().unwrap();
+        assert_eq!(&buf[..], ASSERT);
+    });
+
+    // once the recv is cancelled, data written to the socket is left for other readers
+    tx.write_all(ASSERT).unwrap();
+    rx.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+    let mut buf = [0; 64];
+    let n = rx.read(&mut buf).unwrap();
+    assert_eq!(&buf[..n], ASSERT);
+}
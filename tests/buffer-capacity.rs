@@ -0,0 +1,71 @@
+use futures::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt};
+use futures::executor::block_on;
+
+use ringbahn::fs::File;
+use ringbahn::unix::UnixStream;
+
+fn contents(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+#[test]
+fn file_with_capacity() {
+    let mut tmp = tempfile::tempfile().unwrap();
+    let expected = contents(200_000);
+    std::io::Write::write_all(&mut tmp, &expected).unwrap();
+    std::io::Seek::seek(&mut tmp, std::io::SeekFrom::Start(0)).unwrap();
+
+    block_on(async {
+        let mut file = File::with_capacity(16, tmp);
+        assert_eq!(file.fill_buf().await.unwrap().len(), 16);
+
+        let mut buf = [0; 10];
+        file.read_exact(&mut buf).await.unwrap();
+        assert!(buf[..] == expected[..10]);
+
+        // The buffered data is returned before a large read is made.
+        let mut buf = vec![0; 100_000];
+        assert_eq!(file.read(&mut buf).await.unwrap(), 6);
+        assert!(buf[..6] == expected[10..16]);
+
+        // With the buffer empty, the slice is read at once, up to 64 times the capacity.
+        assert_eq!(file.read(&mut buf).await.unwrap(), 1024);
+        assert!(buf[..1024] == expected[16..1040]);
+
+        // Small reads go back to using the buffer.
+        assert_eq!(file.fill_buf().await.unwrap(), &expected[1040..1056]);
+        file.consume_unpin(16);
+
+        // A large buffer is not kept once it has been read.
+        let mut buf = vec![0; 1000];
+        assert_eq!(file.read(&mut buf).await.unwrap(), 1000);
+        assert!(buf[..] == expected[1056..2056]);
+        assert!(file.read_buffered().is_empty());
+
+        let mut rest = vec![];
+        file.read_to_end(&mut rest).await.unwrap();
+        assert!(rest[..] == expected[2056..]);
+    });
+}
+
+#[test]
+fn stream_with_capacity() {
+    let (a, b) = std::os::unix::net::UnixStream::pair().unwrap();
+    let expected = contents(50_000);
+
+    block_on(async {
+        let mut a = UnixStream::with_capacity(64 * 1024, a);
+        let mut b = UnixStream::with_capacity(32, b);
+        assert_eq!(a.write(&expected).await.unwrap(), 50_000);
+
+        let mut buf = vec![];
+        while buf.len() < expected.len() {
+            let data = b.fill_buf().await.unwrap();
+            assert!(data.len() <= 32);
+            buf.extend_from_slice(data);
+            let len = data.len();
+            b.consume_unpin(len);
+        }
+        assert!(buf == expected);
+    });
+}
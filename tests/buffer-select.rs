@@ -0,0 +1,69 @@
+use std::fs::File;
+use std::io::Write;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::UnixStream;
+
+use ringbahn::buf::BufferGroup;
+use ringbahn::event::{ReadSelect, RecvSelect};
+use ringbahn::drive::{demo, Drive};
+
+use iou::sqe::MsgFlags;
+
+const ASSERT: &[u8] = b"But this formidable power of death -";
+
+#[test]
+fn read_select_replenishes_group() {
+    let file = File::open("props.txt").unwrap();
+    let group = BufferGroup::new(2, 16);
+
+    futures::executor::block_on(async move {
+        // more reads than there are buffers, so returned buffers must be provided again
+        for offset in (0..ASSERT.len() as u64).step_by(8) {
+            let read = ReadSelect::new(file.as_raw_fd(), group.clone(), offset);
+            let (mut read, result) = demo::driver().submit(read).await;
+            let n = result.unwrap() as usize;
+            let buf = read.buf.take().unwrap();
+            let end = std::cmp::min(offset as usize + n, ASSERT.len());
+            // The buffer only holds what was read into it.
+            assert_eq!(buf.len(), n);
+            assert_eq!(&buf[..end - offset as usize], &ASSERT[offset as usize..end]);
+        }
+    });
+}
+
+#[test]
+fn recv_select() {
+    let (mut tx, rx) = UnixStream::pair().unwrap();
+    let group = BufferGroup::new(4, 64);
+    tx.write_all(ASSERT).unwrap();
+
+    futures::executor::block_on(async move {
+        let recv = RecvSelect::new(rx.as_raw_fd(), group, MsgFlags::empty());
+        let (recv, result) = demo::driver().submit(recv).await;
+        assert_eq!(result.unwrap() as usize, ASSERT.len());
+        assert_eq!(&recv.buf.unwrap()[..], ASSERT);
+    });
+}
+
+#[test]
+fn cancelled_recv_select_returns_buffer() {
+    let (mut tx, rx) = UnixStream::pair().unwrap();
+    let group = BufferGroup::new(1, 64);
+
+    futures::executor::block_on(async move {
+        let recv = RecvSelect::new(rx.as_raw_fd(), group.clone(), MsgFlags::empty());
+        let mut recv = Box::pin(demo::driver().submit(recv));
+        assert!(futures::poll!(recv.as_mut()).is_pending());
+        drop(recv);
+
+        // The cancelled recv still takes the group's only buffer when data arrives.
+        tx.write_all(b"lost").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+
+        tx.write_all(ASSERT).unwrap();
+        let recv = RecvSelect::new(rx.as_raw_fd(), group, MsgFlags::empty());
+        let (recv, result) = demo::driver().submit(recv).await;
+        assert_eq!(result.unwrap() as usize, ASSERT.len());
+        assert_eq!(&recv.buf.unwrap()[..], ASSERT);
+    });
+}
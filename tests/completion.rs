@@ -0,0 +1,311 @@
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+use futures::task::{waker, ArcWake};
+use iou::{IoUring, SQE, SQEs};
+
+use ringbahn::drive::{self, Completion, Drive};
+use ringbahn::ring::{Cancellation, Ring};
+
+const IORING_CQE_F_MORE: u32 = 1 << 1;
+
+/// A driver which submits events, but leaves it to the test to decide when they complete.
+#[derive(Clone)]
+struct Manual(Arc<Mutex<IoUring>>);
+
+impl Manual {
+    // Wait for the kernel to finish an event, and return its user_data without completing it.
+    fn reap(&self) -> u64 {
+        self.0.lock().unwrap().wait_for_cqe().unwrap().user_data()
+    }
+}
+
+impl Drive for Manual {
+    fn poll_prepare<'cx>(
+        self: Pin<&mut Self>,
+        ctx: &mut Context<'cx>,
+        count: u32,
+        prepare: impl FnOnce(SQEs<'_>, &mut Context<'cx>) -> Completion<'cx>,
+    ) -> Poll<Completion<'cx>> {
+        let mut ring = self.0.lock().unwrap();
+        Poll::Ready(prepare(ring.prepare_sqes(count).unwrap(), ctx))
+    }
+
+    fn poll_submit(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<u32>> {
+        Poll::Ready(self.0.lock().unwrap().submit_sqes())
+    }
+}
+
+struct Wakes(AtomicUsize);
+
+impl ArcWake for Wakes {
+    fn wake_by_ref(this: &Arc<Self>) {
+        this.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+struct Tracker(Arc<AtomicUsize>);
+
+impl Drop for Tracker {
+    fn drop(&mut self) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+enum Step {
+    Poll,
+    Cancel,
+    Complete(u32, bool),
+}
+
+fn nop<'sq>(sqs: &mut SQEs<'sq>) -> SQE<'sq> {
+    let mut sqe = sqs.single().unwrap();
+    unsafe { sqe.prep_nop(); }
+    sqe
+}
+
+fn submit(driver: &Manual, ring: &mut Ring<Manual>, ctx: &mut Context<'_>) -> u64 {
+    assert!(Pin::new(ring).poll_with_flags(ctx, 1, nop).is_pending());
+    driver.reap()
+}
+
+fn interleavings(a: &[Step], b: &[Step], prefix: &mut Vec<Step>, out: &mut Vec<Vec<Step>>) {
+    match (a.split_first(), b.split_first()) {
+        (None, None)                    => out.push(prefix.clone()),
+        (first_a, first_b)              => {
+            for (first, a, b) in first_a.map(|(s, rest)| (s, rest, b)).into_iter()
+                .chain(first_b.map(|(s, rest)| (s, a, rest)))
+            {
+                prefix.push(*first);
+                interleavings(a, b, prefix, out);
+                prefix.pop();
+            }
+        }
+    }
+}
+
+// Run one interleaving of the task's and the kernel's steps, checking that results are observed
+// in order, that the task is woken, that the cancellation is dropped exactly when the kernel is
+// done with the event, and that the slot is freed exactly once.
+fn run(driver: &Manual, steps: &[Step]) {
+    let wakes = Arc::new(Wakes(AtomicUsize::new(0)));
+    let waker = waker(wakes.clone());
+    let mut ctx = Context::from_waker(&waker);
+
+    let mut ring = Ring::new(driver.clone());
+    let user_data = submit(driver, &mut ring, &mut ctx);
+
+    let dropped = Arc::new(AtomicUsize::new(0));
+    let mut completed = vec![];
+    let mut observed = vec![];
+    let mut finished = false;
+    let mut cancelled = false;
+    let mut waiting = None;
+
+    // Returns whether the event has finished, or None if the task is waiting to be woken.
+    let poll = |ring: &mut Ring<Manual>, observed: &mut Vec<_>, ctx: &mut Context<'_>| {
+        match Pin::new(ring).poll_with_flags(ctx, 1, |_| panic!("prepared a second event")) {
+            Poll::Ready((result, flags)) => {
+                let more = flags & IORING_CQE_F_MORE != 0;
+                observed.push((result.unwrap(), more));
+                Some(!more)
+            }
+            Poll::Pending => None,
+        }
+    };
+
+    for &step in steps {
+        match step {
+            Step::Poll if !finished && !cancelled   => {
+                let wakes_before = wakes.0.load(Ordering::SeqCst);
+                match poll(&mut ring, &mut observed, &mut ctx) {
+                    Some(done)  => finished = done,
+                    None        => waiting = Some(wakes_before),
+                }
+            }
+            Step::Cancel if !finished && !cancelled => {
+                ring.cancel(Cancellation::from(Box::new(Tracker(dropped.clone()))));
+                cancelled = true;
+            }
+            Step::Poll | Step::Cancel               => { }
+            Step::Complete(res, more)               => {
+                let flags = if more { IORING_CQE_F_MORE } else { 0 };
+                drive::complete_raw(user_data, res as i32, flags);
+                completed.push((res, more));
+                if let Some(wakes_before) = waiting.take() {
+                    if !cancelled {
+                        assert!(wakes.0.load(Ordering::SeqCst) > wakes_before, "{:?}", steps);
+                    }
+                }
+            }
+        }
+
+        assert!(completed.starts_with(&observed), "{:?}", steps);
+        let done = matches!(completed.last(), Some(&(_, false)));
+        let expected_drops = if cancelled && done { 1 } else { 0 };
+        assert_eq!(dropped.load(Ordering::SeqCst), expected_drops, "{:?}", steps);
+    }
+
+    while !finished && !cancelled {
+        finished = poll(&mut ring, &mut observed, &mut ctx).expect("completed event is pending");
+    }
+    if !cancelled {
+        assert_eq!(observed, completed, "{:?}", steps);
+    }
+
+    // Slots are reused most recently freed first. If this event's slot was freed, the next event
+    // will use it, with a new generation; if it was freed twice, the event after will use it too.
+    let mut next = [Ring::new(driver.clone()), Ring::new(driver.clone())];
+    let first = submit(driver, &mut next[0], &mut ctx);
+    let second = submit(driver, &mut next[1], &mut ctx);
+    assert_eq!(first as u32, user_data as u32, "{:?}", steps);
+    assert_ne!(first >> 32, user_data >> 32, "{:?}", steps);
+    assert_ne!(second as u32, first as u32, "{:?}", steps);
+
+    for (ring, user_data) in next.iter_mut().zip(&[first, second]) {
+        drive::complete_raw(*user_data, 0, 0);
+        assert!(Pin::new(ring).poll_with_flags(&mut ctx, 1, nop).is_ready());
+    }
+}
+
+#[test]
+fn every_interleaving() {
+    let driver = Manual(Arc::new(Mutex::new(IoUring::new(8).unwrap())));
+
+    let single = [Step::Complete(7, false)];
+    let multishot = [Step::Complete(1, true), Step::Complete(2, true), Step::Complete(3, false)];
+
+    for kernel in [&single[..], &multishot[..]].iter() {
+        for polls in 0..=kernel.len() {
+            for &cancel in &[false, true] {
+                let mut task = vec![Step::Poll; polls];
+                if cancel {
+                    task.push(Step::Cancel);
+                }
+
+                let mut all = vec![];
+                interleavings(&task, kernel, &mut vec![], &mut all);
+                for steps in all {
+                    run(&driver, &steps);
+                }
+            }
+        }
+    }
+}
+
+// Wakes the racing task by setting a flag shared by all of its wakers, and unparking its thread.
+//
+// The waker yields the thread it runs on while it is being woken or dropped. Completions drop
+// the old waker in the middle of replacing it, and wake wakers while the task may be replacing
+// them, so yielding there lets the other thread run in the middle of each race, even on a single
+// CPU. Dropping a waker while it is being woken fails the test.
+struct Unpark {
+    woken: Arc<AtomicBool>,
+    waking: AtomicBool,
+    thread: Thread,
+}
+
+static VTABLE: RawWakerVTable = RawWakerVTable::new(clone_raw, wake_raw, wake_by_ref_raw, drop_raw);
+
+fn unpark_waker(woken: &Arc<AtomicBool>) -> Waker {
+    let waking = AtomicBool::new(false);
+    let unpark = Arc::new(Unpark { woken: woken.clone(), waking, thread: thread::current() });
+    unsafe { Waker::from_raw(RawWaker::new(Arc::into_raw(unpark) as *const (), &VTABLE)) }
+}
+
+// Clones share the same data pointer, so that replacing a waker with its clone is skipped.
+unsafe fn clone_raw(data: *const ()) -> RawWaker {
+    Arc::increment_strong_count(data as *const Unpark);
+    RawWaker::new(data, &VTABLE)
+}
+
+unsafe fn wake_raw(data: *const ()) {
+    wake_by_ref_raw(data);
+    drop_raw(data);
+}
+
+unsafe fn wake_by_ref_raw(data: *const ()) {
+    let unpark = &*(data as *const Unpark);
+    unpark.waking.store(true, Ordering::SeqCst);
+    unpark.woken.store(true, Ordering::SeqCst);
+    unpark.thread.unpark();
+    thread::yield_now();
+    unpark.waking.store(false, Ordering::SeqCst);
+}
+
+unsafe fn drop_raw(data: *const ()) {
+    let unpark = &*(data as *const Unpark);
+    assert!(!unpark.waking.load(Ordering::SeqCst), "dropped a waker while it was being woken");
+    thread::yield_now();
+    drop(Arc::from_raw(data as *const Unpark));
+}
+
+// Stream results of a multishot event from another thread while the task polls for them,
+// checking that each result is observed in order and that no wakeup is lost. Each result is only
+// completed once the task has observed the last, so that it races the task's next poll, and a
+// lost wakeup leaves the task waiting.
+//
+// If `replace_waker` is set, the task polls with a different waker each time, so that the completion replaces
+// its waker while the other thread may be streaming a result and waking it. Otherwise the waker
+// is never replaced, and streamed results race only with the task taking them.
+fn race(driver: &Manual, results: u32, replace_waker: bool) {
+    let woken = Arc::new(AtomicBool::new(false));
+    let wakers = [unpark_waker(&woken), unpark_waker(&woken)];
+
+    let mut ring = Ring::new(driver.clone());
+    let user_data = submit(driver, &mut ring, &mut Context::from_waker(&wakers[0]));
+
+    let seen = Arc::new(AtomicUsize::new(0));
+    let kernel_seen = seen.clone();
+    let kernel = thread::spawn(move || {
+        for res in 0..results {
+            while kernel_seen.load(Ordering::SeqCst) < res as usize {
+                thread::yield_now();
+            }
+            let flags = if res + 1 < results { IORING_CQE_F_MORE } else { 0 };
+            drive::complete_raw(user_data, res as i32, flags);
+        }
+    });
+
+    let mut observed = vec![];
+    loop {
+        woken.store(false, Ordering::SeqCst);
+        // The task is only woken by the next result, so the waker changes with each result.
+        let waker = &wakers[if replace_waker { observed.len() % 2 } else { 0 }];
+        let mut ctx = Context::from_waker(waker);
+        match Pin::new(&mut ring).poll_with_flags(&mut ctx, 1, |_| panic!("prepared again")) {
+            Poll::Ready((result, flags)) => {
+                observed.push(result.unwrap());
+                seen.store(observed.len(), Ordering::SeqCst);
+                if flags & IORING_CQE_F_MORE == 0 {
+                    break;
+                }
+            }
+            Poll::Pending => {
+                let deadline = Instant::now() + Duration::from_secs(5);
+                while !woken.load(Ordering::SeqCst) {
+                    assert!(Instant::now() < deadline, "lost wakeup after {:?}", observed);
+                    thread::park_timeout(Duration::from_millis(100));
+                }
+            }
+        }
+    }
+
+    kernel.join().unwrap();
+    assert_eq!(observed, (0..results).collect::<Vec<_>>());
+}
+
+#[test]
+fn multishot_results_race_the_task() {
+    let driver = Manual(Arc::new(Mutex::new(IoUring::new(8).unwrap())));
+    for _ in 0..200 {
+        race(&driver, 32, false);
+        race(&driver, 32, true);
+    }
+}
@@ -0,0 +1,57 @@
+use futures::{AsyncReadExt, AsyncWriteExt};
+use futures::executor::block_on;
+
+use ringbahn::event::ResolveFlags;
+use ringbahn::fs::Dir;
+
+#[test]
+fn files_relative_to_dir() {
+    let tmp = tempfile::tempdir().unwrap();
+
+    block_on(async {
+        let root = Dir::open(tmp.path()).await.unwrap();
+        root.create_dir("a").await.unwrap();
+        root.create_dir("a/b").await.unwrap();
+
+        let mut file = root.create_file("a/b/file").await.unwrap();
+        file.write_all(b"hello").await.unwrap();
+        file.close().await.unwrap();
+
+        let metadata = root.metadata("a/b/file").await.unwrap();
+        assert_eq!(metadata.len(), 5);
+        assert!(metadata.is_file());
+
+        let sub = root.open_dir("a").await.unwrap();
+        let mut file = sub.open_file("b/file").await.unwrap();
+        let mut buf = String::new();
+        file.read_to_string(&mut buf).await.unwrap();
+        assert_eq!(buf, "hello");
+    });
+}
+
+#[test]
+fn paths_confined_to_dir() {
+    let tmp = tempfile::tempdir().unwrap();
+    std::fs::create_dir(tmp.path().join("root")).unwrap();
+    std::fs::write(tmp.path().join("secret"), b"secret").unwrap();
+    std::fs::write(tmp.path().join("root/secret"), b"inside").unwrap();
+    std::os::unix::fs::symlink("/secret", tmp.path().join("root/link")).unwrap();
+
+    block_on(async {
+        let root = Dir::open(tmp.path().join("root")).await.unwrap();
+        for path in &["../secret", "link"] {
+            let err = root.open_file(path).await.err().unwrap();
+            assert_eq!(err.raw_os_error(), Some(libc::EXDEV), "{}", path);
+        }
+        let err = root.create_dir("../escaped").await.unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EXDEV));
+        let err = root.metadata("..").await.unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EXDEV));
+
+        let root = root.with_resolve_flags(ResolveFlags::RESOLVE_IN_ROOT);
+        let mut file = root.open_file("link").await.unwrap();
+        let mut buf = String::new();
+        file.read_to_string(&mut buf).await.unwrap();
+        assert_eq!(buf, "inside");
+    });
+}
@@ -0,0 +1,60 @@
+use std::io;
+
+use futures::executor::block_on;
+
+use ringbahn::fs::DirectFile;
+
+#[test]
+fn direct_read_write() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+
+    block_on(async {
+        let file = DirectFile::create(&path).await.unwrap();
+        let block = file.offset_align();
+        assert!(block.is_power_of_two());
+
+        let mut buf = file.alloc(block * 2);
+        assert_eq!(buf.as_ptr() as usize % file.mem_align(), 0);
+        for (i, byte) in buf.iter_mut().enumerate() {
+            *byte = (i % 251) as u8;
+        }
+        let (buf, result) = file.write_at(buf, 0).await;
+        assert_eq!(result.unwrap(), block * 2);
+        drop(file);
+        assert!(std::fs::read(&path).unwrap() == buf[..]);
+
+        let file = DirectFile::open(&path).await.unwrap();
+        let (read, result) = file.read_at(file.alloc(block * 2), block as u64).await;
+        assert_eq!(result.unwrap(), block);
+        assert!(read[..block] == buf[block..]);
+    });
+}
+
+#[test]
+fn misaligned_direct_io() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+
+    block_on(async {
+        let file = DirectFile::create(&path).await.unwrap();
+        let block = file.offset_align();
+        if block == 1 {
+            return;
+        }
+
+        let (buf, result) = file.write_at(file.alloc(block), 1).await;
+        let err = result.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(err.to_string().contains("offset 1"), "{}", err);
+
+        let (_, result) = file.read_at(file.alloc(block + 1), 0).await;
+        let err = result.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(err.to_string().contains("length"), "{}", err);
+
+        // The buffer is returned after a misaligned call, and can be used again.
+        let (_, result) = file.write_at(buf, 0).await;
+        assert_eq!(result.unwrap(), block);
+    });
+}
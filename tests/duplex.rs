@@ -0,0 +1,53 @@
+use futures::{AsyncReadExt, AsyncWriteExt};
+
+use ringbahn::unix::UnixStream;
+
+const ASSERT: &[u8] = b"But this formidable power of death -";
+
+#[test]
+fn read_while_writing() {
+    let (a, mut b) = UnixStream::pair().unwrap();
+
+    futures::executor::block_on(async move {
+        let (mut read, mut write) = a.split();
+
+        // the read is in flight before anything is written, and must not be cancelled by the write
+        let reading = async {
+            let mut buf = [0; ASSERT.len()];
+            read.read_exact(&mut buf).await.unwrap();
+            buf
+        };
+        let echoing = async {
+            write.write_all(ASSERT).await.unwrap();
+            let mut buf = [0; ASSERT.len()];
+            b.read_exact(&mut buf).await.unwrap();
+            b.write_all(&buf).await.unwrap();
+        };
+        let (buf, ()) = futures::join!(reading, echoing);
+        assert_eq!(&buf[..], ASSERT);
+    });
+}
+
+#[test]
+fn into_split_across_threads() {
+    let (a, mut b) = UnixStream::pair().unwrap();
+    let (mut read, mut write) = a.into_split();
+
+    let writer = std::thread::spawn(move || futures::executor::block_on(async move {
+        write.write_all(ASSERT).await.unwrap();
+        write.close().await.unwrap();
+    }));
+
+    futures::executor::block_on(async move {
+        let mut buf = vec![];
+        b.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(&buf[..], ASSERT);
+
+        b.write_all(ASSERT).await.unwrap();
+        let mut buf = [0; ASSERT.len()];
+        read.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf[..], ASSERT);
+    });
+
+    writer.join().unwrap();
+}
@@ -0,0 +1,59 @@
+use std::io::SeekFrom;
+
+use futures::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use futures::executor::block_on;
+
+use ringbahn::fs::File;
+
+#[test]
+fn write_after_read() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    std::fs::write(&path, b"0123456789").unwrap();
+
+    block_on(async {
+        let mut file = File::options().read(true).write(true).open(&path).await.unwrap();
+        let mut buf = [0; 5];
+        file.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"01234");
+
+        // The write lands after the data which was consumed, not after the data which was read.
+        file.write_all(b"ab").await.unwrap();
+        assert_eq!(file.seek(SeekFrom::Current(0)).await.unwrap(), 7);
+        let mut rest = vec![];
+        file.read_to_end(&mut rest).await.unwrap();
+        assert_eq!(rest, b"789");
+    });
+    assert_eq!(std::fs::read(&path).unwrap(), b"01234ab789");
+}
+
+#[test]
+fn relative_seeks() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    let contents: Vec<u8> = (0..20_000).map(|i| (i % 251) as u8).collect();
+    std::fs::write(&path, &contents).unwrap();
+
+    block_on(async {
+        for &read_ahead in &[false, true] {
+            let mut file = File::open(&path).await.unwrap();
+            if read_ahead {
+                file.set_read_ahead(2, 4096);
+            }
+
+            let mut buf = [0; 3];
+            file.read_exact(&mut buf).await.unwrap();
+            assert_eq!(file.seek(SeekFrom::Current(2)).await.unwrap(), 5);
+            file.read_exact(&mut buf).await.unwrap();
+            assert_eq!(buf[..], contents[5..8]);
+
+            assert_eq!(file.seek(SeekFrom::Current(-6)).await.unwrap(), 2);
+            file.read_exact(&mut buf).await.unwrap();
+            assert_eq!(buf[..], contents[2..5]);
+
+            assert_eq!(file.seek(SeekFrom::Current(10_000)).await.unwrap(), 10_005);
+            file.read_exact(&mut buf).await.unwrap();
+            assert_eq!(buf[..], contents[10_005..10_008]);
+        }
+    });
+}
@@ -0,0 +1,62 @@
+use futures::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt};
+use futures::executor::block_on;
+use futures::io::SeekFrom;
+
+use ringbahn::fs::File;
+
+#[test]
+fn concurrent_positional_io() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    std::fs::write(&path, vec![b'.'; 64]).unwrap();
+
+    block_on(async {
+        let file = File::options().read(true).write(true).open(&path).await.unwrap();
+        let writes = (0..8u8).map(|i| {
+            let file = &file;
+            async move { file.write_all_at(&[b'a' + i; 8], i as u64 * 8).await }
+        });
+        for result in futures::future::join_all(writes).await {
+            result.unwrap();
+        }
+
+        let mut first = [0; 8];
+        let mut last = [0; 8];
+        let (a, b) = futures::join!(file.read_exact_at(&mut first, 0), file.read_exact_at(&mut last, 56));
+        a.unwrap();
+        b.unwrap();
+        assert_eq!(&first, b"aaaaaaaa");
+        assert_eq!(&last, b"hhhhhhhh");
+
+        let mut rest = [0; 8];
+        let err = file.read_exact_at(&mut rest, 60).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    });
+
+    let expected: Vec<u8> = (0..8).flat_map(|i| vec![b'a' + i; 8]).collect();
+    assert_eq!(std::fs::read(&path).unwrap(), expected);
+}
+
+#[test]
+fn positional_io_leaves_cursor_and_buffer() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    std::fs::write(&path, b"0123456789").unwrap();
+
+    block_on(async {
+        let mut file = File::options().read(true).write(true).open(&path).await.unwrap();
+        file.seek(SeekFrom::Start(2)).await.unwrap();
+        assert_eq!(file.fill_buf().await.unwrap(), b"23456789");
+        file.consume_unpin(2);
+
+        file.write_all_at(b"abc", 0).await.unwrap();
+        let mut buf = [0; 4];
+        file.read_exact_at(&mut buf, 0).await.unwrap();
+        assert_eq!(&buf, b"abc3");
+
+        assert_eq!(file.read_buffered(), b"456789");
+        let mut rest = String::new();
+        file.read_to_string(&mut rest).await.unwrap();
+        assert_eq!(rest, "456789");
+    });
+}
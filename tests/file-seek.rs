@@ -12,6 +12,7 @@ fn seek_to_end() {
 }
 
 #[test]
+#[allow(clippy::unused_io_amount)]
 fn seek_and_then_io() {
     futures::executor::block_on(async move {
         let mut file: File = tempfile::tempfile().unwrap().into();
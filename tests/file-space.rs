@@ -0,0 +1,65 @@
+use futures::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use futures::executor::block_on;
+use futures::io::SeekFrom;
+
+use iou::sqe::PosixFadviseAdvice;
+use ringbahn::fs::File;
+
+#[test]
+fn set_len_and_allocate() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+
+    block_on(async {
+        let mut file = File::options().read(true).write(true).create(true).open(&path).await.unwrap();
+        file.write_all(b"hello, world").await.unwrap();
+        file.set_len(5).await.unwrap();
+        assert_eq!(file.metadata().await.unwrap().len(), 5);
+
+        file.set_len(8).await.unwrap();
+        let mut buf = vec![];
+        file.seek(SeekFrom::Start(0)).await.unwrap();
+        file.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, b"hello\0\0\0");
+
+        file.allocate(0, 1 << 20).await.unwrap();
+        let metadata = file.metadata().await.unwrap();
+        assert_eq!(metadata.len(), 1 << 20);
+        assert!(metadata.blocks() * 512 >= 1 << 20);
+
+        file.advise(0, 0, PosixFadviseAdvice::POSIX_FADV_SEQUENTIAL).await.unwrap();
+    });
+}
+
+#[test]
+fn punch_hole_and_zero_range() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    std::fs::write(&path, vec![1; 3 * 4096]).unwrap();
+
+    block_on(async {
+        let mut file = File::options().write(true).open(&path).await.unwrap();
+        file.punch_hole(0, 4096).await.unwrap();
+        file.zero_range(8192, 8192).await.unwrap();
+        assert_eq!(file.metadata().await.unwrap().len(), 4 * 4096);
+    });
+
+    let contents = std::fs::read(&path).unwrap();
+    assert!(contents[..4096].iter().all(|&b| b == 0));
+    assert!(contents[4096..8192].iter().all(|&b| b == 1));
+    assert!(contents[8192..].iter().all(|&b| b == 0));
+}
+
+#[test]
+fn advise_length_limit() {
+    let file = File::from(tempfile::tempfile().unwrap());
+    let advice = PosixFadviseAdvice::POSIX_FADV_WILLNEED;
+
+    block_on(async {
+        file.advise(0, u32::MAX as u64, advice).await.unwrap();
+        for len in [1 << 32, (1 << 32) + 1, u64::MAX] {
+            let err = file.advise(0, len, advice).await.unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        }
+    });
+}
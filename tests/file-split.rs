@@ -0,0 +1,64 @@
+use futures::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use futures::executor::block_on;
+
+use std::io::SeekFrom;
+
+use ringbahn::fs::File;
+
+const ASSERT: &[u8] = b"But this formidable power of death -";
+
+#[test]
+fn read_while_writing() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("fifo");
+    nix::unistd::mkfifo(&path, nix::sys::stat::Mode::S_IRWXU).unwrap();
+    let fifo = std::fs::OpenOptions::new().read(true).write(true).open(&path).unwrap();
+
+    block_on(async move {
+        let (mut read, mut write) = File::from(fifo).into_split().await.unwrap();
+
+        // the read is in flight before anything is written, and must not be cancelled by the write
+        let reading = async {
+            let mut buf = [0; ASSERT.len()];
+            read.read_exact(&mut buf).await.unwrap();
+            buf
+        };
+        let writing = async {
+            write.write_all(ASSERT).await.unwrap();
+            write.flush().await.unwrap();
+        };
+        let (buf, ()) = futures::join!(reading, writing);
+        assert_eq!(&buf[..], ASSERT);
+    });
+}
+
+#[test]
+fn halves_have_own_cursors() {
+    let tmp = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(tmp.path(), ASSERT).unwrap();
+    let file = std::fs::OpenOptions::new().read(true).write(true).open(tmp.path()).unwrap();
+
+    block_on(async move {
+        let mut file = File::from(file);
+        file.seek(SeekFrom::Start(4)).await.unwrap();
+        let mut buf = [0; 5];
+        file.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"this ");
+
+        // Both halves start at the file's cursor, even though the rest of the file is buffered.
+        let (mut read, mut write) = file.into_split().await.unwrap();
+        assert_eq!((read.position(), write.position()), (9, 9));
+
+        write.write_all(b"FORMIDABLE").await.unwrap();
+        write.flush().await.unwrap();
+        assert_eq!(write.position(), 19);
+        assert_eq!(read.position(), 9);
+
+        let mut buf = [0; 5];
+        read.read_exact(&mut buf).await.unwrap();
+        assert_eq!(read.position(), 14);
+    });
+
+    let contents = std::fs::read(tmp.path()).unwrap();
+    assert_eq!(&contents[..], b"But this FORMIDABLE power of death -");
+}
@@ -0,0 +1,38 @@
+use futures::AsyncWriteExt;
+use futures::executor::block_on;
+
+use ringbahn::event::SyncFileRangeFlags;
+use ringbahn::fs::File;
+
+#[test]
+fn sync_after_writes() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+
+    block_on(async {
+        let mut file = File::create(&path).await.unwrap();
+        file.write_all(b"first").await.unwrap();
+        file.sync_all().await.unwrap();
+        file.write_all(b", second").await.unwrap();
+        file.sync_data().await.unwrap();
+        file.write_all(b", third").await.unwrap();
+        let flags = SyncFileRangeFlags::WAIT_BEFORE | SyncFileRangeFlags::WRITE
+            | SyncFileRangeFlags::WAIT_AFTER;
+        file.sync_range(0, 0, flags).await.unwrap();
+        file.sync_all().await.unwrap();
+        file.close().await.unwrap();
+    });
+
+    assert_eq!(std::fs::read(&path).unwrap(), b"first, second, third");
+}
+
+#[test]
+fn sync_range_rejects_invalid_range() {
+    let dir = tempfile::tempdir().unwrap();
+
+    block_on(async {
+        let mut file = File::create(dir.path().join("file")).await.unwrap();
+        let err = file.sync_range(u64::MAX, 1, SyncFileRangeFlags::WRITE).await.unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+    });
+}
@@ -0,0 +1,55 @@
+use futures::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use futures::executor::block_on;
+
+use std::io::SeekFrom;
+use std::sync::Mutex;
+
+use ringbahn::drive::demo;
+use ringbahn::fs::File;
+
+// The tests share the demo driver's pool, and check which of its buffers are leased.
+static POOL: Mutex<()> = Mutex::new(());
+
+fn contents(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+fn round_trip(expected: &[u8]) -> File {
+    block_on(async {
+        let mut file = File::from(tempfile::tempfile().unwrap());
+        file.write_all(expected).await.unwrap();
+        file.flush().await.unwrap();
+        file.seek(SeekFrom::Start(0)).await.unwrap();
+
+        let mut buf = vec![];
+        file.read_to_end(&mut buf).await.unwrap();
+        assert!(buf == expected);
+        file
+    })
+}
+
+#[test]
+fn file_with_fixed_buffers() {
+    let _guard = POOL.lock().unwrap();
+    let pool = demo::register_fixed_pool().unwrap();
+    assert_eq!(pool.buffer_len(), 8192);
+    let file = round_trip(&contents(100_000));
+
+    // Buffers are returned to the pool once the file has no data or IO in them.
+    let leased: Vec<_> = std::iter::from_fn(|| pool.lease()).collect();
+    assert_eq!(leased.len(), pool.count() as usize);
+    drop(file);
+}
+
+#[test]
+fn fall_back_when_pool_exhausted() {
+    let _guard = POOL.lock().unwrap();
+    let pool = demo::register_fixed_pool().unwrap();
+    let leased: Vec<_> = std::iter::from_fn(|| pool.lease()).collect();
+    assert!(pool.lease().is_none());
+
+    round_trip(&contents(50_000));
+
+    drop(leased);
+    assert!(pool.lease().is_some());
+}
@@ -0,0 +1,13 @@
+use ringbahn::drive::{demo, Drive};
+use ringbahn::fs::File;
+
+#[test]
+fn fixed_pool_is_opt_in() {
+    // IO handles do not register the demo driver's pool of fixed buffers unless it is asked for,
+    // so buffers can still be registered through the registrar.
+    drop(File::from(tempfile::tempfile().unwrap()));
+    assert!(demo::driver().fixed_pool().is_none());
+    let buffers = vec![vec![0; 4096].into_boxed_slice()];
+    let buffers = demo::registrar().unwrap().register_buffers(buffers).unwrap();
+    assert_eq!(buffers.count(), 1);
+}
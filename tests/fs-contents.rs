@@ -0,0 +1,54 @@
+use std::os::unix::fs::PermissionsExt;
+
+use futures::executor::block_on;
+
+use ringbahn::fs;
+
+fn contents(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+#[test]
+fn write_and_read() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    let expected = contents(100_000);
+
+    block_on(async {
+        fs::write(&path, &expected).await.unwrap();
+        assert!(fs::read(&path).await.unwrap() == expected);
+
+        // Writing again truncates the file.
+        fs::write(&path, "hello, world").await.unwrap();
+        assert_eq!(fs::read_to_string(&path).await.unwrap(), "hello, world");
+
+        fs::write(&path, [0xff, 0xfe]).await.unwrap();
+        let err = fs::read_to_string(&path).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        // Files which report no size are read to their end.
+        assert!(!fs::read("/proc/self/status").await.unwrap().is_empty());
+
+        let err = fs::read(dir.path().join("missing")).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    });
+}
+
+#[test]
+fn copy() {
+    let dir = tempfile::tempdir().unwrap();
+    let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+    let expected = contents(3 * 1024 * 1024 + 17);
+    std::fs::write(&from, &expected).unwrap();
+    std::fs::set_permissions(&from, std::fs::Permissions::from_mode(0o640)).unwrap();
+    std::fs::write(&to, contents(10_000_000)).unwrap();
+
+    block_on(async {
+        assert_eq!(fs::copy(&from, &to).await.unwrap(), expected.len() as u64);
+        assert!(std::fs::read(&to).unwrap() == expected);
+        assert_eq!(std::fs::metadata(&to).unwrap().permissions().mode() & 0o777, 0o640);
+
+        let err = fs::copy(dir.path(), &to).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    });
+}
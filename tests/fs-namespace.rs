@@ -0,0 +1,61 @@
+use futures::executor::block_on;
+
+use ringbahn::drive::{demo, Drive};
+use ringbahn::event::{RenameAt, RenameFlags};
+use ringbahn::fs;
+
+#[test]
+fn create_link_rename_and_remove() {
+    let dir = tempfile::tempdir().unwrap();
+    let sub = dir.path().join("sub");
+    let file = sub.join("file");
+    let hard = sub.join("hard");
+    let soft = sub.join("soft");
+    let renamed = sub.join("renamed");
+
+    block_on(async {
+        fs::create_dir(&sub).await.unwrap();
+        assert!(sub.is_dir());
+
+        std::fs::write(&file, b"contents").unwrap();
+        fs::hard_link(&file, &hard).await.unwrap();
+        fs::symlink("file", &soft).await.unwrap();
+        assert_eq!(std::fs::read(&hard).unwrap(), b"contents");
+        assert_eq!(std::fs::read_link(&soft).unwrap(), std::path::Path::new("file"));
+
+        fs::rename(&hard, &renamed).await.unwrap();
+        assert!(!hard.exists());
+        assert_eq!(std::fs::read(&renamed).unwrap(), b"contents");
+
+        let err = fs::remove_dir(&sub).await.unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ENOTEMPTY));
+
+        for path in &[&file, &soft, &renamed] {
+            fs::remove_file(path).await.unwrap();
+        }
+        fs::remove_dir(&sub).await.unwrap();
+        assert!(!sub.exists());
+    });
+}
+
+#[test]
+fn rename_flags() {
+    let dir = tempfile::tempdir().unwrap();
+    let a = dir.path().join("a");
+    let b = dir.path().join("b");
+    std::fs::write(&a, b"a").unwrap();
+    std::fs::write(&b, b"b").unwrap();
+
+    block_on(async {
+        let event = RenameAt::without_dir(&a, &b, RenameFlags::RENAME_NOREPLACE);
+        let (_, result) = demo::driver().submit(event).await;
+        assert_eq!(result.unwrap_err().raw_os_error(), Some(libc::EEXIST));
+
+        let event = RenameAt::without_dir(&a, &b, RenameFlags::RENAME_EXCHANGE);
+        let (_, result) = demo::driver().submit(event).await;
+        result.unwrap();
+    });
+
+    assert_eq!(std::fs::read(&a).unwrap(), b"b");
+    assert_eq!(std::fs::read(&b).unwrap(), b"a");
+}
@@ -0,0 +1,48 @@
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+use futures::executor::block_on;
+
+use ringbahn::fs::{self, File};
+
+#[test]
+fn metadata_matches_std() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    std::fs::write(&path, b"some contents").unwrap();
+    std::fs::set_permissions(&path, PermissionsExt::from_mode(0o640)).unwrap();
+    let expected = std::fs::metadata(&path).unwrap();
+
+    block_on(async {
+        let file = File::open(&path).await.unwrap();
+        for metadata in &[file.metadata().await.unwrap(), fs::metadata(&path).await.unwrap()] {
+            assert!(metadata.is_file());
+            assert!(!metadata.is_dir());
+            assert_eq!(metadata.len(), 13);
+            assert_eq!(metadata.permissions().mode(), 0o640);
+            assert_eq!(metadata.ino(), expected.ino());
+            assert_eq!(metadata.dev(), expected.dev());
+            assert_eq!(metadata.blocks(), expected.blocks());
+            assert_eq!(metadata.nlink(), 1);
+            assert_eq!(metadata.modified().unwrap(), expected.modified().unwrap());
+            assert_eq!(metadata.accessed().unwrap(), expected.accessed().unwrap());
+            assert!(metadata.mount_id().is_some());
+        }
+    });
+}
+
+#[test]
+fn symlink_metadata() {
+    let dir = tempfile::tempdir().unwrap();
+    let link = dir.path().join("link");
+    std::os::unix::fs::symlink(dir.path(), &link).unwrap();
+
+    block_on(async {
+        assert!(fs::metadata(&link).await.unwrap().is_dir());
+        let metadata = fs::symlink_metadata(&link).await.unwrap();
+        assert!(metadata.is_symlink());
+        assert!(metadata.file_type().is_symlink());
+
+        let err = fs::metadata(dir.path().join("missing")).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    });
+}
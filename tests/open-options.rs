@@ -0,0 +1,97 @@
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::io::AsRawFd;
+
+use futures::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use futures::executor::block_on;
+use futures::io::SeekFrom;
+
+use ringbahn::fs::{File, OpenOptions};
+
+#[test]
+fn read_write_append_and_create_new() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+
+    block_on(async {
+        let mut file = File::options().read(true).write(true).create_new(true).mode(0o600)
+            .open(&path).await.unwrap();
+        file.write_all(b"hello").await.unwrap();
+        file.seek(SeekFrom::Start(0)).await.unwrap();
+        let mut buf = String::new();
+        file.read_to_string(&mut buf).await.unwrap();
+        assert_eq!(buf, "hello");
+        file.close().await.unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+
+        let err = OpenOptions::new().write(true).create_new(true).open(&path).await.err().unwrap();
+        assert_eq!(err.raw_os_error(), Some(libc::EEXIST));
+
+        let mut file = OpenOptions::new().append(true).open(&path).await.unwrap();
+        file.write_all(b", world").await.unwrap();
+        file.close().await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"hello, world");
+    });
+}
+
+#[test]
+fn invalid_options_and_tmpfile() {
+    let dir = tempfile::tempdir().unwrap();
+
+    block_on(async {
+        for options in &[
+            OpenOptions::new(),
+            OpenOptions::new().read(true).create(true).clone(),
+            OpenOptions::new().append(true).truncate(true).clone(),
+            OpenOptions::new().read(true).tmpfile(true).clone(),
+        ] {
+            let err = options.open(dir.path().join("x")).await.err().unwrap();
+            assert_eq!(err.raw_os_error(), Some(libc::EINVAL), "{:?}", options);
+        }
+
+        let mut file = OpenOptions::new().write(true).tmpfile(true).open(dir.path()).await.unwrap();
+        file.write_all(b"unnamed").await.unwrap();
+        file.close().await.unwrap();
+    });
+
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn custom_flags_keep_sync_flags() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+
+    block_on(async {
+        let mut file = OpenOptions::new().write(true).create(true).dsync(true)
+            .custom_flags(libc::O_NOATIME).open(&path).await.unwrap();
+        let flags = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_GETFL) };
+        assert_ne!(flags & libc::O_DSYNC, 0);
+        file.close().await.unwrap();
+    });
+}
+
+#[test]
+fn append_moves_the_cursor_to_the_end() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    std::fs::write(&path, b"hello").unwrap();
+
+    block_on(async {
+        let mut file = OpenOptions::new().read(true).append(true).open(&path).await.unwrap();
+        file.write_all(b", world").await.unwrap();
+        assert_eq!(file.seek(SeekFrom::Current(0)).await.unwrap(), 12);
+
+        file.seek(SeekFrom::Start(0)).await.unwrap();
+        file.write_all(b"!").await.unwrap();
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf).await.unwrap();
+        assert!(buf.is_empty());
+        assert_eq!(file.seek(SeekFrom::Current(-13)).await.unwrap(), 0);
+        file.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, b"hello, world!");
+
+        let err = file.set_write_behind(2, 4096).await.err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        file.close().await.unwrap();
+    });
+}
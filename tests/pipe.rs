@@ -0,0 +1,47 @@
+use futures::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt};
+use futures::executor::block_on;
+
+use ringbahn::io;
+
+fn contents(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+#[test]
+fn read_and_write() {
+    let expected = contents(200_000);
+    let (mut reader, mut writer) = io::pipe().unwrap();
+
+    block_on(async {
+        let write = async {
+            writer.write_all(&expected).await.unwrap();
+            writer.close().await.unwrap();
+        };
+        let read = async {
+            let mut buf = vec![];
+            reader.read_to_end(&mut buf).await.unwrap();
+            buf
+        };
+        let ((), buf) = futures::join!(write, read);
+        assert!(buf == expected);
+    });
+}
+
+#[test]
+fn tee() {
+    let (mut a_reader, mut a_writer) = io::pipe().unwrap();
+    let (mut b_reader, b_writer) = io::pipe().unwrap();
+
+    block_on(async {
+        a_writer.write_all(b"hello, world").await.unwrap();
+        assert_eq!(a_reader.tee(&b_writer, 5).await.unwrap(), 5);
+        drop(b_writer);
+
+        let mut buf = vec![];
+        b_reader.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, b"hello");
+
+        // The data is still in the first pipe.
+        assert_eq!(a_reader.fill_buf().await.unwrap(), b"hello, world");
+    });
+}
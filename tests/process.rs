@@ -0,0 +1,52 @@
+use std::os::unix::process::ExitStatusExt;
+
+use futures::{AsyncReadExt, AsyncWriteExt};
+use futures::executor::block_on;
+
+use ringbahn::process::{Command, Stdio};
+
+#[test]
+fn piped_stdio() {
+    block_on(async {
+        let mut child = Command::new("cat")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut stdin = child.stdin.take().unwrap();
+        stdin.write_all(b"hello, child").await.unwrap();
+        stdin.close().await.unwrap();
+        let mut output = String::new();
+        child.stdout.as_mut().unwrap().read_to_string(&mut output).await.unwrap();
+        assert_eq!(output, "hello, child");
+        assert!(child.wait().await.unwrap().success());
+
+        let status = Command::new("sh").args(["-c", "exit 3"]).status().await.unwrap();
+        assert_eq!(status.code(), Some(3));
+
+        let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+        assert!(child.try_wait().unwrap().is_none());
+        child.kill().unwrap();
+        assert_eq!(child.wait().await.unwrap().signal(), Some(libc::SIGKILL));
+        // Waiting again returns the same status, and killing an exited child succeeds.
+        assert_eq!(child.wait().await.unwrap().signal(), Some(libc::SIGKILL));
+        child.kill().unwrap();
+    });
+}
+
+#[test]
+fn output() {
+    block_on(async {
+        let output = Command::new("sh")
+            .args(["-c", "head -c 100000 /dev/zero; echo err >&2; exit 2"])
+            .output()
+            .await
+            .unwrap();
+        assert_eq!(output.status.code(), Some(2));
+        assert!(output.stdout == vec![0; 100_000]);
+        assert_eq!(output.stderr, b"err\n");
+
+        let err = Command::new("/nonexistent").spawn().err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    });
+}
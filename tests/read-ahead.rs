@@ -0,0 +1,58 @@
+use futures::{AsyncReadExt, AsyncSeekExt};
+use futures::executor::block_on;
+use futures::io::SeekFrom;
+
+use ringbahn::fs::File;
+
+fn contents(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+#[test]
+fn sequential_read_ahead() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    let expected = contents(1 << 20);
+    std::fs::write(&path, &expected).unwrap();
+
+    block_on(async {
+        let mut file = File::open(&path).await.unwrap();
+        file.set_read_ahead(4, 16 * 1024);
+
+        let mut buf = vec![];
+        file.read_to_end(&mut buf).await.unwrap();
+        assert!(buf == expected);
+
+        // Seeking discards the data read ahead from the old position.
+        file.seek(SeekFrom::Start(1000)).await.unwrap();
+        let mut buf = vec![0; 100_000];
+        file.read_exact(&mut buf).await.unwrap();
+        assert!(buf[..] == expected[1000..101_000]);
+
+        // Turning read-ahead off keeps the cursor after the data which has been consumed.
+        file.set_read_ahead(0, 0);
+        let mut buf = vec![0; 10];
+        file.read_exact(&mut buf).await.unwrap();
+        assert!(buf[..] == expected[101_000..101_010]);
+    });
+}
+
+#[test]
+fn read_ahead_after_eof() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    std::fs::write(&path, contents(5000)).unwrap();
+
+    block_on(async {
+        let mut file = File::open(&path).await.unwrap();
+        file.set_read_ahead(3, 4096);
+
+        let mut buf = vec![];
+        assert_eq!(file.read_to_end(&mut buf).await.unwrap(), 5000);
+
+        std::fs::write(&path, contents(9000)).unwrap();
+        let mut buf = vec![];
+        assert_eq!(file.read_to_end(&mut buf).await.unwrap(), 4000);
+        assert!(buf[..] == contents(9000)[5000..]);
+    });
+}
@@ -6,6 +6,7 @@ use ringbahn::drive::{demo, Drive};
 use iou::sqe::*;
 
 #[test]
+#[allow(clippy::suspicious_to_owned)]
 fn test_registered_fd_ops() {
     // open and register file
     let file = std::fs::File::open("props.txt").unwrap();
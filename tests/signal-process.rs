@@ -0,0 +1,36 @@
+use futures::{AsyncReadExt, AsyncWriteExt, StreamExt};
+use futures::executor::block_on;
+
+use std::process::Command;
+
+use ringbahn::fs::{self, File};
+use ringbahn::signal;
+
+// IO started before subscribing to a signal spawns the crate's own threads, which must not take a
+// signal sent to the process before it reaches the signalfd.
+fn main() {
+    let dir = tempfile::tempdir().unwrap();
+    let (src, dst) = (dir.path().join("src"), dir.path().join("dst"));
+    block_on(async {
+        let mut file = File::create(&src).await.unwrap();
+        file.write_all(b"hello, world").await.unwrap();
+        file.close().await.unwrap();
+        // Copying sets the permissions of the copy on the blocking pool.
+        fs::copy(&src, &dst).await.unwrap();
+        let mut buf = vec![];
+        File::open(&dst).await.unwrap().read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, b"hello, world");
+    });
+
+    let mut term = signal::signals(&[libc::SIGTERM]).unwrap();
+    let pid = std::process::id().to_string();
+    let status = Command::new("sh").args(["-c", "kill -s TERM \"$1\"", "sh", &pid]).status();
+    assert!(status.unwrap().success());
+
+    block_on(async {
+        let info = term.next().await.unwrap().unwrap();
+        assert_eq!(info.signal(), libc::SIGTERM);
+        assert_eq!(info.code(), libc::SI_USER);
+        assert_ne!(info.pid(), std::process::id());
+    });
+}
@@ -0,0 +1,52 @@
+use futures::StreamExt;
+use futures::executor::block_on;
+
+use ringbahn::signal;
+
+// Signals sent to the process could be delivered to another thread of the test harness, so they
+// are sent to the thread which has blocked them.
+fn raise(signal: libc::c_int) {
+    assert_eq!(unsafe { libc::pthread_kill(libc::pthread_self(), signal) }, 0);
+}
+
+#[test]
+fn several_subscribers() {
+    let mut both = signal::signals(&[libc::SIGUSR1, libc::SIGUSR2]).unwrap();
+    let mut usr1 = signal::signals(&[libc::SIGUSR1]).unwrap();
+
+    block_on(async {
+        raise(libc::SIGUSR1);
+        let info = usr1.next().await.unwrap().unwrap();
+        assert_eq!(info.signal(), libc::SIGUSR1);
+        assert_eq!(info.code(), libc::SI_TKILL);
+        assert_eq!(info.pid(), std::process::id());
+
+        raise(libc::SIGUSR1);
+        raise(libc::SIGUSR2);
+        // Every subscriber receives its signals, whichever of them reads them.
+        assert_eq!(both.next().await.unwrap().unwrap().signal(), libc::SIGUSR1);
+        assert_eq!(both.next().await.unwrap().unwrap().signal(), libc::SIGUSR1);
+        assert_eq!(both.next().await.unwrap().unwrap().signal(), libc::SIGUSR2);
+        assert_eq!(usr1.next().await.unwrap().unwrap().signal(), libc::SIGUSR1);
+
+        // Subscribers can come and go while the signalfd is in use.
+        drop(usr1);
+        let mut hup = signal::signals(&[libc::SIGHUP]).unwrap();
+        // The signal arrives while the read is waiting for it.
+        let thread = unsafe { libc::pthread_self() };
+        let sender = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            assert_eq!(unsafe { libc::pthread_kill(thread, libc::SIGHUP) }, 0);
+        });
+        assert_eq!(hup.next().await.unwrap().unwrap().signal(), libc::SIGHUP);
+        sender.join().unwrap();
+    });
+}
+
+#[test]
+fn invalid_signals() {
+    for &signal in &[libc::SIGKILL, libc::SIGSTOP, 0, 1000] {
+        let err = signal::signals(&[signal]).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,77 @@
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::net::TcpListener;
+use std::os::unix::net::UnixStream;
+use std::thread;
+
+use futures::AsyncWriteExt;
+use futures::executor::block_on;
+
+use ringbahn::fs::File;
+use ringbahn::io;
+use ringbahn::net::TcpStream;
+
+fn contents(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+#[test]
+fn copy_zero_copy() {
+    let expected = contents(300_000);
+    let mut src = tempfile::tempfile().unwrap();
+    src.write_all(&expected).unwrap();
+    src.seek(SeekFrom::Start(0)).unwrap();
+    let mut dst = tempfile::tempfile().unwrap();
+
+    // The copy starts at the file's offset in the kernel.
+    assert_eq!(block_on(io::copy_zero_copy(&src, &dst)).unwrap(), 300_000);
+    assert_eq!(block_on(io::copy_zero_copy(&src, &dst)).unwrap(), 0);
+    dst.seek(SeekFrom::Start(0)).unwrap();
+    let mut buf = vec![];
+    dst.read_to_end(&mut buf).unwrap();
+    assert!(buf == expected);
+
+    // Sockets are read until they are shut down, however the data arrives.
+    let (mut a, b) = UnixStream::pair().unwrap();
+    let writer = thread::spawn(move || {
+        for chunk in expected.chunks(7_000) {
+            a.write_all(chunk).unwrap();
+        }
+    });
+    let mut dst = tempfile::tempfile().unwrap();
+    assert_eq!(block_on(io::copy_zero_copy(&b, &dst)).unwrap(), 300_000);
+    writer.join().unwrap();
+    let mut buf = vec![];
+    dst.seek(SeekFrom::Start(0)).unwrap();
+    dst.read_to_end(&mut buf).unwrap();
+    assert!(buf == contents(300_000));
+}
+
+#[test]
+fn send_file() {
+    let expected = contents(500_000);
+    let mut tmp = tempfile::tempfile().unwrap();
+    tmp.write_all(&expected).unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let reader = thread::spawn(move || {
+        let mut buf = vec![];
+        listener.accept().unwrap().0.read_to_end(&mut buf).unwrap();
+        buf
+    });
+
+    block_on(async {
+        let file = File::from(tmp);
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"header").await.unwrap();
+        assert_eq!(stream.send_file(&file, 1000, 300_000).await.unwrap(), 300_000);
+        // Sending stops at the end of the file.
+        assert_eq!(stream.send_file(&file, 450_000, 100_000).await.unwrap(), 50_000);
+        stream.close().await.unwrap();
+    });
+
+    let buf = reader.join().unwrap();
+    assert!(buf[..6] == b"header"[..]);
+    assert!(buf[6..300_006] == expected[1000..301_000]);
+    assert!(buf[300_006..] == expected[450_000..]);
+}
@@ -0,0 +1,56 @@
+use std::fs::File;
+use std::io::{Seek, SeekFrom, Write};
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::thread;
+
+use futures::{AsyncBufReadExt, AsyncReadExt, TryStreamExt};
+use futures::executor::block_on;
+
+use ringbahn::io;
+
+fn redirect_stdin(fd: &impl AsRawFd) {
+    assert_eq!(unsafe { libc::dup2(fd.as_raw_fd(), libc::STDIN_FILENO) }, libc::STDIN_FILENO);
+}
+
+// Both cases replace the process's standard input, so they run in one test.
+#[test]
+fn read_stdin() {
+    // A regular file is read from its offset, which is left after the last line consumed.
+    let mut file = tempfile::tempfile().unwrap();
+    file.write_all(b"skip\nfirst line\nsecond line\nrest").unwrap();
+    file.seek(SeekFrom::Start(5)).unwrap();
+    redirect_stdin(&file);
+
+    block_on(async {
+        let mut stdin = io::stdin();
+        let mut line = String::new();
+        assert_eq!(stdin.read_line(&mut line).await.unwrap(), 11);
+        assert_eq!(line, "first line\n");
+    });
+    assert_eq!(file.stream_position().unwrap(), 16);
+
+    block_on(async {
+        let rest: Vec<String> = io::stdin().lines().try_collect().await.unwrap();
+        assert_eq!(rest, ["second line", "rest"]);
+    });
+
+    // A pipe is read until every write end is closed.
+    let mut fds = [0; 2];
+    assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+    let (reader, mut writer) = unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) };
+    redirect_stdin(&reader);
+    drop(reader);
+    let writer = thread::spawn(move || {
+        for i in 0..1000 {
+            writeln!(writer, "line {}", i).unwrap();
+        }
+    });
+
+    block_on(async {
+        let mut buf = String::new();
+        io::stdin().read_to_string(&mut buf).await.unwrap();
+        let expected: String = (0..1000).map(|i| format!("line {}\n", i)).collect();
+        assert_eq!(buf, expected);
+    });
+    writer.join().unwrap();
+}
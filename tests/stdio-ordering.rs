@@ -0,0 +1,120 @@
+use std::io::{Read, Seek, SeekFrom};
+use std::os::unix::io::AsRawFd;
+use std::sync::Mutex;
+use std::task::Context;
+use std::thread;
+
+use futures::{AsyncWrite, AsyncWriteExt};
+use futures::executor::block_on;
+
+use ringbahn::io;
+
+// The tests redirect the process's standard output and error one at a time.
+static CAPTURE: Mutex<()> = Mutex::new(());
+
+// Run `f` with `fd` redirected to a temporary file, returning what was written to it.
+fn capture(fd: libc::c_int, f: impl FnOnce()) -> String {
+    let _guard = CAPTURE.lock().unwrap_or_else(|err| err.into_inner());
+    let mut file = tempfile::tempfile().unwrap();
+    let saved = unsafe { libc::dup(fd) };
+    assert_eq!(unsafe { libc::dup2(file.as_raw_fd(), fd) }, fd);
+    f();
+    assert_eq!(unsafe { libc::dup2(saved, fd) }, fd);
+    unsafe { libc::close(saved); }
+    let mut output = String::new();
+    file.seek(SeekFrom::Start(0)).unwrap();
+    file.read_to_string(&mut output).unwrap();
+    output
+}
+
+#[test]
+fn lines_are_not_interleaved() {
+    let output = capture(libc::STDOUT_FILENO, || {
+        let threads: Vec<_> = (0..8).map(|t| thread::spawn(move || block_on(async move {
+            let mut stdout = io::stdout();
+            for i in 0..200 {
+                // Each line is written in pieces, which are buffered until it is complete.
+                stdout.write_all(format!("thread {} ", t).as_bytes()).await.unwrap();
+                stdout.write_all(format!("line {}\nthread {} ", i, t).as_bytes()).await.unwrap();
+                stdout.write_all(b"again\n").await.unwrap();
+            }
+            ringbahn::println!(ringbahn::drive::demo::driver(), "thread {} done", t).await;
+        }))).collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    });
+
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 8 * 401);
+    for t in 0..8 {
+        let prefix = format!("thread {} ", t);
+        let mine: Vec<&str> = lines.iter().filter(|l| l.starts_with(&prefix)).copied().collect();
+        let mut expected = vec![];
+        for i in 0..200 {
+            expected.push(format!("thread {} line {}", t, i));
+            expected.push(format!("thread {} again", t));
+        }
+        expected.push(format!("thread {} done", t));
+        assert_eq!(mine, expected);
+    }
+}
+
+#[test]
+fn lock_batches_output() {
+    let output = capture(libc::STDERR_FILENO, || {
+        let threads: Vec<_> = (0..4).map(|t| thread::spawn(move || block_on(async move {
+            let mut stderr = io::stderr();
+            for _ in 0..20 {
+                let mut lock = stderr.lock().await;
+                for i in 0..10 {
+                    lock.write_all(format!("{} {}\n", t, i).as_bytes()).await.unwrap();
+                }
+                lock.flush().await.unwrap();
+            }
+        }))).collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    });
+
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 4 * 20 * 10);
+    for batch in lines.chunks(10) {
+        let t = batch[0].split(' ').next().unwrap();
+        let expected: Vec<String> = (0..10).map(|i| format!("{} {}", t, i)).collect();
+        assert_eq!(batch, &expected[..]);
+    }
+}
+
+#[test]
+fn dropped_partial_line_waits_for_the_lock() {
+    let output = capture(libc::STDERR_FILENO, || block_on(async {
+        let mut stderr = io::stderr();
+        let mut lock = stderr.lock().await;
+        let mut other = io::stderr();
+        other.write_all(b"partial").await.unwrap();
+        drop(other);
+        lock.write_all(b"locked\n").await.unwrap();
+    }));
+    assert_eq!(output, "locked\npartial");
+}
+
+#[test]
+fn flush_does_not_forget_accepted_bytes() {
+    let output = capture(libc::STDERR_FILENO, || block_on(async {
+        let mut stderr = io::stderr();
+        let lock = stderr.lock().await;
+        let mut other = Box::pin(io::stderr());
+        let waker = futures::task::noop_waker();
+        let pending = other.as_mut().poll_write(&mut Context::from_waker(&waker), b"line\n");
+        assert!(pending.is_pending());
+        drop(lock);
+
+        // The line is written by the flush, and then returned by the retried write.
+        other.flush().await.unwrap();
+        assert_eq!(other.write(b"line\n").await.unwrap(), 5);
+        other.flush().await.unwrap();
+    }));
+    assert_eq!(output, "line\n");
+}
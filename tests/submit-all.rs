@@ -0,0 +1,102 @@
+use std::fs::File;
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use futures::StreamExt;
+use iou::{IoUring, SQEs};
+
+use ringbahn::drive::{self, demo, Completion, Drive};
+use ringbahn::event::Read;
+
+const ASSERT: &[u8] = b"But this formidable power of death -";
+
+fn reads(file: &File) -> impl Iterator<Item = Read> + '_ {
+    (0..ASSERT.len() as u64).step_by(4).map(move |offset| Read {
+        fd: file.as_raw_fd(),
+        buf: vec![0; 4].into(),
+        offset,
+    })
+}
+
+#[test]
+fn join_in_submission_order() {
+    let file = File::open("props.txt").unwrap();
+    let results = futures::executor::block_on(demo::driver().submit_all(reads(&file)).join());
+    let results = results.unwrap();
+
+    assert_eq!(results.len(), ASSERT.len() / 4);
+    for (n, (read, result)) in results.into_iter().enumerate() {
+        let offset = read.offset as usize;
+        assert_eq!(offset, n * 4);
+        assert_eq!(result.unwrap(), 4);
+        assert_eq!(&read.buf[..], &ASSERT[offset..offset + 4]);
+    }
+}
+
+#[test]
+fn stream_in_completion_order() {
+    let file = File::open("props.txt").unwrap();
+    let mut offsets: Vec<_> = futures::executor::block_on(async {
+        demo::driver().submit_all(reads(&file)).map(|item| {
+            let (read, result) = item.unwrap();
+            let offset = read.offset as usize;
+            assert_eq!(&read.buf[..result.unwrap() as usize], &ASSERT[offset..offset + 4]);
+            offset
+        }).collect().await
+    });
+
+    offsets.sort_unstable();
+    assert_eq!(offsets, (0..ASSERT.len()).step_by(4).collect::<Vec<_>>());
+}
+
+/// A driver which fails to submit once, and then submits and completes events as it is asked to.
+#[derive(Clone)]
+struct Interrupted(Arc<Mutex<(IoUring, bool)>>);
+
+impl Drive for Interrupted {
+    fn poll_prepare<'cx>(
+        self: Pin<&mut Self>,
+        ctx: &mut Context<'cx>,
+        count: u32,
+        prepare: impl FnOnce(SQEs<'_>, &mut Context<'cx>) -> Completion<'cx>,
+    ) -> Poll<Completion<'cx>> {
+        let mut ring = self.0.lock().unwrap();
+        Poll::Ready(prepare(ring.0.prepare_sqes(count).unwrap(), ctx))
+    }
+
+    fn poll_submit(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<u32>> {
+        let (ring, failed) = &mut *self.0.lock().unwrap();
+        if !*failed {
+            *failed = true;
+            return Poll::Ready(Err(io::Error::from_raw_os_error(libc::EINTR)));
+        }
+        let n = ring.submit_sqes()?;
+        for _ in 0..n {
+            drive::complete(ring.wait_for_cqe()?);
+        }
+        Poll::Ready(Ok(n))
+    }
+}
+
+#[test]
+fn submit_errors_are_returned() {
+    let file = File::open("props.txt").unwrap();
+    let driver = Interrupted(Arc::new(Mutex::new((IoUring::new(32).unwrap(), false))));
+    let results = futures::executor::block_on(driver.clone().submit_all(reads(&file)).join());
+    assert_eq!(results.err().unwrap().kind(), io::ErrorKind::Interrupted);
+
+    // the stream yields the error, and then submits its events along with the cancelled ones
+    driver.0.lock().unwrap().1 = false;
+    let results: Vec<_> = futures::executor::block_on(driver.submit_all(reads(&file)).collect());
+    assert_eq!(results[0].as_ref().err().unwrap().kind(), io::ErrorKind::Interrupted);
+    assert_eq!(results.len(), ASSERT.len() / 4 + 1);
+    for result in &results[1..] {
+        let (read, result) = result.as_ref().unwrap();
+        let offset = read.offset as usize;
+        assert_eq!(result.as_ref().unwrap(), &4);
+        assert_eq!(&read.buf[..], &ASSERT[offset..offset + 4]);
+    }
+}
@@ -0,0 +1,85 @@
+use futures::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use futures::executor::block_on;
+use futures::io::SeekFrom;
+
+use ringbahn::fs::File;
+
+#[test]
+fn write_behind() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    let expected: Vec<u8> = (0..300_000).map(|i| (i % 251) as u8).collect();
+
+    block_on(async {
+        let mut file = File::options().read(true).write(true).create(true).open(&path).await.unwrap();
+        file.set_write_behind(4, 8192).await.unwrap();
+
+        // Many small writes are coalesced into larger ones.
+        for chunk in expected.chunks(1000) {
+            file.write_all(chunk).await.unwrap();
+        }
+        file.flush().await.unwrap();
+        assert!(std::fs::read(&path).unwrap() == expected);
+
+        // Writing after a seek waits for the writes in flight, then overwrites their data.
+        file.write_all(&[1; 5000]).await.unwrap();
+        file.seek(SeekFrom::Start(10)).await.unwrap();
+        file.write_all(&[2; 20]).await.unwrap();
+
+        // Reading writes out the buffered data first.
+        file.seek(SeekFrom::Start(0)).await.unwrap();
+        let mut buf = vec![];
+        file.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf.len(), 305_000);
+        assert!(buf[..10] == expected[..10]);
+        assert!(buf[10..30] == [2; 20]);
+        assert!(buf[30..300_000] == expected[30..]);
+        assert!(buf[300_000..] == [1; 5000][..]);
+
+        file.close().await.unwrap();
+    });
+}
+
+#[test]
+fn write_behind_error() {
+    block_on(async {
+        // Writes to a file opened read-only fail, but are accepted into the buffer.
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let mut file = File::open(file.path()).await.unwrap();
+        file.set_write_behind(2, 16).await.unwrap();
+        assert_eq!(file.write(&[0; 10]).await.unwrap(), 10);
+        let err = file.flush().await.unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EBADF));
+        file.flush().await.unwrap();
+    });
+}
+
+#[test]
+fn write_behind_and_positional_writes() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+
+    block_on(async {
+        let mut file = File::options().read(true).write(true).create(true)
+            .open(&path).await.unwrap();
+        file.set_write_behind(2, 16).await.unwrap();
+
+        // Positional IO would bypass the data buffered by write-behind.
+        file.write_all(b"0123456789").await.unwrap();
+        assert!(file.write_at(b"abc", 2).await.is_err());
+        assert!(file.read_at(&mut [0; 10], 0).await.is_err());
+        assert!(file.metadata().await.is_err());
+
+        file.flush().await.unwrap();
+        let mut buf = [0; 10];
+        assert_eq!(file.read_at(&mut buf, 0).await.unwrap(), 10);
+        assert_eq!(&buf, b"0123456789");
+        file.write_all_at(b"abc", 2).await.unwrap();
+        file.write_all(b"xyz").await.unwrap();
+        file.flush().await.unwrap();
+        assert_eq!(file.metadata().await.unwrap().len(), 13);
+        file.close().await.unwrap();
+    });
+
+    assert_eq!(std::fs::read(&path).unwrap(), b"01abc56789xyz");
+}